@@ -0,0 +1,134 @@
+//! Date-aligned comparison of several products' price histories
+
+use crate::api::PriceHistory;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One date with each product's `min` price (`None` when a product has no data that day)
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonRow {
+    pub date: NaiveDate,
+    pub prices: Vec<Option<f64>>,
+}
+
+impl ComparisonRow {
+    /// Indices of the products with the lowest price on this date
+    pub fn cheapest(&self) -> Vec<usize> {
+        let Some(best) = self.prices.iter().flatten().copied().reduce(f64::min) else {
+            return Vec::new();
+        };
+        self.prices
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_some_and(|p| (p - best).abs() < f64::EPSILON))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Per-product summary over the compared window
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonSummary {
+    pub product_id: u64,
+    pub lowest: Option<f64>,
+    pub latest: Option<f64>,
+    /// Days on which this product was the cheapest (ties count for every tied product)
+    pub cheapest_days: usize,
+}
+
+/// Price histories of several products aligned on date
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    pub product_ids: Vec<u64>,
+    pub rows: Vec<ComparisonRow>,
+    pub summary: Vec<ComparisonSummary>,
+}
+
+impl Comparison {
+    /// Align histories on date, one column per product in the given order
+    pub fn new(histories: &[(u64, PriceHistory)]) -> Self {
+        let mut by_date: BTreeMap<NaiveDate, Vec<Option<f64>>> = BTreeMap::new();
+        for (col, (_, history)) in histories.iter().enumerate() {
+            for point in &history.data {
+                by_date.entry(point.date).or_insert_with(|| vec![None; histories.len()])[col] =
+                    Some(point.min);
+            }
+        }
+
+        let rows: Vec<ComparisonRow> =
+            by_date.into_iter().map(|(date, prices)| ComparisonRow { date, prices }).collect();
+
+        let mut summary: Vec<ComparisonSummary> = histories
+            .iter()
+            .enumerate()
+            .map(|(col, (product_id, _))| {
+                let mut column = rows.iter().filter_map(|r| r.prices[col]);
+                ComparisonSummary {
+                    product_id: *product_id,
+                    lowest: column.clone().reduce(f64::min),
+                    latest: column.next_back(),
+                    cheapest_days: 0,
+                }
+            })
+            .collect();
+
+        for row in &rows {
+            for col in row.cheapest() {
+                summary[col].cheapest_days += 1;
+            }
+        }
+
+        Self { product_ids: histories.iter().map(|(id, _)| *id).collect(), rows, summary }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::PricePoint;
+
+    fn history(points: &[(&str, f64)]) -> PriceHistory {
+        PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: points
+                .iter()
+                .map(|&(date, min)| PricePoint { date: date.parse().unwrap(), avg: min, min })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn aligns_on_date() {
+        let a = history(&[("2024-01-01", 10.0), ("2024-01-02", 9.0)]);
+        let b = history(&[("2024-01-02", 8.0), ("2024-01-03", 7.0)]);
+        let cmp = Comparison::new(&[(1, a), (2, b)]);
+
+        assert_eq!(cmp.product_ids, vec![1, 2]);
+        assert_eq!(cmp.rows.len(), 3);
+        assert_eq!(cmp.rows[0].prices, vec![Some(10.0), None]);
+        assert_eq!(cmp.rows[1].prices, vec![Some(9.0), Some(8.0)]);
+        assert_eq!(cmp.rows[2].prices, vec![None, Some(7.0)]);
+    }
+
+    #[test]
+    fn summary_counts_cheapest_days() {
+        let a = history(&[("2024-01-01", 10.0), ("2024-01-02", 9.0), ("2024-01-03", 5.0)]);
+        let b = history(&[("2024-01-01", 11.0), ("2024-01-02", 9.0), ("2024-01-03", 6.0)]);
+        let cmp = Comparison::new(&[(1, a), (2, b)]);
+
+        assert_eq!(cmp.summary[0].cheapest_days, 3);
+        assert_eq!(cmp.summary[1].cheapest_days, 1);
+        assert_eq!(cmp.summary[0].lowest, Some(5.0));
+        assert_eq!(cmp.summary[1].latest, Some(6.0));
+    }
+
+    #[test]
+    fn cheapest_empty_row() {
+        let row = ComparisonRow { date: "2024-01-01".parse().unwrap(), prices: vec![None, None] };
+        assert!(row.cheapest().is_empty());
+    }
+}
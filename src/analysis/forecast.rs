@@ -0,0 +1,430 @@
+//! Deterministic short-term price forecasts for the `min` series
+
+use super::resample::fill_gaps;
+use super::stats::Signal;
+use crate::api::PriceHistory;
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+/// Window of the moving-average model, in days
+const MA_WINDOW: usize = 7;
+
+/// z-score for the 95% confidence band
+const Z_95: f64 = 1.96;
+
+/// Minimum expected saving (as a share of the current price) for a "wait" signal
+const WAIT_THRESHOLD: f64 = 0.01;
+
+/// Forecasting model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Model {
+    /// Mean of the last week, held flat
+    MovingAverage,
+    /// Holt's linear exponential smoothing (level + trend)
+    ExponentialSmoothing,
+    /// Least-squares line over the whole window
+    LinearTrend,
+}
+
+impl Model {
+    pub const ALL: [Self; 3] = [Self::MovingAverage, Self::ExponentialSmoothing, Self::LinearTrend];
+
+    /// Human-readable model name
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::MovingAverage => "Moving average",
+            Self::ExponentialSmoothing => "Exp. smoothing",
+            Self::LinearTrend => "Linear trend",
+        }
+    }
+
+    /// Predict the next `horizon` values after `series`
+    fn predict(self, series: &[f64], horizon: usize) -> Vec<f64> {
+        let raw = match self {
+            Self::MovingAverage => {
+                let window = &series[series.len().saturating_sub(MA_WINDOW)..];
+                let mean = window.iter().sum::<f64>() / window.len() as f64;
+                vec![mean; horizon]
+            }
+            Self::ExponentialSmoothing => {
+                let (level, trend) = holt_fit(series);
+                (1..=horizon).map(|k| trend.mul_add(k as f64, level)).collect()
+            }
+            Self::LinearTrend => {
+                let (intercept, slope) = linear_fit(series);
+                let n = series.len() as f64;
+                (0..horizon).map(|k| slope.mul_add(n + k as f64, intercept)).collect()
+            }
+        };
+        raw.into_iter().map(|v| v.max(0.0)).collect()
+    }
+
+    /// One-step-ahead predictions after every prefix of at least two points,
+    /// i.e. `predict(&series[..i], 1)` for `i` in `2..series.len()`, in one pass
+    fn one_step(self, series: &[f64]) -> Vec<f64> {
+        if series.len() < 3 {
+            return Vec::new();
+        }
+        let raw: Vec<f64> = match self {
+            Self::MovingAverage => (2..series.len())
+                .map(|i| {
+                    let window = &series[i.saturating_sub(MA_WINDOW)..i];
+                    window.iter().sum::<f64>() / window.len() as f64
+                })
+                .collect(),
+            Self::ExponentialSmoothing => holt_one_step(series),
+            Self::LinearTrend => {
+                let (mut total, mut weighted) = (series[0] + series[1], series[1]);
+                (2..series.len())
+                    .map(|i| {
+                        let n = i as f64;
+                        let (mean_x, mean_y) = ((n - 1.0) / 2.0, total / n);
+                        let den = n * n.mul_add(n, -1.0) / 12.0;
+                        let slope = n.mul_add(-mean_x * mean_y, weighted) / den;
+                        total += series[i];
+                        weighted = n.mul_add(series[i], weighted);
+                        slope.mul_add(n - mean_x, mean_y)
+                    })
+                    .collect()
+            }
+        };
+        raw.into_iter().map(|v| v.max(0.0)).collect()
+    }
+}
+
+/// A forecast value with its confidence band
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastPoint {
+    pub date: NaiveDate,
+    pub value: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Forecast and backtest error of a single model
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelForecast {
+    pub model: Model,
+    pub points: Vec<ForecastPoint>,
+    /// Mean absolute error when forecasting the held-out tail of the window
+    pub mae: Option<f64>,
+    /// Mean absolute percentage error on the same backtest
+    pub mape: Option<f64>,
+}
+
+/// Forecasts of all models for one product
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Forecast {
+    pub product_id: u64,
+    pub horizon: usize,
+    pub last_date: NaiveDate,
+    pub current: f64,
+    pub models: Vec<ModelForecast>,
+    /// Model with the lowest backtest error
+    pub best: Model,
+    /// Lowest price predicted by the best model over the horizon
+    pub expected_low: f64,
+    pub expected_saving: f64,
+    pub signal: Signal,
+}
+
+impl Forecast {
+    /// Fit all models on a history; `None` if there are fewer than 3 points
+    pub fn compute(product_id: u64, history: &PriceHistory, horizon: usize) -> Option<Self> {
+        let daily = fill_gaps(history);
+        let last_date = daily.data.last()?.date;
+        let series: Vec<f64> = daily.data.iter().map(|p| p.min).collect();
+        if series.len() < 3 || horizon == 0 {
+            return None;
+        }
+        let current = series[series.len() - 1];
+
+        let models: Vec<ModelForecast> =
+            Model::ALL.iter().map(|&m| forecast_model(m, &series, horizon, last_date)).collect();
+
+        let best = models
+            .iter()
+            .min_by(|a, b| {
+                a.mae.unwrap_or(f64::INFINITY).total_cmp(&b.mae.unwrap_or(f64::INFINITY))
+            })
+            .map_or(Model::MovingAverage, |m| m.model);
+
+        let expected_low = models
+            .iter()
+            .find(|m| m.model == best)
+            .and_then(|m| m.points.iter().map(|p| p.value).reduce(f64::min))
+            .unwrap_or(current);
+        let expected_saving = current - expected_low;
+
+        let signal =
+            if expected_saving > current * WAIT_THRESHOLD { Signal::Wait } else { Signal::Buy };
+
+        Some(Self {
+            product_id,
+            horizon,
+            last_date,
+            current,
+            models,
+            best,
+            expected_low,
+            expected_saving,
+            signal,
+        })
+    }
+
+    /// Forecast of the best model
+    pub fn best_forecast(&self) -> Option<&ModelForecast> {
+        self.models.iter().find(|m| m.model == self.best)
+    }
+}
+
+fn forecast_model(
+    model: Model,
+    series: &[f64],
+    horizon: usize,
+    last_date: NaiveDate,
+) -> ModelForecast {
+    let sigma = residual_std(model, series);
+    let points = model
+        .predict(series, horizon)
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let step = (i + 1) as f64;
+            let spread = Z_95 * sigma * step.sqrt();
+            ForecastPoint {
+                date: last_date + Days::new(i as u64 + 1),
+                value,
+                lower: (value - spread).max(0.0),
+                upper: value + spread,
+            }
+        })
+        .collect();
+
+    let errors = backtest(model, series, horizon);
+
+    ModelForecast { model, points, mae: errors.map(|e| e.0), mape: errors.and_then(|e| e.1) }
+}
+
+/// Fit on the head of the series, forecast the tail, and measure the error;
+/// the percentage error skips zero actuals and is `None` if all are zero
+fn backtest(model: Model, series: &[f64], horizon: usize) -> Option<(f64, Option<f64>)> {
+    let holdout = horizon.min(series.len() / 3);
+    if holdout == 0 {
+        return None;
+    }
+
+    let (train, test) = series.split_at(series.len() - holdout);
+    let predicted = model.predict(train, holdout);
+
+    let n = holdout as f64;
+    let mean_abs = test.iter().zip(&predicted).map(|(a, p)| (a - p).abs()).sum::<f64>() / n;
+    let pct: Vec<f64> = test
+        .iter()
+        .zip(&predicted)
+        .filter(|(a, _)| **a > 0.0)
+        .map(|(a, p)| (a - p).abs() / a * 100.0)
+        .collect();
+    let mean_pct = (!pct.is_empty()).then(|| pct.iter().sum::<f64>() / pct.len() as f64);
+
+    Some((mean_abs, mean_pct))
+}
+
+/// Standard deviation of one-step-ahead in-sample errors
+fn residual_std(model: Model, series: &[f64]) -> f64 {
+    let errors: Vec<f64> =
+        model.one_step(series).iter().zip(&series[2..]).map(|(p, y)| y - p).collect();
+    if errors.is_empty() {
+        return 0.0;
+    }
+    (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt()
+}
+
+/// Least-squares line `(intercept, slope)` over indices `0..n`
+fn linear_fit(series: &[f64]) -> (f64, f64) {
+    let n = series.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = series.iter().sum::<f64>() / n;
+
+    let (num, den) = series.iter().enumerate().fold((0.0, 0.0), |(num, den), (i, &y)| {
+        let dx = i as f64 - mean_x;
+        (dx.mul_add(y - mean_y, num), dx.mul_add(dx, den))
+    });
+
+    let slope = if den == 0.0 { 0.0 } else { num / den };
+    (slope.mul_add(-mean_x, mean_y), slope)
+}
+
+/// Smoothing factors tried by the Holt grid search
+const HOLT_GRID: [f64; 9] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+
+/// Holt's linear method with smoothing factors chosen by grid search on in-sample SSE
+fn holt_fit(series: &[f64]) -> (f64, f64) {
+    let mut runs = holt_runs(series);
+    for &y in &series[1..] {
+        for run in &mut runs {
+            run.step(y);
+        }
+    }
+    holt_best(&runs).map_or((series[series.len() - 1], 0.0), |run| (run.level, run.trend))
+}
+
+/// [`holt_fit`] after every prefix of at least two points, as one-step-ahead
+/// predictions; all grid pairs advance side by side in a single pass
+fn holt_one_step(series: &[f64]) -> Vec<f64> {
+    let mut runs = holt_runs(series);
+    let mut predictions = Vec::with_capacity(series.len().saturating_sub(2));
+    for &y in &series[1..series.len() - 1] {
+        for run in &mut runs {
+            run.step(y);
+        }
+        let (level, trend) = holt_best(&runs).map_or((y, 0.0), |run| (run.level, run.trend));
+        predictions.push(level + trend);
+    }
+    predictions
+}
+
+/// State of Holt's method for one pair of smoothing factors
+#[derive(Debug, Clone, Copy)]
+struct HoltRun {
+    alpha: f64,
+    beta: f64,
+    level: f64,
+    trend: f64,
+    sse: f64,
+}
+
+impl HoltRun {
+    /// Fold `y` into the level and trend, adding its one-step error to the SSE
+    fn step(&mut self, y: f64) {
+        let predicted = self.level + self.trend;
+        self.sse += (y - predicted).powi(2);
+        let prev_level = self.level;
+        self.level = self.alpha.mul_add(y, (1.0 - self.alpha) * predicted);
+        self.trend = self.beta.mul_add(self.level - prev_level, (1.0 - self.beta) * self.trend);
+    }
+}
+
+/// A run per grid pair, initialised from the first two points of `series`
+fn holt_runs(series: &[f64]) -> Vec<HoltRun> {
+    let level = series[0];
+    let trend = series.get(1).map_or(0.0, |second| second - series[0]);
+    HOLT_GRID
+        .iter()
+        .flat_map(|&alpha| {
+            HOLT_GRID.iter().map(move |&beta| HoltRun { alpha, beta, level, trend, sse: 0.0 })
+        })
+        .collect()
+}
+
+/// Run with the lowest SSE, the first one on ties
+fn holt_best(runs: &[HoltRun]) -> Option<&HoltRun> {
+    runs.iter().filter(|run| run.sse < f64::INFINITY).min_by(|a, b| a.sse.total_cmp(&b.sse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::PricePoint;
+
+    fn history(prices: &[f64]) -> PriceHistory {
+        let start: NaiveDate = "2024-01-01".parse().unwrap();
+        PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: prices
+                .iter()
+                .enumerate()
+                .map(|(i, &min)| PricePoint { date: start + Days::new(i as u64), avg: min, min })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compute_requires_three_points() {
+        assert!(Forecast::compute(1, &history(&[10.0, 9.0]), 7).is_none());
+        assert!(Forecast::compute(1, &history(&[10.0, 9.0, 8.0]), 0).is_none());
+    }
+
+    #[test]
+    fn linear_trend_extrapolates() {
+        let forecast =
+            Forecast::compute(1, &history(&[100.0, 98.0, 96.0, 94.0, 92.0, 90.0]), 3).unwrap();
+        let linear = forecast.models.iter().find(|m| m.model == Model::LinearTrend).unwrap();
+
+        assert_eq!(linear.points.len(), 3);
+        assert!((linear.points[0].value - 88.0).abs() < 1e-9);
+        assert!((linear.points[2].value - 84.0).abs() < 1e-9);
+        assert_eq!(linear.points[0].date.to_string(), "2024-01-07");
+        assert!(linear.mae.unwrap() < 1e-9);
+    }
+
+    #[test]
+    fn falling_prices_signal_wait() {
+        let prices: Vec<f64> = (0..30).map(|i| f64::from(i).mul_add(-2.0, 500.0)).collect();
+        let forecast = Forecast::compute(1, &history(&prices), 14).unwrap();
+
+        assert_ne!(forecast.best, Model::MovingAverage);
+        assert!(forecast.expected_low < forecast.current);
+        assert_eq!(forecast.signal, Signal::Wait);
+    }
+
+    #[test]
+    fn flat_prices_signal_buy() {
+        let forecast = Forecast::compute(1, &history(&[50.0; 20]), 14).unwrap();
+
+        assert!(forecast.expected_saving.abs() < 1e-9);
+        assert_eq!(forecast.signal, Signal::Buy);
+        let best = forecast.best_forecast().unwrap();
+        assert!((best.points[0].upper - best.points[0].lower).abs() < 1e-9);
+    }
+
+    #[test]
+    fn confidence_band_widens() {
+        let prices = [100.0, 104.0, 98.0, 103.0, 97.0, 105.0, 99.0, 102.0];
+        let forecast = Forecast::compute(1, &history(&prices), 5).unwrap();
+
+        for m in &forecast.models {
+            let first = m.points[0].upper - m.points[0].lower;
+            let last = m.points[4].upper - m.points[4].lower;
+            assert!(last > first, "{:?}", m.model);
+            assert!(m.points.iter().all(|p| p.lower <= p.value && p.value <= p.upper));
+        }
+    }
+
+    #[test]
+    fn one_step_matches_refitting() {
+        let prices = [100.0, 104.0, 98.0, 103.0, 97.0, 105.0, 99.0, 102.0, 96.0, 101.0];
+        for model in Model::ALL {
+            let refit: Vec<f64> =
+                (2..prices.len()).map(|i| model.predict(&prices[..i], 1)[0]).collect();
+            let one_step = model.one_step(&prices);
+            assert_eq!(one_step.len(), refit.len());
+            for (a, b) in one_step.iter().zip(&refit) {
+                assert!((a - b).abs() < 1e-9, "{model:?}: {a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn mape_skips_zero_actuals() {
+        let errors =
+            backtest(Model::MovingAverage, &[10.0, 10.0, 10.0, 10.0, 0.0, 20.0], 2).unwrap();
+        assert!((errors.0 - 10.0).abs() < 1e-9);
+        assert!((errors.1.unwrap() - 50.0).abs() < 1e-9);
+        assert!(backtest(Model::MovingAverage, &[10.0, 10.0, 10.0, 10.0, 0.0, 0.0], 2)
+            .unwrap()
+            .1
+            .is_none());
+    }
+
+    #[test]
+    fn forecast_is_deterministic() {
+        let prices = [100.0, 104.0, 98.0, 103.0, 97.0, 105.0, 99.0, 102.0];
+        let a = serde_json::to_string(&Forecast::compute(1, &history(&prices), 5)).unwrap();
+        let b = serde_json::to_string(&Forecast::compute(1, &history(&prices), 5)).unwrap();
+        assert_eq!(a, b);
+    }
+}
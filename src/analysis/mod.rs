@@ -0,0 +1,11 @@
+//! Price analytics built on top of API data
+
+pub mod compare;
+pub mod forecast;
+pub mod resample;
+pub mod stats;
+
+pub use compare::{Comparison, ComparisonRow, ComparisonSummary};
+pub use forecast::{Forecast, ForecastPoint, Model, ModelForecast};
+pub use resample::Period;
+pub use stats::{PriceMove, PriceStats, Signal};
@@ -0,0 +1,214 @@
+//! Date-range selection, gap filling and resampling of price history
+
+use crate::api::{PriceHistory, PricePoint};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Resampling period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    /// ISO weeks, starting on Monday
+    Week,
+    /// Calendar months
+    Month,
+}
+
+impl Period {
+    /// First day of the period containing `date`
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Days::new(u64::from(date.weekday().num_days_from_monday())),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// Same day one period earlier
+    pub fn before(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Week => date - Days::new(7),
+            Self::Month => date.checked_sub_months(Months::new(1)).unwrap_or(date),
+        }
+    }
+
+    /// Lowercase adjective used in headers ("weekly", "monthly")
+    pub const fn adjective(self) -> &'static str {
+        match self {
+            Self::Week => "weekly",
+            Self::Month => "monthly",
+        }
+    }
+
+    /// Lowercase noun ("week", "month")
+    pub const fn noun(self) -> &'static str {
+        match self {
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+}
+
+/// Keep only points within `since..=until` (either bound may be open)
+pub fn select_range(
+    history: &PriceHistory,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> PriceHistory {
+    let data = history
+        .data
+        .iter()
+        .filter(|p| since.map_or(true, |s| p.date >= s) && until.map_or(true, |u| p.date <= u))
+        .cloned()
+        .collect();
+
+    PriceHistory { data, ..*history }
+}
+
+/// Insert a point for every missing day, carrying the previous day's prices forward
+pub fn fill_gaps(history: &PriceHistory) -> PriceHistory {
+    let mut points: Vec<&PricePoint> = history.data.iter().collect();
+    points.sort_by_key(|p| p.date);
+
+    let mut data: Vec<PricePoint> = Vec::with_capacity(points.len());
+    for point in points {
+        if let Some(prev) = data.last().cloned() {
+            let mut day = prev.date + Days::new(1);
+            while day < point.date {
+                data.push(PricePoint { date: day, ..prev });
+                day = day + Days::new(1);
+            }
+            if prev.date == point.date {
+                continue;
+            }
+        }
+        data.push(point.clone());
+    }
+
+    PriceHistory { data, ..*history }
+}
+
+/// Aggregate daily points per period: lowest `min` and mean `avg`
+///
+/// Each resulting point is dated on the first day of its period.
+pub fn resample(history: &PriceHistory, period: Period) -> PriceHistory {
+    let mut points: Vec<&PricePoint> = history.data.iter().collect();
+    points.sort_by_key(|p| p.date);
+
+    let mut data: Vec<PricePoint> = Vec::new();
+    let mut count = 0.0;
+
+    for point in points {
+        let start = period.start_of(point.date);
+        match data.last_mut() {
+            Some(bucket) if bucket.date == start => {
+                bucket.min = bucket.min.min(point.min);
+                // Running mean of avg prices in the bucket
+                count += 1.0;
+                bucket.avg += (point.avg - bucket.avg) / count;
+            }
+            _ => {
+                data.push(PricePoint { date: start, ..point.clone() });
+                count = 1.0;
+            }
+        }
+    }
+
+    PriceHistory { data, ..*history }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn history(points: &[(&str, f64, f64)]) -> PriceHistory {
+        PriceHistory {
+            min_axis: 0.0,
+            max_axis: 100.0,
+            data: points
+                .iter()
+                .map(|&(d, min, avg)| PricePoint { date: date(d), min, avg })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn period_start_of() {
+        // 2024-01-10 is a Wednesday
+        assert_eq!(Period::Week.start_of(date("2024-01-10")), date("2024-01-08"));
+        assert_eq!(Period::Week.start_of(date("2024-01-08")), date("2024-01-08"));
+        assert_eq!(Period::Month.start_of(date("2024-02-29")), date("2024-02-01"));
+        assert_eq!(Period::Week.before(date("2024-01-10")), date("2024-01-03"));
+        assert_eq!(Period::Month.before(date("2024-03-31")), date("2024-02-29"));
+    }
+
+    #[test]
+    fn select_range_bounds_inclusive() {
+        let h = history(&[
+            ("2024-01-01", 1.0, 1.0),
+            ("2024-01-02", 2.0, 2.0),
+            ("2024-01-03", 3.0, 3.0),
+        ]);
+
+        let both = select_range(&h, Some(date("2024-01-02")), Some(date("2024-01-03")));
+        assert_eq!(both.data.len(), 2);
+        assert_eq!(both.data[0].date, date("2024-01-02"));
+
+        let open = select_range(&h, None, Some(date("2024-01-01")));
+        assert_eq!(open.data.len(), 1);
+        assert!((open.max_axis - 100.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn fill_gaps_carries_forward() {
+        let h = history(&[("2024-01-01", 10.0, 12.0), ("2024-01-04", 8.0, 9.0)]);
+        let filled = fill_gaps(&h);
+
+        assert_eq!(filled.data.len(), 4);
+        assert_eq!(filled.data[1].date, date("2024-01-02"));
+        assert!((filled.data[2].min - 10.0).abs() < f64::EPSILON);
+        assert!((filled.data[3].min - 8.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn fill_gaps_sorts_and_dedups() {
+        let h = history(&[
+            ("2024-01-02", 5.0, 5.0),
+            ("2024-01-01", 4.0, 4.0),
+            ("2024-01-02", 6.0, 6.0),
+        ]);
+        let filled = fill_gaps(&h);
+
+        assert_eq!(filled.data.len(), 2);
+        assert_eq!(filled.data[0].date, date("2024-01-01"));
+    }
+
+    #[test]
+    fn resample_weekly() {
+        let h = history(&[
+            ("2024-01-08", 10.0, 20.0),
+            ("2024-01-09", 8.0, 10.0),
+            ("2024-01-14", 9.0, 30.0),
+            ("2024-01-15", 7.0, 7.0),
+        ]);
+        let weekly = resample(&h, Period::Week);
+
+        assert_eq!(weekly.data.len(), 2);
+        assert_eq!(weekly.data[0].date, date("2024-01-08"));
+        assert!((weekly.data[0].min - 8.0).abs() < f64::EPSILON);
+        assert!((weekly.data[0].avg - 20.0).abs() < 1e-9);
+        assert_eq!(weekly.data[1].date, date("2024-01-15"));
+    }
+
+    #[test]
+    fn resample_monthly() {
+        let h = history(&[("2024-01-31", 10.0, 10.0), ("2024-02-01", 9.0, 9.0)]);
+        let monthly = resample(&h, Period::Month);
+
+        assert_eq!(monthly.data.len(), 2);
+        assert_eq!(monthly.data[1].date, date("2024-02-01"));
+    }
+}
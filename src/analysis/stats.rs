@@ -0,0 +1,247 @@
+//! Summary statistics over a product's price history
+
+use crate::api::{PriceHistory, PricePoint};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Default percentile below which the current price is considered a buy
+pub const DEFAULT_BUY_PERCENTILE: f64 = 25.0;
+
+/// Buy/wait recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    Buy,
+    Wait,
+}
+
+/// Largest day-over-day price change
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceMove {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub from: f64,
+    pub to: f64,
+    pub change: f64,
+    pub change_pct: f64,
+}
+
+/// Price statistics for the `min` series of a price history window
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceStats {
+    pub product_id: u64,
+    pub points: usize,
+    pub first_date: NaiveDate,
+    pub last_date: NaiveDate,
+    pub current: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// Share of observations strictly cheaper than the current price (0-100)
+    pub percentile: f64,
+    /// Standard deviation of day-over-day changes, in percent
+    pub volatility: f64,
+    pub largest_drop: Option<PriceMove>,
+    pub largest_spike: Option<PriceMove>,
+    pub days_since_low: i64,
+    /// Least-squares slope of the `min` series, in euros per day
+    pub trend_slope: f64,
+    pub buy_percentile: f64,
+    pub signal: Signal,
+}
+
+impl PriceStats {
+    /// Compute statistics for a history, or `None` if it has no data points
+    pub fn compute(product_id: u64, history: &PriceHistory, buy_percentile: f64) -> Option<Self> {
+        let points = &history.data;
+        let first = points.first()?;
+        let last = points.last()?;
+
+        let prices: Vec<f64> = points.iter().map(|p| p.min).collect();
+        let n = prices.len() as f64;
+        let current = last.min;
+
+        let min = prices.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = prices.iter().sum::<f64>() / n;
+
+        let below = prices.iter().filter(|&&p| p < current).count() as f64;
+        let percentile = below / n * 100.0;
+
+        let last_low = points
+            .iter()
+            .rev()
+            .find(|p| (p.min - min).abs() < f64::EPSILON)
+            .map_or(last.date, |p| p.date);
+
+        let signal = if percentile <= buy_percentile { Signal::Buy } else { Signal::Wait };
+
+        Some(Self {
+            product_id,
+            points: points.len(),
+            first_date: first.date,
+            last_date: last.date,
+            current,
+            min,
+            max,
+            mean,
+            median: median(&prices),
+            percentile,
+            volatility: volatility(&prices),
+            largest_drop: largest_move(points, |change| change < 0.0, f64::lt),
+            largest_spike: largest_move(points, |change| change > 0.0, f64::gt),
+            days_since_low: (last.date - last_low).num_days(),
+            trend_slope: trend_slope(&prices),
+            buy_percentile,
+            signal,
+        })
+    }
+}
+
+fn median(prices: &[f64]) -> f64 {
+    let mut sorted = prices.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn volatility(prices: &[f64]) -> f64 {
+    let returns: Vec<f64> =
+        prices.windows(2).filter(|w| w[0] > 0.0).map(|w| (w[1] - w[0]) / w[0] * 100.0).collect();
+
+    if returns.is_empty() {
+        return 0.0;
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt()
+}
+
+fn trend_slope(prices: &[f64]) -> f64 {
+    let n = prices.len() as f64;
+    if prices.len() < 2 {
+        return 0.0;
+    }
+
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = prices.iter().sum::<f64>() / n;
+
+    let (num, den) = prices.iter().enumerate().fold((0.0, 0.0), |(num, den), (i, &y)| {
+        let dx = i as f64 - mean_x;
+        (dx.mul_add(y - mean_y, num), dx.mul_add(dx, den))
+    });
+
+    if den == 0.0 {
+        0.0
+    } else {
+        num / den
+    }
+}
+
+fn largest_move(
+    points: &[PricePoint],
+    keep: impl Fn(f64) -> bool,
+    better: impl Fn(&f64, &f64) -> bool,
+) -> Option<PriceMove> {
+    points
+        .windows(2)
+        .map(|w| (w, w[1].min - w[0].min))
+        .filter(|&(_, change)| keep(change))
+        .fold(None, |best: Option<(&[PricePoint], f64)>, (w, change)| match best {
+            Some((_, best_change)) if !better(&change, &best_change) => best,
+            _ => Some((w, change)),
+        })
+        .map(|(w, change)| PriceMove {
+            from_date: w[0].date,
+            to_date: w[1].date,
+            from: w[0].min,
+            to: w[1].min,
+            change,
+            change_pct: if w[0].min > 0.0 { change / w[0].min * 100.0 } else { 0.0 },
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Days;
+
+    fn history(prices: &[f64]) -> PriceHistory {
+        PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: prices
+                .iter()
+                .enumerate()
+                .map(|(i, &min)| PricePoint {
+                    date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + Days::new(i as u64),
+                    avg: min + 10.0,
+                    min,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compute_empty_history() {
+        assert!(PriceStats::compute(1, &history(&[]), DEFAULT_BUY_PERCENTILE).is_none());
+    }
+
+    #[test]
+    fn compute_basic_stats() {
+        let stats = PriceStats::compute(1, &history(&[100.0, 90.0, 120.0, 110.0]), 25.0).unwrap();
+
+        assert_eq!(stats.points, 4);
+        assert_eq!(stats.first_date.to_string(), "2024-01-01");
+        assert_eq!(stats.last_date.to_string(), "2024-01-04");
+        assert!((stats.current - 110.0).abs() < f64::EPSILON);
+        assert!((stats.min - 90.0).abs() < f64::EPSILON);
+        assert!((stats.max - 120.0).abs() < f64::EPSILON);
+        assert!((stats.mean - 105.0).abs() < f64::EPSILON);
+        assert!((stats.median - 105.0).abs() < f64::EPSILON);
+        assert!((stats.percentile - 50.0).abs() < f64::EPSILON);
+        assert_eq!(stats.days_since_low, 2);
+        assert_eq!(stats.signal, Signal::Wait);
+    }
+
+    #[test]
+    fn compute_largest_moves() {
+        let stats = PriceStats::compute(1, &history(&[100.0, 90.0, 120.0, 110.0]), 25.0).unwrap();
+
+        let drop = stats.largest_drop.unwrap();
+        assert_eq!(drop.from_date.to_string(), "2024-01-01");
+        assert!((drop.change + 10.0).abs() < f64::EPSILON);
+
+        let spike = stats.largest_spike.unwrap();
+        assert_eq!(spike.to_date.to_string(), "2024-01-03");
+        assert!((spike.change - 30.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn compute_buy_signal_at_low() {
+        let stats = PriceStats::compute(1, &history(&[120.0, 110.0, 100.0]), 25.0).unwrap();
+
+        assert!(stats.percentile.abs() < f64::EPSILON);
+        assert_eq!(stats.days_since_low, 0);
+        assert!((stats.trend_slope + 10.0).abs() < 1e-9);
+        assert!(stats.largest_spike.is_none());
+        assert_eq!(stats.signal, Signal::Buy);
+    }
+
+    #[test]
+    fn compute_flat_history() {
+        let stats = PriceStats::compute(1, &history(&[50.0, 50.0, 50.0]), 25.0).unwrap();
+
+        assert!(stats.volatility.abs() < f64::EPSILON);
+        assert!(stats.trend_slope.abs() < f64::EPSILON);
+        assert!(stats.largest_drop.is_none());
+    }
+}
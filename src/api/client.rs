@@ -1,18 +1,60 @@
 //! HTTP client for `KuantoKusta` API
 
+use super::metrics::ClientMetrics;
 use super::models::{
     Category, DealsResponse, PriceHistory, Product, ProductsResponse, RelatedResponse,
 };
+use super::ratelimit::RateLimiter;
+use crate::store::{self, SharedStore, Store};
 use anyhow::{Context, Result};
 use reqwest::Client;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 const API_BASE: &str = "https://api.kuantokusta.pt";
 
+/// History windows served by the price history endpoint, shortest first
+pub const HISTORY_WINDOWS: [u32; 2] = [30, 90];
+
+/// Days to request for `days` of history: the shortest window covering them,
+/// or the longest one when none does
+pub fn history_window(days: u32) -> u32 {
+    let longest = HISTORY_WINDOWS[HISTORY_WINDOWS.len() - 1];
+    HISTORY_WINDOWS.into_iter().find(|&window| window >= days).unwrap_or(longest)
+}
+
 /// `KuantoKusta` API client
 #[derive(Debug, Clone)]
 pub struct KuantoKustaClient {
     client: Client,
     base_url: String,
+    store: Option<SharedStore>,
+    limiter: Option<Arc<RateLimiter>>,
+    cache: Option<Arc<ProductCache>>,
+    metrics: Arc<ClientMetrics>,
+}
+
+/// Products fetched less than `ttl` ago
+#[derive(Debug)]
+struct ProductCache {
+    ttl: Duration,
+    products: Mutex<HashMap<u64, (Instant, Product)>>,
+}
+
+impl ProductCache {
+    fn get(&self, id: u64) -> Option<Product> {
+        let products = self.products.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        products.get(&id).filter(|(at, _)| at.elapsed() < self.ttl).map(|(_, p)| p.clone())
+    }
+
+    fn insert(&self, product: &Product) {
+        let mut products = self.products.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        products.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        products.insert(product.id, (Instant::now(), product.clone()));
+    }
 }
 
 impl KuantoKustaClient {
@@ -29,21 +71,113 @@ impl KuantoKustaClient {
             .build()
             .context("Failed to create HTTP client")?;
 
-        Ok(Self { client, base_url: base_url.to_string() })
+        Ok(Self {
+            client,
+            base_url: base_url.to_string(),
+            store: None,
+            limiter: None,
+            cache: None,
+            metrics: Arc::default(),
+        })
+    }
+
+    /// Record every fetched product, deal and price point in a local store
+    #[must_use]
+    pub fn with_store(mut self, store: Store) -> Self {
+        self.store = Some(Arc::new(Mutex::new(store)));
+        self
+    }
+
+    /// Space requests at least `interval` apart, across all clones of this client
+    #[must_use]
+    pub fn with_rate_limit(mut self, interval: Duration) -> Self {
+        self.limiter = (!interval.is_zero()).then(|| Arc::new(RateLimiter::new(interval)));
+        self
+    }
+
+    /// Answer product lookups from memory for `ttl` after fetching them,
+    /// across all clones of this client
+    #[must_use]
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache =
+            (!ttl.is_zero()).then(|| Arc::new(ProductCache { ttl, products: Mutex::default() }));
+        self
+    }
+
+    /// Request counters, shared by all clones of this client
+    pub fn metrics(&self) -> &ClientMetrics {
+        &self.metrics
+    }
+
+    /// Run a request to `endpoint`, counting it in the metrics
+    async fn timed<T>(
+        &self,
+        endpoint: &'static str,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = request.await;
+        self.metrics.record(endpoint, started.elapsed(), result.is_ok());
+        result
+    }
+
+    /// Wait for the rate limiter, if any
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.wait().await;
+        }
+    }
+
+    /// Local store attached with [`Self::with_store`]
+    pub const fn store(&self) -> Option<&SharedStore> {
+        self.store.as_ref()
+    }
+
+    /// Run `f` against the local store with a copy of `value`, on the blocking
+    /// pool so `SQLite` writes never stall the runtime; failures are logged,
+    /// never returned
+    async fn record<T>(
+        &self,
+        what: &str,
+        value: &T,
+        f: impl FnOnce(&mut Store, &T) -> Result<()> + Send + 'static,
+    ) where
+        T: ToOwned + ?Sized,
+        T::Owned: Send + 'static,
+    {
+        let Some(shared) = self.store.clone() else {
+            return;
+        };
+        let value = value.to_owned();
+        let write = move || f(&mut store::lock(&shared), value.borrow());
+        match tokio::task::spawn_blocking(write).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to record {what} in local store: {e:#}"),
+            Err(e) => tracing::warn!("Failed to record {what} in local store: {e}"),
+        }
     }
 
     /// Get products (popular products - note: search is SSR-only)
     pub async fn products(&self, rows: u32) -> Result<ProductsResponse> {
+        self.throttle().await;
         let url = format!("{}/products", self.base_url);
-        let resp = self
-            .client
-            .get(&url)
-            .query(&[("rows", &rows.to_string())])
-            .send()
-            .await
-            .context("Failed to fetch products")?;
-
-        resp.json().await.context("Failed to parse products response")
+        let products: ProductsResponse = self
+            .timed("products", async {
+                let resp = self
+                    .client
+                    .get(&url)
+                    .query(&[("rows", &rows.to_string())])
+                    .send()
+                    .await
+                    .context("Failed to fetch products")?;
+                resp.json().await.context("Failed to parse products response")
+            })
+            .await?;
+        self.record("products", products.data.as_slice(), |s, data| {
+            s.record_products(data, store::today())
+        })
+        .await;
+        Ok(products)
     }
 
     /// Get deals/discounts
@@ -55,6 +189,7 @@ impl KuantoKustaClient {
         min_price: Option<f64>,
         max_price: Option<f64>,
     ) -> Result<DealsResponse> {
+        self.throttle().await;
         let url = format!("{}/deals", self.base_url);
         let mut req = self.client.get(&url);
 
@@ -69,59 +204,143 @@ impl KuantoKustaClient {
 
         req = req.query(&[("rows", &rows.to_string()), ("page", &page.to_string())]);
 
-        let resp = req.send().await.context("Failed to fetch deals")?;
+        let deals: DealsResponse = self
+            .timed("deals", async {
+                let resp = req.send().await.context("Failed to fetch deals")?;
+                resp.json().await.context("Failed to parse deals response")
+            })
+            .await?;
+        self.record("deals", deals.data.as_slice(), |s, data| s.record_deals(data, store::today()))
+            .await;
+        Ok(deals)
+    }
 
-        resp.json().await.context("Failed to parse deals response")
+    /// Get a single product
+    pub async fn product(&self, product_id: u64) -> Result<Product> {
+        if let Some(cache) = &self.cache {
+            if let Some(product) = cache.get(product_id) {
+                self.metrics.cache_hit();
+                return Ok(product);
+            }
+            self.metrics.cache_miss();
+        }
+        self.throttle().await;
+        let url = format!("{}/products/{product_id}", self.base_url);
+        let product: Product = self
+            .timed("product", async {
+                let resp = self
+                    .client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .with_context(|| format!("Failed to fetch product {product_id}"))?;
+                resp.json().await.context("Failed to parse product")
+            })
+            .await?;
+        if let Some(cache) = &self.cache {
+            cache.insert(&product);
+        }
+        self.record("product", std::slice::from_ref(&product), |s, data| {
+            s.record_products(data, store::today())
+        })
+        .await;
+        Ok(product)
     }
 
     /// Get price history for a product
     pub async fn price_history(&self, product_id: u64, days: u32) -> Result<PriceHistory> {
+        self.throttle().await;
         let url = format!("{}/products/{product_id}/price-history", self.base_url);
-        let resp = self
-            .client
-            .get(&url)
-            .query(&[("days", &days.to_string())])
-            .send()
-            .await
-            .context("Failed to fetch price history")?;
-
-        resp.json().await.context("Failed to parse price history")
+        let history: PriceHistory = self
+            .timed("price_history", async {
+                let resp = self
+                    .client
+                    .get(&url)
+                    .query(&[("days", &days.to_string())])
+                    .send()
+                    .await
+                    .context("Failed to fetch price history")?;
+                resp.json().await.context("Failed to parse price history")
+            })
+            .await?;
+        self.record("price history", &history, move |s, data| s.record_history(product_id, data))
+            .await;
+        Ok(history)
     }
 
     /// Get popular products in a category
     pub async fn popular(&self, category_id: u64, rows: u32) -> Result<Vec<Product>> {
+        self.throttle().await;
         let url = format!("{}/products/popular", self.base_url);
-        let resp = self
-            .client
-            .get(&url)
-            .query(&[("categoryId", &category_id.to_string()), ("rows", &rows.to_string())])
-            .send()
-            .await
-            .context("Failed to fetch popular products")?;
-
-        resp.json().await.context("Failed to parse popular products")
+        let products: Vec<Product> = self
+            .timed("popular", async {
+                let resp = self
+                    .client
+                    .get(&url)
+                    .query(&[("categoryId", &category_id.to_string()), ("rows", &rows.to_string())])
+                    .send()
+                    .await
+                    .context("Failed to fetch popular products")?;
+                resp.json().await.context("Failed to parse popular products")
+            })
+            .await?;
+        self.record("popular products", products.as_slice(), move |s, data| {
+            s.record_category_products(category_id, data, store::today())
+        })
+        .await;
+        Ok(products)
     }
 
     /// Get related products
     pub async fn related(&self, product_id: u64) -> Result<RelatedResponse> {
+        self.throttle().await;
         let url = format!("{}/products/{product_id}/related", self.base_url);
-        let resp =
-            self.client.get(&url).send().await.context("Failed to fetch related products")?;
-
-        resp.json().await.context("Failed to parse related products")
+        let related: RelatedResponse = self
+            .timed("related", async {
+                let resp = self
+                    .client
+                    .get(&url)
+                    .send()
+                    .await
+                    .context("Failed to fetch related products")?;
+                resp.json().await.context("Failed to parse related products")
+            })
+            .await?;
+        self.record("related products", related.data.as_slice(), |s, data| {
+            s.record_products(data, store::today())
+        })
+        .await;
+        Ok(related)
     }
 
     /// Get all categories
     pub async fn categories(&self) -> Result<Vec<Category>> {
+        self.throttle().await;
         let url = format!("{}/categories", self.base_url);
-        let resp = self.client.get(&url).send().await.context("Failed to fetch categories")?;
-
-        resp.json().await.context("Failed to parse categories")
+        let categories: Vec<Category> = self
+            .timed("categories", async {
+                let resp =
+                    self.client.get(&url).send().await.context("Failed to fetch categories")?;
+                resp.json().await.context("Failed to parse categories")
+            })
+            .await?;
+        self.record("categories", categories.as_slice(), |s, data| {
+            s.record_categories(data, store::today())
+        })
+        .await;
+        Ok(categories)
     }
 
     /// Search products (via HTML scraping with wreq)
     pub async fn search(&self, query: &str, max: usize) -> Result<super::scraper::SearchResult> {
-        super::scraper::search(query, max).await
+        self.throttle().await;
+        let result = self.timed("search", super::scraper::search(query, max)).await?;
+        self.record("search results", result.products.as_slice(), |s, data| {
+            s.record_products(data, store::today())
+        })
+        .await;
+        Ok(result)
     }
 }
 
@@ -137,6 +356,14 @@ mod tests {
     use wiremock::matchers::{method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    #[test]
+    fn history_window_rounds_up_to_api_windows() {
+        assert_eq!(history_window(7), 30);
+        assert_eq!(history_window(30), 30);
+        assert_eq!(history_window(31), 90);
+        assert_eq!(history_window(365), 90);
+    }
+
     fn mock_products_response() -> serde_json::Value {
         serde_json::json!({
             "data": [{
@@ -352,6 +579,76 @@ mod tests {
         assert_eq!(categories[1].parent_id, Some(1));
     }
 
+    #[tokio::test]
+    async fn test_product_success() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(&mock_products_response()["data"][0]),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let product = client.product(12345).await.unwrap();
+        assert_eq!(product.name, "Test Product");
+
+        assert!(client.product(404).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_product_cache_and_metrics() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(&mock_products_response()["data"][0]),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri())
+            .unwrap()
+            .with_cache(Duration::from_secs(60));
+        client.product(12345).await.unwrap();
+        client.clone().product(12345).await.unwrap();
+        assert!(client.product(404).await.is_err());
+
+        let endpoints = client.metrics().endpoints();
+        assert_eq!((endpoints["product"].requests, endpoints["product"].errors), (2, 1));
+        assert_eq!(client.metrics().cache(), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_records_into_store() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_products_response()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri())
+            .unwrap()
+            .with_store(Store::open_in_memory().unwrap());
+        client.products(20).await.unwrap();
+        client.price_history(12345, 30).await.unwrap();
+
+        let history = store::lock(client.store().unwrap()).history(12345).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().any(|p| p.date == store::today()));
+    }
+
     #[tokio::test]
     async fn test_products_server_error() {
         let mock_server = MockServer::start().await;
@@ -0,0 +1,92 @@
+//! Request counters shared by every clone of a client, exposed by `kk exporter`
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Requests to one endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointStats {
+    pub requests: u64,
+    pub errors: u64,
+    /// Requests that took at most the matching [`LATENCY_BUCKETS`] bound
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    pub seconds_total: f64,
+}
+
+impl EndpointStats {
+    fn add(&mut self, seconds: f64, ok: bool) {
+        self.requests += 1;
+        self.errors += u64::from(!ok);
+        self.seconds_total += seconds;
+        for (count, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            *count += u64::from(seconds <= bound);
+        }
+    }
+}
+
+/// Request, error, latency and cache counters of a client
+#[derive(Debug, Default)]
+pub struct ClientMetrics {
+    endpoints: Mutex<BTreeMap<&'static str, EndpointStats>>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl ClientMetrics {
+    /// Count a request to `endpoint` that took `elapsed`
+    pub fn record(&self, endpoint: &'static str, elapsed: Duration, ok: bool) {
+        self.endpoints
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(endpoint)
+            .or_default()
+            .add(elapsed.as_secs_f64(), ok);
+    }
+
+    pub fn cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counters per endpoint, by endpoint name
+    pub fn endpoints(&self) -> BTreeMap<&'static str, EndpointStats> {
+        self.endpoints.lock().unwrap_or_else(std::sync::PoisonError::into_inner).clone()
+    }
+
+    /// Cache hits and misses
+    pub fn cache(&self) -> (u64, u64) {
+        (self.cache_hits.load(Ordering::Relaxed), self.cache_misses.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_into_buckets() {
+        let metrics = ClientMetrics::default();
+        metrics.record("product", Duration::from_millis(80), true);
+        metrics.record("product", Duration::from_secs(3), false);
+        metrics.record("deals", Duration::from_secs(20), true);
+        metrics.cache_hit();
+        metrics.cache_miss();
+        metrics.cache_hit();
+
+        let endpoints = metrics.endpoints();
+        let product = &endpoints["product"];
+        assert_eq!((product.requests, product.errors), (2, 1));
+        assert_eq!(product.buckets, [0, 1, 1, 1, 1, 1, 2, 2]);
+        assert!((product.seconds_total - 3.08).abs() < 1e-9);
+        assert_eq!(endpoints["deals"].buckets, [0; 8]);
+        assert_eq!(metrics.cache(), (2, 1));
+    }
+}
@@ -1,9 +1,11 @@
 //! `KuantoKusta` API module
 
 pub mod client;
+pub mod metrics;
 pub mod models;
+pub mod ratelimit;
 pub mod scraper;
 
-pub use client::KuantoKustaClient;
+pub use client::{history_window, KuantoKustaClient, HISTORY_WINDOWS};
 pub use models::*;
-pub use scraper::{parse_search_html, search_with_base_url, SearchResult};
+pub use scraper::{parse_search_html, search_with_base_url, web_url, SearchResult, WEB_BASE};
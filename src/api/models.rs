@@ -1,5 +1,6 @@
 //! Data models for `KuantoKusta` API responses
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 /// Product from search/popular/related endpoints
@@ -29,6 +30,55 @@ pub struct Product {
     pub tags: Tags,
 }
 
+impl Product {
+    /// Discount percentage, from the badges or (failing that) the tags
+    pub fn discount(&self) -> Option<u8> {
+        self.badges.discount_percentage.or(self.tags.discount_percentage)
+    }
+}
+
+/// Test fixtures: `Product::fixture(id, price)` plus `with_*` setters
+#[cfg(test)]
+impl Product {
+    /// "Product <id>" at `price` from 3 offers, at `/p/<id>`, with no brand,
+    /// category, badges or rating
+    pub fn fixture(id: u64, price: f64) -> Self {
+        Self {
+            id,
+            name: format!("Product {id}"),
+            brand: String::new(),
+            category: String::new(),
+            price_min: price,
+            total_offers: 3,
+            url: format!("/p/{id}"),
+            images: vec![],
+            badges: Badges::default(),
+            rating: None,
+            tags: Tags::default(),
+        }
+    }
+
+    pub fn with_name(self, name: &str) -> Self {
+        Self { name: name.to_string(), ..self }
+    }
+
+    pub fn with_brand(self, brand: &str) -> Self {
+        Self { brand: brand.to_string(), ..self }
+    }
+
+    pub fn with_category(self, category: &str) -> Self {
+        Self { category: category.to_string(), ..self }
+    }
+
+    pub fn with_offers(self, total_offers: u32) -> Self {
+        Self { total_offers, ..self }
+    }
+
+    pub fn with_url(self, url: &str) -> Self {
+        Self { url: url.to_string(), ..self }
+    }
+}
+
 /// Deserialize images from either array or single string
 fn deserialize_images<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
@@ -159,11 +209,23 @@ pub struct PriceHistory {
 /// Single price history data point
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PricePoint {
-    pub date: String,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
     pub avg: f64,
     pub min: f64,
 }
 
+/// Deserialize a date from `YYYY-MM-DD`, ignoring any trailing time component
+pub(crate) fn deserialize_date<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    let day = raw.get(..10).unwrap_or(&raw);
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|e| serde::de::Error::custom(format!("invalid date {raw:?}: {e}")))
+}
+
 /// Category from /categories endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -205,6 +267,13 @@ pub struct Deal {
     pub tags: Tags,
 }
 
+impl Deal {
+    /// Discount percentage, from the badges or (failing that) the tags
+    pub fn discount(&self) -> Option<u8> {
+        self.badges.discount_percentage.or(self.tags.discount_percentage)
+    }
+}
+
 /// Deals response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DealsResponse {
@@ -268,6 +337,18 @@ mod tests {
         assert!((product.price_min - 0.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn discount_falls_back_to_tags() {
+        let json = r#"{"id": 1, "name": "Deal", "tags": {"discountPercentage": 15}}"#;
+        let deal: Deal = serde_json::from_str(json).unwrap();
+        assert_eq!(deal.discount(), Some(15));
+
+        let json = r#"{"id": 1, "name": "Product", "badges": {"discountPercentage": 20},
+                       "tags": {"discountPercentage": 15}}"#;
+        let product: Product = serde_json::from_str(json).unwrap();
+        assert_eq!(product.discount(), Some(20));
+    }
+
     #[test]
     fn parse_category() {
         let json = r#"{
@@ -300,6 +381,20 @@ mod tests {
         let history: PriceHistory = serde_json::from_str(json).unwrap();
         assert!((history.min_axis - 500.0).abs() < f64::EPSILON);
         assert_eq!(history.data.len(), 2);
-        assert_eq!(history.data[0].date, "2024-01-01");
+        assert_eq!(history.data[0].date, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn parse_price_point_with_time() {
+        let json = r#"{"date": "2024-03-05T00:00:00.000Z", "avg": 10.0, "min": 9.0}"#;
+        let point: PricePoint = serde_json::from_str(json).unwrap();
+        assert_eq!(point.date, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+        assert_eq!(serde_json::to_value(&point).unwrap()["date"], "2024-03-05");
+    }
+
+    #[test]
+    fn parse_price_point_invalid_date() {
+        let json = r#"{"date": "yesterday", "avg": 10.0, "min": 9.0}"#;
+        assert!(serde_json::from_str::<PricePoint>(json).is_err());
     }
 }
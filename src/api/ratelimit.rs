@@ -0,0 +1,61 @@
+//! Request pacing shared by every clone of a client
+
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces requests at least `interval` apart
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, next: Mutex::new(Instant::now()) }
+    }
+
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Wait for the next free slot; concurrent callers queue up in turn
+    pub async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            tokio::time::sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + self.interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn spaces_concurrent_requests() {
+        let limiter = Arc::new(RateLimiter::new(Duration::from_millis(100)));
+        let start = Instant::now();
+
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                tokio::spawn(async move { limiter.wait().await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(200));
+
+        // Idle time isn't banked: after a pause the next request goes straight out
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let before = Instant::now();
+        limiter.wait().await;
+        assert_eq!(before.elapsed(), Duration::ZERO);
+    }
+}
@@ -7,7 +7,17 @@ use serde::Deserialize;
 use wreq::Client;
 use wreq_util::Emulation;
 
-const WEB_BASE: &str = "https://www.kuantokusta.pt";
+/// Public website, for product links
+pub const WEB_BASE: &str = "https://www.kuantokusta.pt";
+
+/// Absolute website URL for a product path such as `/p/123/name`
+pub fn web_url(path: &str) -> String {
+    if path.starts_with('/') {
+        format!("{WEB_BASE}{path}")
+    } else {
+        path.to_string()
+    }
+}
 
 /// Search response from __`NEXT_DATA`__
 #[derive(Debug, Deserialize)]
@@ -138,6 +148,12 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_web_url() {
+        assert_eq!(web_url("/p/1/ps5"), "https://www.kuantokusta.pt/p/1/ps5");
+        assert_eq!(web_url("https://example.com/p/1"), "https://example.com/p/1");
+    }
+
     #[test]
     fn test_parse_search_html_success() {
         let html = mock_next_data_html();
@@ -0,0 +1,482 @@
+//! Watchlist evaluation: compare current prices against the last check
+
+use crate::api::{Deal, PriceHistory, Product};
+use crate::rules::{AlertsConfig, Facts, Gate, Rule};
+use crate::watchlist::WatchEntry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Price and store count of a product at the last check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    pub price_min: f64,
+    pub total_offers: u32,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Last observation of every watched product
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheckState {
+    pub products: BTreeMap<u64, Observation>,
+    /// When each rule last fired, keyed by `<product id>:<rule>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fired: BTreeMap<String, DateTime<Utc>>,
+    /// Rule matches held back by quiet hours, keyed like `fired`, with the
+    /// price they matched at; they fire once the quiet hours are over
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub held: BTreeMap<String, f64>,
+}
+
+impl CheckState {
+    /// Default state file location
+    pub fn default_path() -> PathBuf {
+        crate::paths::data_dir().join("check-state.json")
+    }
+
+    /// Load the state, or an empty one if there is none yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid check state in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Save the state
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// A watched product with what was fetched for it
+#[derive(Debug, Clone)]
+pub struct Observed {
+    pub entry: WatchEntry,
+    /// Current product data (`None` if it couldn't be fetched)
+    pub product: Option<Product>,
+    /// Price history, fetched when a rule reads a history window
+    pub history: Option<PriceHistory>,
+}
+
+/// What changed for a product since the last check
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ChangeKind {
+    #[serde(rename_all = "camelCase")]
+    PriceDrop { from: f64, to: f64, change_pct: f64 },
+    #[serde(rename_all = "camelCase")]
+    PriceIncrease { from: f64, to: f64, change_pct: f64 },
+    /// The price crossed to at or below the target
+    #[serde(rename_all = "camelCase")]
+    TargetReached { target: f64, price: f64 },
+    #[serde(rename_all = "camelCase")]
+    OffersChanged { from: u32, to: u32 },
+    /// An alert rule's condition holds
+    #[serde(rename_all = "camelCase")]
+    RuleMatched { rule: String, price: f64 },
+}
+
+/// A change of one watched product
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub product_id: u64,
+    pub label: String,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+    /// Whether this change is an alert (target reached, or a drop beyond `max_drop`)
+    pub alert: bool,
+}
+
+/// Outcome of checking the watchlist
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub checked_at: DateTime<Utc>,
+    pub checked: usize,
+    /// Products whose price couldn't be fetched
+    pub failed: Vec<u64>,
+    pub changes: Vec<Change>,
+}
+
+impl CheckReport {
+    pub fn alerts(&self) -> usize {
+        self.changes.iter().filter(|c| c.alert).count()
+    }
+}
+
+/// Compare one product's current status against its previous observation
+pub fn evaluate(
+    entry: &WatchEntry,
+    previous: Option<&Observation>,
+    price: f64,
+    offers: u32,
+) -> Vec<ChangeKind> {
+    let mut changes = Vec::new();
+
+    if let Some(prev) = previous {
+        if (price - prev.price_min).abs() >= 0.005 {
+            let change_pct = if prev.price_min > 0.0 {
+                (price - prev.price_min) / prev.price_min * 100.0
+            } else {
+                0.0
+            };
+            changes.push(if price < prev.price_min {
+                ChangeKind::PriceDrop { from: prev.price_min, to: price, change_pct }
+            } else {
+                ChangeKind::PriceIncrease { from: prev.price_min, to: price, change_pct }
+            });
+        }
+        if offers != prev.total_offers {
+            changes.push(ChangeKind::OffersChanged { from: prev.total_offers, to: offers });
+        }
+    }
+
+    if let Some(target) = entry.target_price {
+        let was_above = previous.map_or(true, |prev| prev.price_min > target);
+        if price <= target && was_above {
+            changes.push(ChangeKind::TargetReached { target, price });
+        }
+    }
+
+    changes
+}
+
+/// Whether a change should fire an alert for this entry
+pub fn is_alert(entry: &WatchEntry, kind: &ChangeKind) -> bool {
+    match kind {
+        ChangeKind::TargetReached { .. } | ChangeKind::RuleMatched { .. } => true,
+        ChangeKind::PriceDrop { change_pct, .. } => {
+            entry.max_drop.is_some_and(|max_drop| -change_pct >= max_drop)
+        }
+        ChangeKind::PriceIncrease { .. } | ChangeKind::OffersChanged { .. } => false,
+    }
+}
+
+/// Evaluate fetched products against `state`, updating it with the new observations
+///
+/// `alerts` supplies the global rules and the cooldown and quiet hours applied
+/// to every rule.
+pub fn run_check(
+    observed: &[Observed],
+    state: &mut CheckState,
+    alerts: &AlertsConfig,
+    now: DateTime<Utc>,
+) -> CheckReport {
+    let mut report =
+        CheckReport { checked_at: now, checked: 0, failed: Vec::new(), changes: Vec::new() };
+    let local = now.with_timezone(&Local);
+
+    for item in observed {
+        let entry = &item.entry;
+        let Some(product) = &item.product else {
+            report.failed.push(entry.id);
+            continue;
+        };
+        report.checked += 1;
+        let (price, offers) = (product.price_min, product.total_offers);
+        let previous = state.products.get(&entry.id).copied();
+
+        let mut kinds = evaluate(entry, previous.as_ref(), price, offers);
+
+        let mut facts = Facts::from_product(product).with_previous(previous.map(|p| p.price_min));
+        if let Some(history) = &item.history {
+            facts = facts.with_history(history, local.date_naive());
+        }
+        for rule in entry.rules.iter().chain(&alerts.rules) {
+            let key = rule_key(entry.id, rule);
+            let matched = rule.when.expr().matches(&facts).then_some(price);
+            // A match held back by quiet hours still fires after them, even if
+            // the condition (e.g. a drop since the last check) no longer holds
+            let Some(matched_at) = matched.or_else(|| state.held.get(&key).copied()) else {
+                continue;
+            };
+            match alerts.gate(rule, state.fired.get(&key).copied(), now, local.time()) {
+                Gate::Fire => {
+                    state.held.remove(&key);
+                    state.fired.insert(key, now);
+                    kinds.push(ChangeKind::RuleMatched {
+                        rule: rule.label().to_string(),
+                        price: matched_at,
+                    });
+                }
+                Gate::Quiet => {
+                    tracing::debug!("Rule `{}` matched {} in quiet hours", rule.label(), entry.id);
+                    state.held.insert(key, matched_at);
+                }
+                Gate::Cooldown => {
+                    tracing::debug!("Rule `{}` matched {} in its cooldown", rule.label(), entry.id);
+                    state.held.remove(&key);
+                }
+            }
+        }
+
+        for kind in kinds {
+            report.changes.push(Change {
+                product_id: entry.id,
+                label: entry.label.clone(),
+                alert: is_alert(entry, &kind),
+                kind,
+            });
+        }
+
+        state.products.insert(
+            entry.id,
+            Observation { price_min: price, total_offers: offers, checked_at: now },
+        );
+    }
+
+    report
+}
+
+/// Match `rules` against current deals, gated by cooldowns and quiet hours like watchlist rules
+pub fn run_deal_rules(
+    deals: &[Deal],
+    rules: &[Rule],
+    state: &mut CheckState,
+    alerts: &AlertsConfig,
+    now: DateTime<Utc>,
+) -> CheckReport {
+    let mut report = CheckReport {
+        checked_at: now,
+        checked: deals.len(),
+        failed: Vec::new(),
+        changes: Vec::new(),
+    };
+    let local = now.with_timezone(&Local);
+
+    for deal in deals {
+        let facts = Facts::from_deal(deal);
+        for rule in rules.iter().filter(|rule| rule.when.expr().matches(&facts)) {
+            let key = format!("deal:{}", rule_key(deal.id, rule));
+            if alerts.gate(rule, state.fired.get(&key).copied(), now, local.time()) == Gate::Fire {
+                state.fired.insert(key, now);
+                report.changes.push(Change {
+                    product_id: deal.id,
+                    label: deal.name.clone(),
+                    kind: ChangeKind::RuleMatched {
+                        rule: rule.label().to_string(),
+                        price: deal.price_min,
+                    },
+                    alert: true,
+                });
+            }
+        }
+    }
+
+    report
+}
+
+fn rule_key(id: u64, rule: &Rule) -> String {
+    format!("{id}:{}", rule.label())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(target_price: Option<f64>, max_drop: Option<f64>) -> WatchEntry {
+        WatchEntry {
+            id: 1,
+            label: "PS5".to_string(),
+            tags: vec![],
+            target_price,
+            max_drop,
+            rules: vec![],
+            added: "2024-01-01".parse().unwrap(),
+        }
+    }
+
+    fn observed(price_min: f64, total_offers: u32) -> Observation {
+        Observation { price_min, total_offers, checked_at: DateTime::default() }
+    }
+
+    fn status(entry: WatchEntry, price: Option<f64>) -> Observed {
+        let product = price.map(|price| {
+            serde_json::from_value(serde_json::json!({
+                "id": entry.id,
+                "name": entry.label,
+                "priceMin": price,
+                "totalOffers": 10,
+                "badges": { "discountPercentage": 20 },
+                "tags": {}
+            }))
+            .unwrap()
+        });
+        Observed { entry, product, history: None }
+    }
+
+    #[test]
+    fn first_check_only_reports_target() {
+        assert!(evaluate(&entry(None, None), None, 100.0, 5).is_empty());
+        assert_eq!(
+            evaluate(&entry(Some(120.0), None), None, 100.0, 5),
+            vec![ChangeKind::TargetReached { target: 120.0, price: 100.0 }]
+        );
+    }
+
+    #[test]
+    fn detects_drops_increases_and_offers() {
+        let prev = observed(100.0, 5);
+        let changes = evaluate(&entry(None, None), Some(&prev), 90.0, 7);
+        assert_eq!(
+            changes,
+            vec![
+                ChangeKind::PriceDrop { from: 100.0, to: 90.0, change_pct: -10.0 },
+                ChangeKind::OffersChanged { from: 5, to: 7 },
+            ]
+        );
+
+        let changes = evaluate(&entry(None, None), Some(&prev), 110.0, 5);
+        assert!(matches!(changes[0], ChangeKind::PriceIncrease { .. }));
+        assert!(evaluate(&entry(None, None), Some(&prev), 100.001, 5).is_empty());
+    }
+
+    #[test]
+    fn target_fires_once() {
+        let e = entry(Some(95.0), None);
+        assert_eq!(evaluate(&e, Some(&observed(100.0, 5)), 94.0, 5).len(), 2);
+        let changes = evaluate(&e, Some(&observed(94.0, 5)), 93.0, 5);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0], ChangeKind::PriceDrop { .. }));
+    }
+
+    #[test]
+    fn alerts_respect_max_drop() {
+        let drop = ChangeKind::PriceDrop { from: 100.0, to: 92.0, change_pct: -8.0 };
+        assert!(!is_alert(&entry(None, None), &drop));
+        assert!(!is_alert(&entry(None, Some(10.0)), &drop));
+        assert!(is_alert(&entry(None, Some(5.0)), &drop));
+    }
+
+    #[test]
+    fn run_check_updates_state() {
+        let mut state = CheckState::default();
+        let now = Utc::now();
+        let statuses = vec![status(entry(Some(95.0), None), Some(100.0))];
+
+        let report = run_check(&statuses, &mut state, &AlertsConfig::default(), now);
+        assert!(report.changes.is_empty());
+        assert_eq!(
+            state.products[&1],
+            Observation { price_min: 100.0, total_offers: 10, checked_at: now }
+        );
+
+        let statuses = vec![status(entry(Some(95.0), None), Some(90.0))];
+        let report = run_check(&statuses, &mut state, &AlertsConfig::default(), now);
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(report.alerts(), 1);
+
+        let statuses = vec![status(entry(None, None), None)];
+        let report = run_check(&statuses, &mut state, &AlertsConfig::default(), now);
+        assert_eq!(report.failed, vec![1]);
+        assert_eq!(
+            state.products[&1],
+            Observation { price_min: 90.0, total_offers: 10, checked_at: now }
+        );
+    }
+
+    #[test]
+    fn rules_fire_with_cooldown() {
+        let mut state = CheckState::default();
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let mut e = entry(None, None);
+        e.rules.push(Rule::new("drop >= 10% and discount >= 20".parse().unwrap()));
+        let alerts: AlertsConfig = toml::from_str(
+            "cooldown = \"1h\"\n[[rules]]\nname = \"cheap\"\nwhen = \"price < 95\"\n",
+        )
+        .unwrap();
+
+        let report = run_check(&[status(e.clone(), Some(100.0))], &mut state, &alerts, now);
+        assert!(report.changes.is_empty());
+
+        let report = run_check(&[status(e.clone(), Some(90.0))], &mut state, &alerts, now);
+        let rules: Vec<&ChangeKind> = report.changes.iter().map(|c| &c.kind).skip(1).collect();
+        assert_eq!(
+            rules,
+            vec![
+                &ChangeKind::RuleMatched {
+                    rule: "drop >= 10% and discount >= 20".into(),
+                    price: 90.0
+                },
+                &ChangeKind::RuleMatched { rule: "cheap".into(), price: 90.0 },
+            ]
+        );
+        assert_eq!(report.alerts(), 2);
+
+        // Still cheap, but within the cooldown
+        let later = now + chrono::TimeDelta::minutes(30);
+        let report = run_check(&[status(e.clone(), Some(90.0))], &mut state, &alerts, later);
+        assert_eq!(report.alerts(), 0);
+        let later = now + chrono::TimeDelta::hours(2);
+        let report = run_check(&[status(e, Some(90.0))], &mut state, &alerts, later);
+        assert_eq!(report.alerts(), 1);
+        assert_eq!(state.fired["1:cheap"], later);
+    }
+
+    #[test]
+    fn quiet_hours_delay_rule_alerts() {
+        let mut state = CheckState::default();
+        let mut e = entry(None, None);
+        e.rules.push(Rule::new("drop >= 10%".parse().unwrap()));
+        // Quiet all day except for the hour starting now, in local time
+        let now = Utc::now();
+        let start = (now + chrono::TimeDelta::hours(1)).with_timezone(&Local).format("%H:00");
+        let end = now.with_timezone(&Local).format("%H:00");
+        let alerts: AlertsConfig =
+            toml::from_str(&format!("quiet_hours = \"{start}-{end}\"\n")).unwrap();
+        let (quiet, after) = (now - chrono::TimeDelta::hours(2), now);
+
+        run_check(&[status(e.clone(), Some(100.0))], &mut state, &alerts, quiet);
+        let report = run_check(&[status(e.clone(), Some(85.0))], &mut state, &alerts, quiet);
+        assert_eq!(report.alerts(), 0);
+        assert!((state.held["1:drop >= 10%"] - 85.0).abs() < 1e-9);
+
+        // The drop is no longer "since the last check", but still gets reported
+        let report = run_check(&[status(e.clone(), Some(86.0))], &mut state, &alerts, after);
+        assert_eq!(report.alerts(), 1);
+        assert_eq!(
+            report.changes.last().unwrap().kind,
+            ChangeKind::RuleMatched { rule: "drop >= 10%".into(), price: 85.0 }
+        );
+        assert!(state.held.is_empty());
+        let later = after + chrono::TimeDelta::days(1);
+        let report = run_check(&[status(e, Some(86.0))], &mut state, &alerts, later);
+        assert_eq!(report.alerts(), 0);
+    }
+
+    #[test]
+    fn deal_rules() {
+        let deals: Vec<Deal> = serde_json::from_value(serde_json::json!([
+            { "id": 1, "name": "TV", "priceMin": 499.0, "totalOffers": 15, "badges": { "discountPercentage": 45 }, "tags": {} },
+            { "id": 2, "name": "Phone", "priceMin": 299.0, "totalOffers": 3, "badges": { "discountPercentage": 50 }, "tags": {} }
+        ]))
+        .unwrap();
+        let rules = vec![Rule::new("discount >= 40 and stores >= 10".parse().unwrap())];
+        let mut state = CheckState::default();
+        let now = Utc::now();
+
+        let report = run_deal_rules(&deals, &rules, &mut state, &AlertsConfig::default(), now);
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.alerts(), 1);
+        assert_eq!(report.changes[0].label, "TV");
+
+        let report = run_deal_rules(&deals, &rules, &mut state, &AlertsConfig::default(), now);
+        assert_eq!(report.alerts(), 0);
+    }
+
+    #[test]
+    fn state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut state = CheckState::load(&path).unwrap();
+        state.products.insert(1, observed(10.0, 2));
+        state.save(&path).unwrap();
+        assert_eq!(CheckState::load(&path).unwrap(), state);
+    }
+}
@@ -1,18 +1,27 @@
 //! Browse command - returns popular products
 
+use super::ListOutcome;
 use crate::api::KuantoKustaClient;
 use crate::format::{format_products, OutputFormat};
 use anyhow::Result;
+use std::path::Path;
 
 /// Execute browse command (popular products)
 /// Note: `KuantoKusta` search is SSR-only, this returns popular products instead
-pub async fn browse(client: &KuantoKustaClient, max: u32, format: OutputFormat) -> Result<String> {
+pub async fn browse(
+    client: &KuantoKustaClient,
+    max: u32,
+    snapshot: Option<&Path>,
+    format: OutputFormat,
+) -> Result<ListOutcome> {
     let response = client.products(max).await?;
 
     let header = format!("Popular products ({} total):\n\n", response.total);
     let products = format_products(&response.data, format);
+    let items = response.data.iter().map(Into::into).collect();
+    let snapshot = super::save_snapshot(snapshot, "browse", "", items)?;
 
-    Ok(format!("{header}{products}"))
+    Ok(ListOutcome { output: format!("{header}{products}"), snapshot })
 }
 
 #[cfg(test)]
@@ -48,10 +57,10 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = browse(&client, 20, OutputFormat::Table).await;
+        let result = browse(&client, 20, None, OutputFormat::Table).await;
 
         assert!(result.is_ok());
-        let output = result.unwrap();
+        let output = result.unwrap().output;
         assert!(output.contains("Popular products"));
         assert!(output.contains("Test Product"));
     }
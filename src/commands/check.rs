@@ -0,0 +1,284 @@
+//! Check command
+
+use super::FETCH_CONCURRENCY;
+use crate::api::{history_window, KuantoKustaClient};
+use crate::check::{run_check, CheckReport, CheckState, Observed};
+use crate::config::Config;
+use crate::format::{format_check, OutputFormat};
+use crate::notify::{notify_alerts, run_hooks, Alert};
+use crate::rules::AlertsConfig;
+use crate::store;
+use crate::watchlist::{WatchEntry, Watchlist};
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Output of `kk check` and the number of alerts that fired
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    pub output: String,
+    pub alerts: usize,
+}
+
+/// A check of the watchlist, with its alerts ready to notify
+#[derive(Debug, Clone)]
+pub struct CheckRun {
+    pub report: CheckReport,
+    pub alerts: Vec<Alert>,
+    /// What was fetched, for publishing (empty for deal scans)
+    pub observed: Vec<Observed>,
+    /// State after the run, saved once its alerts are delivered
+    pub state: CheckState,
+}
+
+/// Check the watchlist against the last recorded state and report what changed
+///
+/// Alerts go to the configured notification targets and prices to the MQTT
+/// broker, unless the check isn't saved.
+pub async fn check(
+    client: &KuantoKustaClient,
+    watchlist: &Path,
+    state_path: &Path,
+    tag: Option<&str>,
+    save: bool,
+    config: &Config,
+    format: OutputFormat,
+) -> Result<CheckOutcome> {
+    let list = Watchlist::load(watchlist)?;
+    let entries: Vec<WatchEntry> = list.filtered(tag).into_iter().cloned().collect();
+    if entries.is_empty() {
+        return Ok(CheckOutcome {
+            output: "The watchlist is empty. Add products with `kk watch add <id|url>`."
+                .to_string(),
+            alerts: 0,
+        });
+    }
+
+    let run = check_watchlist(client, entries, state_path, &config.alerts).await?;
+    let report =
+        if save { publish_run(client, config, run, state_path).await? } else { run.report };
+    Ok(CheckOutcome { output: format_check(&report, format), alerts: report.alerts() })
+}
+
+/// Run a run's exec hooks, send its alerts to the notification targets and its
+/// prices to the MQTT broker, then save its state to `state_path`
+///
+/// Delivery failures are logged. The state is only saved afterwards, so alerts
+/// of a run that is interrupted before delivering them fire again next time.
+pub async fn publish_run(
+    client: &KuantoKustaClient,
+    config: &Config,
+    run: CheckRun,
+    state_path: &Path,
+) -> Result<CheckReport> {
+    let CheckRun { report, alerts, observed, state } = run;
+    log_alerts(client, &alerts);
+    run_hooks(&config.hooks, &alerts).await;
+    notify_alerts(client, &config.notify, alerts).await;
+    if let Some(mqtt) = &config.mqtt {
+        if let Err(e) = crate::mqtt::publish(mqtt, &observed).await {
+            tracing::error!("{e:#}");
+        }
+    }
+    state.save(state_path)?;
+    Ok(report)
+}
+
+/// Keep `alerts` in the client's local store for `kk feed alerts`; failures are logged
+pub fn log_alerts(client: &KuantoKustaClient, alerts: &[Alert]) {
+    if let Some(shared) = client.store().filter(|_| !alerts.is_empty()) {
+        let recorded = store::lock(shared).record_alerts(alerts, chrono::Utc::now());
+        if let Err(e) = recorded {
+            tracing::warn!("{e:#}");
+        }
+    }
+}
+
+/// Fetch and evaluate `entries` against the state in `state_path`; the new
+/// state is saved by [`publish_run`]
+pub async fn check_watchlist(
+    client: &KuantoKustaClient,
+    entries: Vec<WatchEntry>,
+    state_path: &Path,
+    alerts: &AlertsConfig,
+) -> Result<CheckRun> {
+    let mut state = CheckState::load(state_path)?;
+    let observed = fetch_observed(client, entries, alerts).await;
+    let report = run_check(&observed, &mut state, alerts, chrono::Utc::now());
+    let products: Vec<_> = observed.iter().filter_map(|o| o.product.as_ref()).collect();
+    let mut fired = Alert::from_check(&report, &products);
+    for alert in &mut fired {
+        if let Some(item) = observed.iter().find(|o| o.entry.id == alert.product_id) {
+            alert.set_exec(item.entry.rules.iter().chain(&alerts.rules));
+        }
+    }
+    Ok(CheckRun { report, alerts: fired, observed, state })
+}
+
+/// Fetch each entry's product, plus its price history when a rule needs one,
+/// at most [`FETCH_CONCURRENCY`] at a time
+async fn fetch_observed(
+    client: &KuantoKustaClient,
+    entries: Vec<WatchEntry>,
+    alerts: &AlertsConfig,
+) -> Vec<Observed> {
+    let semaphore = Arc::new(Semaphore::new(FETCH_CONCURRENCY));
+    let handles: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let (client, semaphore) = (client.clone(), Arc::clone(&semaphore));
+            let id = entry.id;
+            let history_days = entry
+                .rules
+                .iter()
+                .chain(&alerts.rules)
+                .filter_map(|rule| rule.when.expr().history_days())
+                .max();
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let product = client.product(id).await?;
+                let history = match history_days {
+                    Some(days) => match client.price_history(id, history_window(days)).await {
+                        Ok(history) => Some(history),
+                        Err(e) => {
+                            tracing::warn!("{e:#}");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                anyhow::Ok((product, history))
+            })
+        })
+        .collect();
+
+    let mut observed = Vec::with_capacity(entries.len());
+    for (handle, entry) in handles.into_iter().zip(entries) {
+        let (product, history) = match handle.await {
+            Ok(Ok((product, history))) => (Some(product), history),
+            Ok(Err(e)) => {
+                tracing::warn!("{e:#}");
+                (None, None)
+            }
+            Err(e) => {
+                tracing::warn!("Price fetch task for product {} failed: {e}", entry.id);
+                (None, None)
+            }
+        };
+        observed.push(Observed { entry, product, history });
+    }
+    observed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_price(server: &MockServer, price: f64, offers: u32) {
+        server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 12345,
+                "name": "PlayStation 5",
+                "priceMin": price,
+                "totalOffers": offers,
+                "badges": {},
+                "tags": {}
+            })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_changes() {
+        let mock_server = MockServer::start().await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let list = dir.path().join("watchlist.toml");
+        let state = dir.path().join("state.json");
+        super::super::watch_add(
+            &client,
+            &list,
+            "12345",
+            Some("PS5".to_string()),
+            vec![],
+            Some(420.0),
+            None,
+        )
+        .await
+        .unwrap();
+
+        mock_price(&mock_server, 449.99, 12).await;
+        let outcome =
+            check(&client, &list, &state, None, true, &Config::default(), OutputFormat::Table)
+                .await
+                .unwrap();
+        assert_eq!(outcome.alerts, 0);
+        assert!(outcome.output.contains("No changes"));
+
+        mock_price(&mock_server, 399.99, 14).await;
+        let outcome =
+            check(&client, &list, &state, None, false, &Config::default(), OutputFormat::Compact)
+                .await
+                .unwrap();
+        assert_eq!(outcome.alerts, 1);
+        let lines: Vec<&str> = outcome.output.lines().collect();
+        assert_eq!(lines[0], "12345\tdrop\t449.99\t399.99\t-11.1%\t\tPS5");
+        assert_eq!(lines[1], "12345\toffers\t12\t14\t\t\tPS5");
+        assert_eq!(lines[2], "12345\ttarget\t420.00\t399.99\t\t!\tPS5");
+
+        // --no-save left the state untouched, so the same changes are reported again
+        let outcome =
+            check(&client, &list, &state, None, true, &Config::default(), OutputFormat::Json)
+                .await
+                .unwrap();
+        assert_eq!(outcome.alerts, 1);
+        let json: serde_json::Value = serde_json::from_str(&outcome.output).unwrap();
+        assert_eq!(json["changes"][2]["kind"], "targetReached");
+        assert_eq!(json["changes"][2]["alert"], true);
+
+        let outcome =
+            check(&client, &list, &state, None, true, &Config::default(), OutputFormat::Table)
+                .await
+                .unwrap();
+        assert_eq!(outcome.alerts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_notifies_alerts() {
+        let mock_server = MockServer::start().await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let list = dir.path().join("watchlist.toml");
+        let state = dir.path().join("state.json");
+        mock_price(&mock_server, 399.99, 12).await;
+        super::super::watch_add(&client, &list, "12345", None, vec![], Some(420.0), None)
+            .await
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(wiremock::matchers::body_partial_json(serde_json::json!({
+                "title": "kk: PlayStation 5",
+                "alerts": [{ "productId": 12345, "newPrice": 399.99 }]
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let config: Config = toml::from_str(&format!(
+            "[notify.team]\ntype = \"webhook\"\nurl = \"{}/hook\"\n",
+            mock_server.uri()
+        ))
+        .unwrap();
+        // Unsaved checks don't notify
+        check(&client, &list, &state, None, false, &config, OutputFormat::Json).await.unwrap();
+        let outcome =
+            check(&client, &list, &state, None, true, &config, OutputFormat::Json).await.unwrap();
+        assert_eq!(outcome.alerts, 1);
+    }
+}
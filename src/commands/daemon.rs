@@ -0,0 +1,498 @@
+//! Daemon commands
+
+use super::CheckRun;
+use crate::api::KuantoKustaClient;
+use crate::check::{run_deal_rules, CheckReport, CheckState};
+use crate::config::Config;
+use crate::daemon::{DaemonStatus, JobStatus, Schedule};
+use crate::format::{format_check, format_daemon_status, format_search_diffs, OutputFormat};
+use crate::notify::{notify_alerts, send_to, Alert};
+use crate::searches::SavedSearches;
+use crate::store;
+use crate::watchlist::{WatchEntry, Watchlist};
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Files used by `kk daemon`
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+    pub config_path: PathBuf,
+    pub watchlist: PathBuf,
+    pub state: PathBuf,
+    pub searches: PathBuf,
+    /// Run every enabled job once and exit
+    pub once: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobKind {
+    Check,
+    Deals,
+    Digest,
+    Searches,
+    Dashboard,
+}
+
+impl JobKind {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::Deals => "deals",
+            Self::Digest => "digest",
+            Self::Searches => "searches",
+            Self::Dashboard => "dashboard",
+        }
+    }
+}
+
+struct Job {
+    kind: JobKind,
+    schedule: Schedule,
+    next: DateTime<Local>,
+    status: JobStatus,
+}
+
+/// Run the scheduled jobs until SIGTERM or Ctrl-C; SIGHUP reloads the config
+pub async fn daemon_run(client: &KuantoKustaClient, opts: &DaemonOptions) -> Result<String> {
+    let config = Config::load(&opts.config_path)?;
+    let mut daemon = Daemon::new(client, opts, config)?;
+
+    if opts.once {
+        let mut summary = Vec::new();
+        for idx in 0..daemon.jobs.len() {
+            let status = daemon.run_job(idx).await;
+            summary.push(format!("{}: {}", status.name, status.last_result.unwrap_or_default()));
+        }
+        daemon.after_cycle();
+        daemon.write_status(false);
+        return Ok(summary.join("\n"));
+    }
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    daemon.write_status(true);
+
+    loop {
+        let Some(next) = daemon.jobs.iter().map(|j| j.next).min() else {
+            anyhow::bail!("No daemon jobs are enabled; see [daemon] in the config");
+        };
+        let wait = (next - Local::now()).to_std().unwrap_or(Duration::ZERO);
+
+        tokio::select! {
+            () = tokio::time::sleep(wait) => {}
+            _ = sigterm.recv() => break,
+            _ = tokio::signal::ctrl_c() => break,
+            _ = sighup.recv() => {
+                daemon.reload();
+                continue;
+            }
+        }
+
+        let now = Local::now();
+        for idx in 0..daemon.jobs.len() {
+            if daemon.jobs[idx].next <= now {
+                daemon.run_job(idx).await;
+            }
+        }
+        daemon.after_cycle();
+    }
+
+    daemon.write_status(false);
+    Ok("kk daemon stopped".to_string())
+}
+
+/// Show what the daemon is doing, from its status file
+pub fn daemon_status(config_path: &Path, format: OutputFormat) -> Result<String> {
+    let config = Config::load(config_path)?;
+    let path = config.daemon.status_file.unwrap_or_else(DaemonStatus::default_path);
+    let status = DaemonStatus::load(&path)?;
+    Ok(format_daemon_status(&status, format))
+}
+
+struct Daemon<'a> {
+    /// The client as configured by the caller; rate limits are applied on top
+    base: &'a KuantoKustaClient,
+    client: KuantoKustaClient,
+    opts: &'a DaemonOptions,
+    config: Config,
+    jobs: Vec<Job>,
+    started_at: DateTime<Utc>,
+}
+
+impl<'a> Daemon<'a> {
+    fn new(base: &'a KuantoKustaClient, opts: &'a DaemonOptions, config: Config) -> Result<Self> {
+        let mut daemon = Self {
+            base,
+            client: base.clone(),
+            opts,
+            config: Config::default(),
+            jobs: Vec::new(),
+            started_at: Utc::now(),
+        };
+        daemon.configure(config)?;
+        Ok(daemon)
+    }
+
+    /// Apply `config`, keeping the run statistics of jobs that are still enabled
+    /// and the next run of those whose schedule didn't change
+    fn configure(&mut self, config: Config) -> Result<()> {
+        let now = Local::now();
+        let mut schedules = Vec::new();
+        if config.daemon.check.enabled {
+            schedules.push((JobKind::Check, config.daemon.check.schedule.clone()));
+        }
+        if let Some(deals) = &config.daemon.deals {
+            schedules.push((JobKind::Deals, deals.schedule.clone()));
+        }
+        if let Some(digest) = &config.daemon.digest {
+            schedules.push((JobKind::Digest, digest.schedule.clone()));
+        }
+        if let Some(searches) = &config.daemon.searches {
+            schedules.push((JobKind::Searches, searches.schedule.clone()));
+        }
+        if let Some(dashboard) = &config.daemon.dashboard {
+            schedules.push((JobKind::Dashboard, dashboard.schedule.clone()));
+        }
+
+        let mut jobs = Vec::with_capacity(schedules.len());
+        for (kind, schedule) in schedules {
+            let previous = self.jobs.iter().find(|j| j.kind == kind);
+            // New interval jobs start right away; new cron jobs wait for their first slot
+            let next = match (previous, &schedule) {
+                (Some(job), _) if job.schedule.to_string() == schedule.to_string() => job.next,
+                (_, Schedule::Every(_)) => now,
+                (_, Schedule::Cron(_)) => schedule.next_after(now)?,
+            };
+            let mut status = previous
+                .map_or_else(|| JobStatus::new(kind.name(), &schedule), |j| j.status.clone());
+            status.schedule = schedule.to_string();
+            status.next_run = Some(next.with_timezone(&Utc));
+            jobs.push(Job { kind, schedule, next, status });
+        }
+
+        self.client = self
+            .base
+            .clone()
+            .with_rate_limit(Duration::from_millis(config.request.delay_ms))
+            .with_cache(Duration::from_secs(config.request.cache_secs));
+        self.jobs = jobs;
+        self.config = config;
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        let result = Config::load(&self.opts.config_path).and_then(|config| self.configure(config));
+        match result {
+            Ok(()) => tracing::info!("Reloaded {}", self.opts.config_path.display()),
+            Err(e) => tracing::error!("Keeping the previous configuration: {e:#}"),
+        }
+        self.write_status(true);
+    }
+
+    /// Run one job, record the outcome and schedule its next run
+    async fn run_job(&mut self, idx: usize) -> JobStatus {
+        let kind = self.jobs[idx].kind;
+        let started = Instant::now();
+        let result = match kind {
+            JobKind::Check | JobKind::Deals => self.checks(kind).await,
+            JobKind::Digest => self.digest().await.map(|summary| (summary, 0)),
+            JobKind::Searches => self.searches().await,
+            JobKind::Dashboard => self.dashboard().map(|summary| (summary, 0)),
+        };
+
+        let now = Local::now();
+        let job = &mut self.jobs[idx];
+        job.status.runs += 1;
+        job.status.last_run = Some(now.with_timezone(&Utc));
+        job.status.last_duration_ms =
+            Some(u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX));
+        match result {
+            Ok((summary, alerts)) => {
+                job.status.alerts += alerts as u64;
+                job.status.last_ok = true;
+                job.status.last_result = Some(summary);
+            }
+            Err(e) => {
+                tracing::error!("Daemon job {} failed: {e:#}", kind.name());
+                job.status.failures += 1;
+                job.status.last_ok = false;
+                job.status.last_result = Some(format!("{e:#}"));
+            }
+        }
+        match job.schedule.next_after(now) {
+            Ok(next) => {
+                job.next = next;
+                job.status.next_run = Some(next.with_timezone(&Utc));
+            }
+            Err(e) => {
+                tracing::error!("Daemon job {} won't run again: {e:#}", kind.name());
+                job.next = now + chrono::TimeDelta::weeks(52 * 100);
+                job.status.next_run = None;
+            }
+        }
+
+        let status = job.status.clone();
+        self.write_status(true);
+        status
+    }
+
+    /// Run the check or deals job and publish what it found; returns its
+    /// summary and number of alerts
+    async fn checks(&self, kind: JobKind) -> Result<(String, usize)> {
+        let run = if kind == JobKind::Deals { self.deals().await? } else { self.check().await? };
+        if !run.report.changes.is_empty() {
+            tracing::info!("{}", format_check(&run.report, OutputFormat::Compact));
+        }
+        let report = super::publish_run(&self.client, &self.config, run, &self.opts.state).await?;
+        Ok((summary(&report), report.alerts()))
+    }
+
+    async fn check(&self) -> Result<CheckRun> {
+        let job = &self.config.daemon.check;
+        let list = Watchlist::load(&self.opts.watchlist)?;
+        let entries: Vec<WatchEntry> =
+            list.filtered(job.tag.as_deref()).into_iter().cloned().collect();
+        super::check_watchlist(&self.client, entries, &self.opts.state, &self.config.alerts).await
+    }
+
+    async fn deals(&self) -> Result<CheckRun> {
+        let Some(job) = &self.config.daemon.deals else {
+            anyhow::bail!("The deals job is not configured");
+        };
+        let deals = self.client.deals(job.max, 1, job.min_discount, None, None).await?.data;
+        let mut state = CheckState::load(&self.opts.state)?;
+        let report =
+            run_deal_rules(&deals, &job.rules, &mut state, &self.config.alerts, Utc::now());
+        let mut alerts = Alert::from_deals(&report, &deals);
+        for alert in &mut alerts {
+            alert.set_exec(&job.rules);
+        }
+        Ok(CheckRun { report, alerts, observed: Vec::new(), state })
+    }
+
+    async fn digest(&self) -> Result<String> {
+        let Some(job) = &self.config.daemon.digest else {
+            anyhow::bail!("The digest job is not configured");
+        };
+        let Some(shared) = self.client.store() else {
+            anyhow::bail!("The digest job needs the local price store");
+        };
+        let digest = super::build_digest(
+            &store::lock(shared),
+            &self.opts.watchlist,
+            job.tag.as_deref(),
+            job.period,
+        )?;
+        let names = if job.notify.is_empty() {
+            self.config.notify.keys().cloned().collect()
+        } else {
+            job.notify.clone()
+        };
+        send_to(&self.config.notify, &names, &digest.message()).await?;
+        Ok(format!("{}; sent to {} target(s)", digest.summary(), names.len()))
+    }
+
+    /// Regenerate the static dashboard from the local store
+    fn dashboard(&self) -> Result<String> {
+        let Some(job) = &self.config.daemon.dashboard else {
+            anyhow::bail!("The dashboard job is not configured");
+        };
+        let Some(shared) = self.client.store() else {
+            anyhow::bail!("The dashboard job needs the local price store");
+        };
+        let opts = super::DashboardOptions {
+            watchlist: self.opts.watchlist.clone(),
+            tag: job.tag.clone(),
+            out: job.out.clone().unwrap_or_else(crate::paths::site_dir),
+        };
+        super::write_dashboard(&store::lock(shared), &opts)
+    }
+
+    /// Run the saved searches and alert on new products and price drops
+    async fn searches(&self) -> Result<(String, usize)> {
+        let Some(job) = &self.config.daemon.searches else {
+            anyhow::bail!("The searches job is not configured");
+        };
+        let saved = SavedSearches::load(&self.opts.searches)?;
+        let selected = if job.searches.is_empty() {
+            saved.entries
+        } else {
+            job.searches.iter().map(|name| saved.get(name).cloned()).collect::<Result<Vec<_>>>()?
+        };
+        let diffs = super::run_searches(&self.client, &selected, true).await?;
+        if diffs.iter().any(|d| !d.is_empty()) {
+            tracing::info!("{}", format_search_diffs(&diffs, OutputFormat::Compact));
+        }
+        let alerts: Vec<Alert> = diffs.iter().flat_map(Alert::from_search).collect();
+        let count = alerts.len();
+        super::log_alerts(&self.client, &alerts);
+        notify_alerts(&self.client, &self.config.notify, alerts).await;
+        let changed = diffs.iter().filter(|d| !d.is_empty()).count();
+        Ok((format!("{} search(es), {changed} changed, {count} alert(s)", diffs.len()), count))
+    }
+
+    /// Housekeeping after a round of jobs
+    fn after_cycle(&self) {
+        if let Some(shared) = self.client.store() {
+            let result = store::lock(shared).auto_prune(&self.config.retention, store::today());
+            if let Err(e) = result {
+                tracing::warn!("Automatic retention failed: {e:#}");
+            }
+        }
+    }
+
+    fn write_status(&self, running: bool) {
+        let status = DaemonStatus {
+            pid: std::process::id(),
+            running,
+            started_at: self.started_at,
+            updated_at: Utc::now(),
+            config: self.opts.config_path.display().to_string(),
+            jobs: self.jobs.iter().map(|j| j.status.clone()).collect(),
+        };
+        let path =
+            self.config.daemon.status_file.clone().unwrap_or_else(DaemonStatus::default_path);
+        if let Err(e) = status.save(&path) {
+            tracing::warn!("Failed to write daemon status: {e:#}");
+        }
+    }
+}
+
+fn summary(report: &CheckReport) -> String {
+    let mut out = format!(
+        "{} checked, {} change(s), {} alert(s)",
+        report.checked,
+        report.changes.len(),
+        report.alerts()
+    );
+    if !report.failed.is_empty() {
+        out.push_str(&format!(", {} failed", report.failed.len()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn reload_keeps_unchanged_schedules() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = DaemonOptions {
+            config_path: dir.path().join("config.toml"),
+            watchlist: dir.path().join("watchlist.toml"),
+            state: dir.path().join("state.json"),
+            searches: dir.path().join("searches.toml"),
+            once: false,
+        };
+        let config = |deals: &str| -> Config {
+            toml::from_str(&format!(
+                "[daemon.check]\nschedule = \"every 30m\"\n[daemon.deals]\nschedule = \"{deals}\"\n"
+            ))
+            .unwrap()
+        };
+        let client = KuantoKustaClient::new().unwrap();
+        let mut daemon = Daemon::new(&client, &opts, config("every 1h")).unwrap();
+        let later = Local::now() + chrono::TimeDelta::minutes(20);
+        for job in &mut daemon.jobs {
+            job.next = later;
+        }
+
+        daemon.configure(config("every 2h")).unwrap();
+        assert_eq!(daemon.jobs[0].kind, JobKind::Check);
+        assert_eq!(daemon.jobs[0].next, later);
+        assert!(daemon.jobs[1].next < later);
+    }
+
+    #[tokio::test]
+    async fn test_daemon_once() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 12345,
+                "name": "PlayStation 5",
+                "priceMin": 449.99,
+                "totalOffers": 12,
+                "badges": {},
+                "tags": {}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/deals"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{ "id": 7, "name": "TV", "priceMin": 499.0, "totalOffers": 15,
+                           "badges": { "discountPercentage": 45 }, "tags": {} }],
+                "page": 1, "rows": 1, "total": 1
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let status_path = dir.path().join("daemon.json");
+        std::fs::write(
+            &config_path,
+            format!(
+                "[request]\ndelay_ms = 0\n[daemon]\nstatus_file = {:?}\n\
+                 [daemon.deals]\nschedule = \"0 8 * * *\"\n\
+                 [[daemon.deals.rules]]\nwhen = \"discount >= 40\"\n\
+                 [daemon.digest]\nschedule = \"0 9 * * 1\"\n\
+                 [daemon.dashboard]\nschedule = \"every 1h\"\nout = {:?}\n\
+                 [notify.team]\ntype = \"webhook\"\nurl = \"{}/hook\"\nalerts = false\n",
+                status_path.display().to_string(),
+                dir.path().join("www").display().to_string(),
+                mock_server.uri()
+            ),
+        )
+        .unwrap();
+        let opts = DaemonOptions {
+            config_path: config_path.clone(),
+            watchlist: dir.path().join("watchlist.toml"),
+            state: dir.path().join("state.json"),
+            searches: dir.path().join("searches.toml"),
+            once: true,
+        };
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri())
+            .unwrap()
+            .with_store(store::Store::open_in_memory().unwrap());
+        super::super::watch_add(&client, &opts.watchlist, "12345", None, vec![], None, None)
+            .await
+            .unwrap();
+
+        let output = daemon_run(&client, &opts).await.unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "check: 1 checked, 0 change(s), 0 alert(s)\n\
+                 deals: 1 checked, 1 change(s), 1 alert(s)\n\
+                 digest: 1 product(s), 0 drop(s), 0 at all-time low, 0 target(s) reached, \
+                 0 new deal(s), 0 new in saved searches; sent to 1 target(s)\n\
+                 dashboard: Wrote 2 page(s) to {}",
+                dir.path().join("www").display()
+            )
+        );
+
+        let status = DaemonStatus::load(&status_path).unwrap();
+        assert!(!status.running);
+        assert_eq!(status.jobs.len(), 4);
+        assert_eq!(status.jobs[1].alerts, 1);
+        assert!(status.jobs[1].next_run.is_some());
+        assert!(CheckState::load(&opts.state).unwrap().products.contains_key(&12345));
+
+        let output = daemon_status(&config_path, OutputFormat::Compact).unwrap();
+        assert!(output.starts_with("check\t"));
+    }
+}
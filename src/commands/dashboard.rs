@@ -0,0 +1,63 @@
+//! Dashboard command
+
+use crate::dashboard::Dashboard;
+use crate::store::Store;
+use crate::watchlist::{WatchEntry, Watchlist};
+use anyhow::Result;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+
+/// What `kk dashboard` shows and where it goes
+#[derive(Debug, Clone)]
+pub struct DashboardOptions {
+    pub watchlist: PathBuf,
+    /// Only include products with this tag
+    pub tag: Option<String>,
+    /// Directory the site is written to
+    pub out: PathBuf,
+}
+
+/// Generate the static dashboard from the local store at `db`
+pub fn dashboard(db: &Path, opts: &DashboardOptions) -> Result<String> {
+    write_dashboard(&Store::open_existing(db)?, opts)
+}
+
+/// Write the dashboard of the watchlist in `opts` from `store`
+pub fn write_dashboard(store: &Store, opts: &DashboardOptions) -> Result<String> {
+    let list = Watchlist::load(&opts.watchlist)?;
+    let entries: Vec<WatchEntry> =
+        list.filtered(opts.tag.as_deref()).into_iter().cloned().collect();
+    let pages = Dashboard::build(store, &entries, Local::now())?.write(&opts.out)?;
+    Ok(format!("Wrote {pages} page(s) to {}", opts.out.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Product;
+
+    #[test]
+    fn test_dashboard_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let product = Product::fixture(1, 449.0).with_name("PS5").with_offers(10);
+        Store::open(&db).unwrap().record_products(&[product], crate::store::today()).unwrap();
+        let watchlist = dir.path().join("watchlist.toml");
+        std::fs::write(
+            &watchlist,
+            "[[products]]\nid = 1\nlabel = \"PS5\"\ntags = [\"console\"]\nadded = \"2024-06-01\"\n\n\
+             [[products]]\nid = 2\nlabel = \"TV\"\nadded = \"2024-06-01\"\n",
+        )
+        .unwrap();
+
+        let out = dir.path().join("site");
+        let opts =
+            DashboardOptions { watchlist, tag: Some("console".to_string()), out: out.clone() };
+        let output = dashboard(&db, &opts).unwrap();
+        assert_eq!(output, format!("Wrote 2 page(s) to {}", out.display()));
+        assert!(out.join("products/1.html").exists());
+        assert!(!out.join("products/2.html").exists());
+
+        assert!(dashboard(&dir.path().join("missing.db"), &opts).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! Local price database maintenance commands
+
+use crate::format::{format_bytes, format_db_stats, OutputFormat};
+use crate::store::dataset::{read_rows, write_rows, DataFormat, ExportFilter};
+use crate::store::{self, RetentionPolicy, Store};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Invalid rows listed before the rest are summarised
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// Options for `kk db import`
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    pub path: PathBuf,
+    /// Dataset format (guessed from the extension when `None`)
+    pub format: Option<DataFormat>,
+    /// Overwrite prices the store already has for the same product and day
+    pub replace: bool,
+    /// Import valid rows even if some rows are invalid
+    pub skip_invalid: bool,
+    /// Validate only, don't write anything
+    pub dry_run: bool,
+}
+
+/// Options for `kk db export`
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Output file (stdout when `None`)
+    pub output: Option<PathBuf>,
+    /// Dataset format (guessed from the output extension, CSV otherwise)
+    pub format: Option<DataFormat>,
+    pub filter: ExportFilter,
+}
+
+/// Import a CSV or NDJSON dataset of daily prices
+pub fn db_import(db: &Path, opts: &ImportOptions) -> Result<String> {
+    let format = resolve_format(opts.format, &opts.path)?;
+    let parsed = read_rows(&opts.path, format, store::today())?;
+
+    if !parsed.invalid.is_empty() && !opts.skip_invalid {
+        let mut msg =
+            format!("{} invalid row(s) in {}:\n", parsed.invalid.len(), opts.path.display());
+        for err in parsed.invalid.iter().take(MAX_REPORTED_ERRORS) {
+            msg.push_str(&format!("  {err}\n"));
+        }
+        if parsed.invalid.len() > MAX_REPORTED_ERRORS {
+            msg.push_str(&format!(
+                "  ... and {} more\n",
+                parsed.invalid.len() - MAX_REPORTED_ERRORS
+            ));
+        }
+        msg.push_str("Nothing was imported; fix the rows or pass --skip-invalid");
+        anyhow::bail!(msg);
+    }
+
+    let invalid = if parsed.invalid.is_empty() {
+        String::new()
+    } else {
+        format!(", {} invalid row(s) ignored", parsed.invalid.len())
+    };
+
+    if opts.dry_run {
+        return Ok(format!("{} valid row(s) ready to import{invalid}", parsed.rows.len()));
+    }
+
+    let mut store = Store::open(db)?;
+    let report = store.import_prices(&parsed.rows, opts.replace)?;
+
+    Ok(format!(
+        "Imported {} row(s) into {}: {} new, {} replaced, {} already present{invalid}",
+        parsed.rows.len(),
+        db.display(),
+        report.inserted,
+        report.replaced,
+        report.skipped
+    ))
+}
+
+/// Export daily prices; returns the data itself when writing to stdout
+pub fn db_export(db: &Path, opts: &ExportOptions) -> Result<String> {
+    let format = match (opts.format, &opts.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => resolve_format(None, path)?,
+        (None, None) => DataFormat::Csv,
+    };
+
+    let store = Store::open_read_only(db)?;
+    let rows = store.export_prices(&opts.filter)?;
+
+    let Some(path) = &opts.output else {
+        if format == DataFormat::Parquet {
+            anyhow::bail!("Parquet export needs an output file (--output FILE)");
+        }
+        let mut buf = Vec::new();
+        write_rows(&rows, format, &mut buf)?;
+        return Ok(String::from_utf8(buf)?.trim_end().to_string());
+    };
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    write_rows(&rows, format, file)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(format!("Exported {} row(s) to {}", rows.len(), path.display()))
+}
+
+/// Show row counts, size and date coverage of the local database
+pub fn db_stats(db: &Path, format: OutputFormat) -> Result<String> {
+    let store = Store::open_read_only(db)?;
+    Ok(format_db_stats(&store.stats()?, format))
+}
+
+/// Apply a retention policy to the local database
+pub fn db_prune(db: &Path, policy: &RetentionPolicy, dry_run: bool) -> Result<String> {
+    let mut store = Store::open_existing(db)?;
+    let report = store.prune(policy, store::today(), dry_run)?;
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    let mut out = format!("{verb} {} row(s)", report.total());
+    let tables: Vec<String> = report
+        .removed
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(table, n)| format!("{table}: {n}"))
+        .collect();
+    if !tables.is_empty() {
+        out.push_str(&format!(" ({})", tables.join(", ")));
+    }
+    Ok(out)
+}
+
+/// Compact the local database file
+pub fn db_vacuum(db: &Path) -> Result<String> {
+    let (before, after) = Store::open_existing(db)?.vacuum()?;
+    Ok(format!("Vacuumed {}: {} -> {}", db.display(), format_bytes(before), format_bytes(after)))
+}
+
+/// Write a consistent copy of the local database
+pub fn db_backup(db: &Path, dest: &Path) -> Result<String> {
+    Store::open_existing(db)?.backup(dest)?;
+    Ok(format!("Backed up {} to {}", db.display(), dest.display()))
+}
+
+fn resolve_format(format: Option<DataFormat>, path: &Path) -> Result<DataFormat> {
+    format.or_else(|| DataFormat::from_path(path)).with_context(|| {
+        format!("Can't tell the format of {}; pass --type csv|ndjson|parquet", path.display())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import_options(path: PathBuf) -> ImportOptions {
+        ImportOptions { path, format: None, replace: false, skip_invalid: false, dry_run: false }
+    }
+
+    #[test]
+    fn test_import_then_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let csv = dir.path().join("old.csv");
+        std::fs::write(&csv, "product_id,date,min,avg\n1,2023-01-01,10,12\n1,2023-01-02,9,9\n")
+            .unwrap();
+
+        let dry = db_import(&db, &ImportOptions { dry_run: true, ..import_options(csv.clone()) });
+        assert_eq!(dry.unwrap(), "2 valid row(s) ready to import");
+        assert!(!db.exists());
+
+        let output = db_import(&db, &import_options(csv.clone())).unwrap();
+        assert!(output.contains("2 new, 0 replaced, 0 already present"));
+        let output = db_import(&db, &import_options(csv)).unwrap();
+        assert!(output.contains("0 new, 0 replaced, 2 already present"));
+
+        let export = ExportOptions { output: None, format: None, filter: ExportFilter::default() };
+        let data = db_export(&db, &export).unwrap();
+        assert_eq!(data.lines().count(), 3);
+
+        let path = dir.path().join("out.ndjson");
+        let export = ExportOptions { output: Some(path.clone()), ..export };
+        assert_eq!(
+            db_export(&db, &export).unwrap(),
+            format!("Exported 2 row(s) to {}", path.display())
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let csv = dir.path().join("bad.csv");
+        std::fs::write(&csv, "product_id,date,min\n1,2023-01-01,10\n1,nope,9\n").unwrap();
+
+        let err = db_import(&db, &import_options(csv.clone())).unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+        assert!(!db.exists());
+
+        let output =
+            db_import(&db, &ImportOptions { skip_invalid: true, ..import_options(csv) }).unwrap();
+        assert!(output.contains("1 new"));
+        assert!(output.contains("1 invalid row(s) ignored"));
+    }
+
+    #[test]
+    fn test_maintenance_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let csv = dir.path().join("old.csv");
+        std::fs::write(&csv, "product_id,date,min\n1,2020-01-01,10\n1,2020-01-02,9\n").unwrap();
+        db_import(&db, &import_options(csv)).unwrap();
+
+        let stats = db_stats(&db, OutputFormat::Compact).unwrap();
+        assert!(stats.starts_with("price_points\t2\t2020-01-01\t2020-01-02"));
+
+        let policy = RetentionPolicy::default();
+        assert_eq!(
+            db_prune(&db, &policy, true).unwrap(),
+            "Would remove 1 row(s) (price_points: 1)"
+        );
+        assert_eq!(db_prune(&db, &policy, false).unwrap(), "Removed 1 row(s) (price_points: 1)");
+        assert_eq!(db_prune(&db, &policy, false).unwrap(), "Removed 0 row(s)");
+
+        assert!(db_vacuum(&db).unwrap().starts_with("Vacuumed"));
+        let backup = dir.path().join("backup.db");
+        db_backup(&db, &backup).unwrap();
+        assert!(backup.exists());
+    }
+
+    #[test]
+    fn test_import_unknown_format() {
+        let err = db_import(Path::new("unused.db"), &import_options(PathBuf::from("prices.txt")))
+            .unwrap_err();
+        assert!(err.to_string().contains("--type"));
+    }
+}
@@ -1,24 +1,52 @@
 //! Deals command
 
+use super::ListOutcome;
 use crate::api::KuantoKustaClient;
 use crate::format::{format_deals, OutputFormat};
+use crate::seen::SeenDeals;
 use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+use std::path::{Path, PathBuf};
+
+/// Filters of the deals command
+#[derive(Debug, Clone, Default)]
+pub struct DealsOptions {
+    pub max: u32,
+    pub min_discount: Option<u8>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    /// Only list deals that are new or better than when last listed, keeping
+    /// the seen-set in this file
+    pub seen: Option<PathBuf>,
+    /// Forget deals not listed for this long (zero: never)
+    pub expire: TimeDelta,
+}
 
 /// Execute deals command
 pub async fn deals(
     client: &KuantoKustaClient,
-    max: u32,
-    min_discount: Option<u8>,
-    min_price: Option<f64>,
-    max_price: Option<f64>,
+    opts: &DealsOptions,
+    snapshot: Option<&Path>,
     format: OutputFormat,
-) -> Result<String> {
-    let response = client.deals(max, 1, min_discount, min_price, max_price).await?;
+) -> Result<ListOutcome> {
+    let response =
+        client.deals(opts.max, 1, opts.min_discount, opts.min_price, opts.max_price).await?;
 
-    let header = format!("Found {} deals:\n\n", response.total);
-    let deals = format_deals(&response.data, format);
+    let (header, data) = match &opts.seen {
+        Some(path) => {
+            let mut seen = SeenDeals::load(path)?;
+            let data = seen.retain_new(response.data, opts.expire, Utc::now());
+            seen.save(path)?;
+            (format!("Found {} new deals ({} total):\n\n", data.len(), response.total), data)
+        }
+        None => (format!("Found {} deals:\n\n", response.total), response.data),
+    };
+    let deals = format_deals(&data, format);
+    let args = opts.min_discount.map_or_else(String::new, |d| format!("≥ {d}%"));
+    let items = data.iter().map(Into::into).collect();
+    let snapshot = super::save_snapshot(snapshot, "deals", args, items)?;
 
-    Ok(format!("{header}{deals}"))
+    Ok(ListOutcome { output: format!("{header}{deals}"), snapshot })
 }
 
 #[cfg(test)]
@@ -54,13 +82,50 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = deals(&client, 20, None, None, None, OutputFormat::Table).await;
+        let opts = DealsOptions { max: 20, ..Default::default() };
+        let result = deals(&client, &opts, None, OutputFormat::Table).await;
 
         assert!(result.is_ok());
-        let output = result.unwrap();
+        let output = result.unwrap().output;
         assert!(output.contains("Found 1 deals"));
         assert!(output.contains("67890"));
         assert!(output.contains("Deal Product"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let listed = deals(&client, &opts, Some(dir.path()), OutputFormat::Compact).await.unwrap();
+        assert!(!listed.output.contains("Snapshot saved"));
+        let saved = std::fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+        assert_eq!(listed.snapshot, Some(saved.clone()));
+        let snapshot = crate::snapshot::Snapshot::load(&saved).unwrap();
+        assert_eq!(snapshot.command, "deals");
+        assert_eq!(snapshot.items[0].discount, Some(25));
+    }
+
+    #[tokio::test]
+    async fn test_deals_new_only() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/deals"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_deals_response()))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let opts = DealsOptions {
+            max: 20,
+            seen: Some(dir.path().join("seen-deals.json")),
+            expire: TimeDelta::weeks(1),
+            ..Default::default()
+        };
+        let output = deals(&client, &opts, None, OutputFormat::Compact).await.unwrap().output;
+        assert!(output.starts_with("Found 1 new deals (1 total):"));
+        assert!(output.contains("67890"));
+
+        let output = deals(&client, &opts, None, OutputFormat::Compact).await.unwrap().output;
+        assert!(output.starts_with("Found 0 new deals (1 total):"));
+        assert!(!output.contains("67890"));
     }
 
     #[tokio::test]
@@ -74,11 +139,17 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result =
-            deals(&client, 20, Some(10), Some(10.0), Some(100.0), OutputFormat::Json).await;
+        let opts = DealsOptions {
+            max: 20,
+            min_discount: Some(10),
+            min_price: Some(10.0),
+            max_price: Some(100.0),
+            ..Default::default()
+        };
+        let result = deals(&client, &opts, None, OutputFormat::Json).await;
 
         assert!(result.is_ok());
-        let output = result.unwrap();
+        let output = result.unwrap().output;
         assert!(output.contains("\"id\": 67890"));
     }
 }
@@ -0,0 +1,124 @@
+//! Digest command
+
+use crate::analysis::Period;
+use crate::digest::Digest;
+use crate::format::{format_digest, OutputFormat};
+use crate::notify::{send_to, Target};
+use crate::store::{self, Store};
+use crate::watchlist::{WatchEntry, Watchlist};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What `kk digest` summarizes and where it goes
+#[derive(Debug, Clone)]
+pub struct DigestOptions {
+    pub watchlist: PathBuf,
+    /// Only include products with this tag
+    pub tag: Option<String>,
+    pub period: Period,
+    /// Render HTML instead of Markdown
+    pub html: bool,
+    /// Also send the digest to these notification targets
+    pub notify: Vec<String>,
+}
+
+/// Summarize the last period of the watchlist and deals from the local store at `db`
+pub async fn digest(
+    db: &Path,
+    opts: &DigestOptions,
+    targets: &BTreeMap<String, Target>,
+    format: OutputFormat,
+) -> Result<String> {
+    let digest = build_digest(
+        &Store::open_existing(db)?,
+        &opts.watchlist,
+        opts.tag.as_deref(),
+        opts.period,
+    )?;
+    if !opts.notify.is_empty() {
+        send_to(targets, &opts.notify, &digest.message()).await?;
+    }
+    Ok(format_digest(&digest, format, opts.html))
+}
+
+/// Digest of the watchlist at `watchlist` for the period ending today
+pub fn build_digest(
+    store: &Store,
+    watchlist: &Path,
+    tag: Option<&str>,
+    period: Period,
+) -> Result<Digest> {
+    let list = Watchlist::load(watchlist)?;
+    let entries: Vec<WatchEntry> = list.filtered(tag).into_iter().cloned().collect();
+    Digest::build(store, &entries, period, store::today())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Product;
+    use crate::config::Config;
+    use chrono::Days;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_digest_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let list = dir.path().join("watchlist.toml");
+        let mut store = Store::open(&db).unwrap();
+        let product = |price| {
+            Product::fixture(12345, price)
+                .with_name("PlayStation 5")
+                .with_category("Consoles")
+                .with_offers(12)
+                .with_url("/p/12345/ps5")
+        };
+        let today = store::today();
+        store.record_products(&[product(499.99)], today - Days::new(10)).unwrap();
+        store.record_products(&[product(449.99)], today).unwrap();
+        drop(store);
+        std::fs::write(
+            &list,
+            "[[products]]\nid = 12345\nlabel = \"PS5\"\nadded = \"2024-06-01\"\n",
+        )
+        .unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(body_partial_json(serde_json::json!({ "alerts": [] })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let config: Config = toml::from_str(&format!(
+            "[notify.team]\ntype = \"webhook\"\nurl = \"{}/hook\"\n",
+            server.uri()
+        ))
+        .unwrap();
+
+        let mut opts = DigestOptions {
+            watchlist: list,
+            tag: None,
+            period: Period::Week,
+            html: false,
+            notify: vec!["team".to_string()],
+        };
+        let output = digest(&db, &opts, &config.notify, OutputFormat::Table).await.unwrap();
+        assert!(output.starts_with("# kk weekly digest"));
+        assert!(output.contains("499.99€ → 449.99€ (-10.0%)"));
+
+        opts.notify.clear();
+        let output = digest(&db, &opts, &config.notify, OutputFormat::Json).await.unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["period"], "week");
+        assert_eq!(json["lows"][0]["previousLow"], 499.99);
+
+        opts.notify = vec!["phone".to_string()];
+        let err = digest(&db, &opts, &config.notify, OutputFormat::Table).await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown notification target `phone` (configured: team)");
+    }
+}
@@ -0,0 +1,171 @@
+//! Prometheus exporter command
+
+use super::FETCH_CONCURRENCY;
+use crate::api::KuantoKustaClient;
+use crate::exporter::{PriceGauges, CONTENT_TYPE};
+use crate::serve::{bind, serve, Handler, Response};
+use crate::watchlist::Watchlist;
+use anyhow::Result;
+use chrono::Utc;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Settings of `kk exporter`
+#[derive(Debug, Clone)]
+pub struct ExporterOptions {
+    pub watchlist: PathBuf,
+    /// Time between price refreshes
+    pub refresh: Duration,
+    /// Serve `/metrics` on this address
+    pub listen: Option<SocketAddr>,
+    /// Write the metrics to this file after every refresh, for the `node_exporter`
+    /// textfile collector
+    pub textfile: Option<PathBuf>,
+    /// Refresh once, write the textfile and exit
+    pub once: bool,
+}
+
+/// Refresh the watched products' prices on a schedule and expose them, with
+/// the client's request counters, as Prometheus metrics
+pub async fn exporter(client: &KuantoKustaClient, opts: &ExporterOptions) -> Result<String> {
+    let gauges = Arc::new(Mutex::new(PriceGauges::default()));
+    if let Some(addr) = opts.listen {
+        let listener = bind(addr).await?;
+        tracing::info!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+        tokio::spawn(serve(listener, metrics_handler(client, &gauges)));
+    }
+
+    loop {
+        let refreshed = refresh(client, &opts.watchlist, &gauges).await;
+        if let Some(path) = &opts.textfile {
+            let text = lock(&gauges).render(client.metrics());
+            // Replaced atomically, so the collector never reads half of it
+            let written = refreshed.and_then(|()| crate::paths::write_atomic(path, &text));
+            if opts.once {
+                written?;
+                return Ok(format!("Wrote metrics to {}", path.display()));
+            }
+            if let Err(e) = written {
+                tracing::error!("{e:#}");
+            }
+        } else if let Err(e) = refreshed {
+            tracing::error!("{e:#}");
+        }
+
+        tokio::select! {
+            () = tokio::time::sleep(opts.refresh) => {}
+            _ = tokio::signal::ctrl_c() => return Ok("kk exporter stopped".to_string()),
+        }
+    }
+}
+
+/// Serves the metrics at `/metrics`, rendered on every scrape
+fn metrics_handler(client: &KuantoKustaClient, gauges: &Arc<Mutex<PriceGauges>>) -> Handler {
+    let (client, gauges) = (client.clone(), Arc::clone(gauges));
+    Arc::new(move |path| {
+        let response = match path.as_str() {
+            "/metrics" => Response::ok(CONTENT_TYPE, lock(&gauges).render(client.metrics())),
+            "/" => Response::ok(
+                "text/html; charset=utf-8",
+                "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n",
+            ),
+            _ => Response::not_found(),
+        };
+        Box::pin(std::future::ready(response))
+    })
+}
+
+/// Fetch the prices of the products in the watchlist at `path`
+async fn refresh(
+    client: &KuantoKustaClient,
+    path: &Path,
+    gauges: &Mutex<PriceGauges>,
+) -> Result<()> {
+    let watched: Vec<u64> = Watchlist::load(path)?.entries.iter().map(|e| e.id).collect();
+    let semaphore = Arc::new(Semaphore::new(FETCH_CONCURRENCY));
+    let handles: Vec<_> = watched
+        .iter()
+        .map(|&id| {
+            let (client, semaphore) = (client.clone(), Arc::clone(&semaphore));
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                client.product(id).await
+            })
+        })
+        .collect();
+
+    let mut fetched = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(product)) => fetched.push(product),
+            Ok(Err(e)) => tracing::warn!("{e:#}"),
+            Err(e) => tracing::warn!("Price fetch task failed: {e}"),
+        }
+    }
+    tracing::info!("Refreshed {} of {} watched products", fetched.len(), watched.len());
+    lock(gauges).update(&watched, fetched, Utc::now());
+    Ok(())
+}
+
+fn lock(gauges: &Mutex<PriceGauges>) -> std::sync::MutexGuard<'_, PriceGauges> {
+    gauges.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_exporter_textfile_and_handler() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 12345, "name": "PS5", "brand": "Sony", "priceMin": 449.99,
+                "totalOffers": 15, "badges": {}, "tags": {}
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let watchlist = dir.path().join("watchlist.toml");
+        std::fs::write(
+            &watchlist,
+            "[[products]]\nid = 12345\nlabel = \"PS5\"\nadded = \"2024-06-01\"\n\n\
+             [[products]]\nid = 404\nlabel = \"Gone\"\nadded = \"2024-06-01\"\n",
+        )
+        .unwrap();
+        let textfile = dir.path().join("kk.prom");
+        let opts = ExporterOptions {
+            watchlist: watchlist.clone(),
+            refresh: Duration::from_secs(300),
+            listen: None,
+            textfile: Some(textfile.clone()),
+            once: true,
+        };
+        let output = exporter(&client, &opts).await.unwrap();
+        assert_eq!(output, format!("Wrote metrics to {}", textfile.display()));
+
+        let text = std::fs::read_to_string(&textfile).unwrap();
+        assert!(
+            text.contains("kk_price_min_euros{id=\"12345\",name=\"PS5\",brand=\"Sony\"} 449.99")
+        );
+        assert!(!text.contains("id=\"404\""));
+        assert!(text.contains("kk_client_requests_total{endpoint=\"product\"} 2"));
+        assert!(text.contains("kk_client_request_errors_total{endpoint=\"product\"} 1"));
+
+        let gauges = Arc::new(Mutex::new(PriceGauges::default()));
+        refresh(&client, &watchlist, &gauges).await.unwrap();
+        let handler = metrics_handler(&client, &gauges);
+        let response = handler("/metrics".to_string()).await;
+        assert_eq!(response.content_type, CONTENT_TYPE);
+        assert!(String::from_utf8(response.body).unwrap().contains("endpoint=\"product\"} 4"));
+        assert_eq!(handler("/other".to_string()).await.status, 404);
+    }
+}
@@ -0,0 +1,137 @@
+//! Feed and serve commands
+
+use crate::api::{KuantoKustaClient, WEB_BASE};
+use crate::feed::{Feed, FeedEntry, FeedFormat};
+use crate::serve::{bind, serve as serve_http, static_files};
+use crate::store::Store;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Where `kk feed` writes a feed and in which syntax
+#[derive(Debug, Clone)]
+pub struct FeedOutput {
+    pub format: FeedFormat,
+    /// Output file; `-` prints the feed instead
+    pub path: PathBuf,
+}
+
+impl FeedOutput {
+    /// `<site dir>/<name>.xml`, served by `kk serve`
+    pub fn default_path(name: &str) -> PathBuf {
+        crate::paths::site_dir().join(format!("{name}.xml"))
+    }
+}
+
+/// Write a feed of the current deals
+pub async fn feed_deals(
+    client: &KuantoKustaClient,
+    max: u32,
+    min_discount: Option<u8>,
+    out: &FeedOutput,
+) -> Result<String> {
+    let deals = client.deals(max, 1, min_discount, None, None).await?.data;
+    let now = Utc::now();
+    let feed = Feed {
+        id: "urn:kuantokusta:deals".to_string(),
+        title: "KuantoKusta deals".to_string(),
+        description: "Current deals and discounts on KuantoKusta.pt".to_string(),
+        link: WEB_BASE.to_string(),
+        updated: now,
+        entries: deals.iter().map(|d| FeedEntry::from_deal(d, now)).collect(),
+    };
+    write_feed(&feed, out)
+}
+
+/// Write a feed of the latest alerts kept in the local store at `db`
+pub fn feed_alerts(db: &Path, max: usize, out: &FeedOutput) -> Result<String> {
+    let alerts = Store::open_existing(db)?.recent_alerts(max)?;
+    let feed = Feed {
+        id: "urn:kuantokusta:alerts".to_string(),
+        title: "kk alerts".to_string(),
+        description: "Alerts fired by kk check, kk daemon and saved searches".to_string(),
+        link: WEB_BASE.to_string(),
+        updated: alerts.first().map_or_else(Utc::now, |a| a.fired_at),
+        entries: alerts.iter().map(FeedEntry::from_alert).collect(),
+    };
+    write_feed(&feed, out)
+}
+
+fn write_feed(feed: &Feed, out: &FeedOutput) -> Result<String> {
+    let xml = feed.render(out.format);
+    if out.path.as_os_str() == "-" {
+        return Ok(xml.trim_end().to_string());
+    }
+    if let Some(parent) = out.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&out.path, xml)
+        .with_context(|| format!("Failed to write {}", out.path.display()))?;
+    Ok(format!("Wrote {} entries to {}", feed.entries.len(), out.path.display()))
+}
+
+/// Serve the files in `root` (feeds, dashboards) over HTTP until Ctrl-C
+pub async fn serve(root: &Path, listen: SocketAddr) -> Result<String> {
+    if !root.is_dir() {
+        anyhow::bail!("{} doesn't exist; write a feed first, e.g. `kk feed deals`", root.display());
+    }
+    let listener = bind(listen).await?;
+    tracing::info!("Serving {} on http://{}/", root.display(), listener.local_addr()?);
+    serve_http(listener, static_files(root.to_path_buf())).await?;
+    Ok("kk serve stopped".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::Alert;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_feed_commands() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/deals"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": [{ "id": 7, "name": "TV", "priceMin": 499.0, "totalOffers": 15,
+                           "images": ["https://img/7.jpg"],
+                           "badges": { "discountPercentage": 45 }, "tags": {} }],
+                "page": 1, "rows": 1, "total": 1
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let out = FeedOutput { format: FeedFormat::Rss, path: dir.path().join("www/deals.xml") };
+        let output = feed_deals(&client, 20, None, &out).await.unwrap();
+        assert_eq!(output, format!("Wrote 1 entries to {}", out.path.display()));
+        let xml = std::fs::read_to_string(&out.path).unwrap();
+        assert!(xml.contains("<guid isPermaLink=\"false\">urn:kuantokusta:product:7:49900</guid>"));
+
+        let db = dir.path().join("prices.db");
+        let mut store = Store::open(&db).unwrap();
+        let alert = Alert {
+            product_id: 12345,
+            name: "PS5".to_string(),
+            reason: "Target 450.00€ reached".to_string(),
+            rule: None,
+            old_price: None,
+            new_price: 449.99,
+            discount: None,
+            url: "https://www.kuantokusta.pt/p/12345".to_string(),
+            history: vec![],
+            sparkline: None,
+            exec: None,
+        };
+        store.record_alerts(&[alert], Utc::now()).unwrap();
+        drop(store);
+        let out = FeedOutput { format: FeedFormat::Atom, path: PathBuf::from("-") };
+        let xml = feed_alerts(&db, 50, &out).unwrap();
+        assert!(xml.contains("<title>PS5: Target 450.00€ reached</title>"));
+        assert!(xml.ends_with("</feed>"));
+    }
+}
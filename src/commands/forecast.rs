@@ -0,0 +1,104 @@
+//! Price forecast command
+
+use crate::analysis::Forecast;
+use crate::api::KuantoKustaClient;
+use crate::format::{format_forecast, OutputFormat};
+use anyhow::Result;
+
+/// Execute price forecast command
+pub async fn forecast(
+    client: &KuantoKustaClient,
+    product_id: u64,
+    horizon: u32,
+    window: u32,
+    format: OutputFormat,
+) -> Result<String> {
+    let history = client.price_history(product_id, window).await?;
+
+    let Some(forecast) = Forecast::compute(product_id, &history, horizon as usize) else {
+        anyhow::bail!("Not enough price history to forecast product {product_id}");
+    };
+
+    let header = format!(
+        "Price forecast for product {product_id} (next {horizon} days, fitted on {window} days):\n\n"
+    );
+    let output = format_forecast(&forecast, format);
+
+    Ok(format!("{header}{output}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mock_history_response(prices: &[f64]) -> serde_json::Value {
+        let data: Vec<_> = prices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| serde_json::json!({"date": format!("2024-01-{:02}", i + 1), "avg": p, "min": p}))
+            .collect();
+        serde_json::json!({"minAxis": 0.0, "maxAxis": 1000.0, "data": data})
+    }
+
+    #[tokio::test]
+    async fn test_forecast_command() {
+        let mock_server = MockServer::start().await;
+        let prices: Vec<f64> = (0..20).map(|i| f64::from(i).mul_add(-5.0, 700.0)).collect();
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .and(query_param("days", "90"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response(&prices)))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = forecast(&client, 12345, 14, 90, OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("Price forecast for product 12345 (next 14 days"));
+        assert!(output.contains("WAIT"));
+    }
+
+    #[tokio::test]
+    async fn test_forecast_command_json() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(mock_history_response(&[50.0; 10])),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = forecast(&client, 12345, 7, 30, OutputFormat::Json).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("\"signal\": \"buy\""));
+        assert!(output.contains("\"model\": \"linearTrend\""));
+    }
+
+    #[tokio::test]
+    async fn test_forecast_command_not_enough_data() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(mock_history_response(&[50.0, 49.0])),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = forecast(&client, 12345, 7, 30, OutputFormat::Table).await;
+
+        assert!(result.unwrap_err().to_string().contains("Not enough price history"));
+    }
+}
@@ -1,30 +1,222 @@
 //! Price history command
 
-use crate::api::KuantoKustaClient;
-use crate::format::{format_history, OutputFormat};
-use anyhow::Result;
+use super::FETCH_CONCURRENCY;
+use crate::analysis::resample::{fill_gaps, resample, select_range, Period};
+use crate::analysis::Comparison;
+use crate::api::{history_window, KuantoKustaClient, PriceHistory};
+use crate::format::chart::{terminal_width, ChartStyle};
+use crate::format::svg::{history_svg, SvgSeries};
+use crate::format::{
+    comparison_csv, format_comparison, format_history, format_history_chart, OutputFormat,
+};
+use crate::store;
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Options for the price history command
+#[derive(Debug, Clone, Default)]
+pub struct HistoryOptions {
+    /// Number of days of history
+    pub days: u32,
+    /// Draw a terminal chart instead of a table
+    pub chart: Option<ChartStyle>,
+    /// Write an SVG chart to this path
+    pub svg: Option<PathBuf>,
+    /// Write a date-aligned CSV of `min` prices to this path
+    pub csv: Option<PathBuf>,
+    /// Drop points before this date
+    pub since: Option<NaiveDate>,
+    /// Drop points after this date
+    pub until: Option<NaiveDate>,
+    /// Aggregate points per week or month
+    pub resample: Option<Period>,
+    /// Carry prices forward over days with no data
+    pub fill_gaps: bool,
+    /// Merge in prices recorded in the client's local store
+    pub local: bool,
+}
+
+impl HistoryOptions {
+    /// Days of history to request, widened to the shortest API window that
+    /// covers `since`. Dates older than the longest window only come from the
+    /// local store, so they need `--local`.
+    pub fn fetch_days(&self) -> Result<u32> {
+        let Some(since) = self.since else {
+            return Ok(self.days);
+        };
+        let needed = u32::try_from((Local::now().date_naive() - since).num_days() + 1).unwrap_or(0);
+        if needed <= self.days {
+            return Ok(self.days);
+        }
+        let window = history_window(needed);
+        if window < needed && !self.local {
+            anyhow::bail!(
+                "--since {since} is more than {window} days ago, which the API does not serve; \
+                 add --local to fill older dates from the local store"
+            );
+        }
+        Ok(window)
+    }
+
+    /// Apply range selection, gap filling and resampling (in that order)
+    pub fn apply(&self, history: &PriceHistory) -> PriceHistory {
+        let mut history = select_range(history, self.since, self.until);
+        if self.fill_gaps {
+            history = fill_gaps(&history);
+        }
+        if let Some(period) = self.resample {
+            history = resample(&history, period);
+        }
+        history
+    }
+
+    fn describe(&self, days: u32) -> String {
+        let mut parts = vec![match (self.since, self.until) {
+            (None, None) => format!("{days} days"),
+            (since, until) => format!(
+                "{} to {}",
+                since.map_or_else(|| "start".to_string(), |d| d.to_string()),
+                until.map_or_else(|| "today".to_string(), |d| d.to_string())
+            ),
+        }];
+        if let Some(period) = self.resample {
+            parts.push(period.adjective().to_string());
+        }
+        if self.fill_gaps {
+            parts.push("gaps filled".to_string());
+        }
+        if self.local {
+            parts.push("with local data".to_string());
+        }
+        parts.join(", ")
+    }
+}
 
 /// Execute price history command
 pub async fn history(
     client: &KuantoKustaClient,
-    product_id: u64,
-    days: u32,
+    product_ids: &[u64],
+    opts: &HistoryOptions,
     format: OutputFormat,
 ) -> Result<String> {
-    let response = client.price_history(product_id, days).await?;
+    let days = opts.fetch_days()?;
+    let window = opts.describe(days);
+
+    let mut fetched = fetch_histories(client, product_ids, days).await?;
+    if opts.local {
+        let shared = client
+            .store()
+            .context("--local needs the local price store (remove --no-store or check --db)")?;
+        let store = store::lock(shared);
+        for (product_id, history) in &mut fetched {
+            *history = store::merge_history(store.history(*product_id)?, history);
+        }
+    }
+
+    let histories: Vec<(u64, PriceHistory)> = fetched
+        .into_iter()
+        .map(|(product_id, response)| (product_id, opts.apply(&response)))
+        .collect();
+
+    if let Some(path) = &opts.svg {
+        return write_svg(path, &histories, &window);
+    }
+
+    if let Some(path) = &opts.csv {
+        let csv = comparison_csv(&Comparison::new(&histories));
+        std::fs::write(path, csv)
+            .with_context(|| format!("Failed to write CSV to {}", path.display()))?;
+        return Ok(format!(
+            "Saved price history for {} product(s) to {}",
+            histories.len(),
+            path.display()
+        ));
+    }
+
+    let [(product_id, response)] = histories.as_slice() else {
+        if opts.chart.is_some() {
+            anyhow::bail!("--chart supports a single product; use --svg to overlay several");
+        }
+        let ids: Vec<String> = product_ids.iter().map(ToString::to_string).collect();
+        let header = format!("Price comparison for {} ({window}):\n\n", ids.join(", "));
+        let comparison = format_comparison(&Comparison::new(&histories), format);
+        return Ok(format!("{header}{comparison}"));
+    };
 
-    let header = format!("Price history for product {product_id} ({days} days):\n\n");
-    let history = format_history(&response, format);
+    let header = format!("Price history for product {product_id} ({window}):\n\n");
+    let history = match (opts.chart, format) {
+        (Some(style), OutputFormat::Table | OutputFormat::Compact) => {
+            format_history_chart(response, style, terminal_width())
+        }
+        _ => format_history(response, format),
+    };
 
     Ok(format!("{header}{history}"))
 }
 
+/// Fetch price histories for several products, at most [`FETCH_CONCURRENCY`]
+/// at a time, preserving order
+pub async fn fetch_histories(
+    client: &KuantoKustaClient,
+    product_ids: &[u64],
+    days: u32,
+) -> Result<Vec<(u64, PriceHistory)>> {
+    let semaphore = Arc::new(Semaphore::new(FETCH_CONCURRENCY));
+    let handles: Vec<_> = product_ids
+        .iter()
+        .map(|&product_id| {
+            let (client, semaphore) = (client.clone(), Arc::clone(&semaphore));
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                client.price_history(product_id, days).await
+            })
+        })
+        .collect();
+
+    let mut histories = Vec::with_capacity(handles.len());
+    for (handle, &product_id) in handles.into_iter().zip(product_ids) {
+        let history = handle
+            .await
+            .context("Price history task failed")?
+            .with_context(|| format!("Failed to fetch price history for product {product_id}"))?;
+        histories.push((product_id, history));
+    }
+
+    Ok(histories)
+}
+
+fn write_svg(path: &PathBuf, histories: &[(u64, PriceHistory)], window: &str) -> Result<String> {
+    let labels: Vec<String> = histories.iter().map(|(id, _)| format!("#{id}")).collect();
+    let series: Vec<SvgSeries> = histories
+        .iter()
+        .zip(&labels)
+        .map(|((_, history), label)| SvgSeries { label, history })
+        .collect();
+
+    let title = format!("Price history ({window}): {}", labels.join(", "));
+    std::fs::write(path, history_svg(&title, &series))
+        .with_context(|| format!("Failed to write SVG chart to {}", path.display()))?;
+
+    Ok(format!(
+        "Saved price history chart for {} product(s) to {}",
+        histories.len(),
+        path.display()
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use wiremock::matchers::{method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    fn options(days: u32) -> HistoryOptions {
+        HistoryOptions { days, ..HistoryOptions::default() }
+    }
+
     fn mock_history_response() -> serde_json::Value {
         serde_json::json!({
             "minAxis": 500.0,
@@ -44,7 +236,7 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = history(&client, 12345, 30, OutputFormat::Table).await;
+        let result = history(&client, &[12345], &options(30), OutputFormat::Table).await;
 
         assert!(result.is_ok());
         let output = result.unwrap();
@@ -64,10 +256,223 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = history(&client, 12345, 90, OutputFormat::Json).await;
+        let result = history(&client, &[12345], &options(90), OutputFormat::Json).await;
 
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("\"minAxis\": 500.0"));
     }
+
+    #[tokio::test]
+    async fn test_history_command_chart() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let opts = HistoryOptions { chart: Some(ChartStyle::Ascii), ..options(30) };
+        let result = history(&client, &[12345], &opts, OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("800.00€ ┤"));
+        assert!(output.contains("* min"));
+    }
+
+    #[tokio::test]
+    async fn test_history_command_svg() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/67890/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let svg = dir.path().join("out.svg");
+        let opts = HistoryOptions { svg: Some(svg.clone()), ..options(30) };
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = history(&client, &[12345, 67890], &opts, OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().contains("2 product(s)"));
+        let content = std::fs::read_to_string(&svg).unwrap();
+        assert!(content.contains("#12345 (min; avg dashed)"));
+        assert!(content.contains("#67890 (min; avg dashed)"));
+    }
+
+    #[tokio::test]
+    async fn test_history_command_comparison() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/1/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/2/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "minAxis": 500.0,
+                "maxAxis": 800.0,
+                "data": [{"date": "2024-01-01", "avg": 620.0, "min": 550.0}]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = history(&client, &[1, 2], &options(30), OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("Price comparison for 1, 2"));
+        assert!(output.contains("550.00€*"));
+        assert!(output.contains("Cheapest on"));
+
+        let chart = HistoryOptions { chart: Some(ChartStyle::Ascii), ..options(30) };
+        assert!(history(&client, &[1, 2], &chart, OutputFormat::Table).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_history_command_csv() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let csv = dir.path().join("prices.csv");
+        let opts = HistoryOptions { csv: Some(csv.clone()), ..options(30) };
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = history(&client, &[1, 2], &opts, OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        let content = std::fs::read_to_string(&csv).unwrap();
+        assert!(content.starts_with("date,1,2\n2024-01-01,600.00,600.00\n"));
+        assert!(content.contains("cheapest_days,1,1"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_histories_error_names_product() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = fetch_histories(&client, &[42], 30).await;
+
+        assert!(result.unwrap_err().to_string().contains("product 42"));
+    }
+
+    #[tokio::test]
+    async fn test_history_command_resample_and_range() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "minAxis": 500.0,
+                "maxAxis": 800.0,
+                "data": [
+                    {"date": "2024-01-01", "avg": 650.0, "min": 600.0},
+                    {"date": "2024-01-08", "avg": 640.0, "min": 590.0},
+                    {"date": "2024-01-10", "avg": 630.0, "min": 580.0},
+                    {"date": "2024-02-01", "avg": 620.0, "min": 570.0}
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let opts = HistoryOptions {
+            since: Some("2024-01-05".parse().unwrap()),
+            until: Some("2024-01-31".parse().unwrap()),
+            resample: Some(Period::Week),
+            fill_gaps: true,
+            local: true,
+            ..options(30)
+        };
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri())
+            .unwrap()
+            .with_store(store::Store::open_in_memory().unwrap());
+        let result = history(&client, &[12345], &opts, OutputFormat::Compact).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("2024-01-05 to 2024-01-31, weekly, gaps filled"));
+        assert!(output.contains("2024-01-08\t2024-01-08\t580.00€\t580.00€\t580.00€"));
+    }
+
+    #[tokio::test]
+    async fn test_history_command_local() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let mut store = store::Store::open_in_memory().unwrap();
+        let old = PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: vec![crate::api::PricePoint {
+                date: "2023-06-01".parse().unwrap(),
+                avg: 700.0,
+                min: 640.0,
+            }],
+        };
+        store.record_history(12345, &old).unwrap();
+
+        let opts = HistoryOptions { local: true, ..options(30) };
+        let plain = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let err = history(&plain, &[12345], &opts, OutputFormat::Table).await.unwrap_err();
+        assert!(err.to_string().contains("--local"));
+
+        let client = plain.with_store(store);
+        let output = history(&client, &[12345], &opts, OutputFormat::Table).await.unwrap();
+        assert!(output.contains("with local data"));
+        assert!(output.contains("2023-06-01"));
+        assert!(output.contains("2024-01-01"));
+    }
+
+    #[test]
+    fn test_fetch_days_covers_since() {
+        let since = Local::now().date_naive() - chrono::Days::new(60);
+        let opts = HistoryOptions { since: Some(since), ..options(30) };
+        assert_eq!(opts.fetch_days().unwrap(), 90);
+        assert_eq!(options(90).fetch_days().unwrap(), 90);
+
+        let since = Local::now().date_naive() - chrono::Days::new(10);
+        let opts = HistoryOptions { since: Some(since), ..options(7) };
+        assert_eq!(opts.fetch_days().unwrap(), 30);
+    }
+
+    #[test]
+    fn test_fetch_days_beyond_api_needs_local() {
+        let since = Local::now().date_naive() - chrono::Days::new(200);
+        let opts = HistoryOptions { since: Some(since), ..options(30) };
+        assert!(opts.fetch_days().unwrap_err().to_string().contains("--local"));
+
+        let opts = HistoryOptions { local: true, ..opts };
+        assert_eq!(opts.fetch_days().unwrap(), 90);
+    }
 }
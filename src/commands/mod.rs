@@ -2,16 +2,49 @@
 
 mod browse;
 mod categories;
+mod check;
+mod daemon;
+mod dashboard;
+mod db;
 mod deals;
+mod digest;
+mod exporter;
+mod feed;
+mod forecast;
 mod history;
+mod notify;
 mod popular;
+mod query;
 mod related;
+mod rules;
+mod saved;
 mod search;
+mod snapshot;
+mod stats;
+mod watch;
 
 pub use browse::*;
 pub use categories::*;
+pub use check::*;
+pub use daemon::*;
+pub use dashboard::*;
+pub use db::*;
 pub use deals::*;
+pub use digest::*;
+pub use exporter::*;
+pub use feed::*;
+pub use forecast::*;
 pub use history::*;
+pub use notify::*;
 pub use popular::*;
+pub use query::*;
 pub use related::*;
+pub use rules::*;
+pub use saved::*;
 pub use search::*;
+pub use snapshot::*;
+pub use stats::*;
+pub use watch::*;
+
+/// Products fetched at once when a command fans out over many of them
+const FETCH_CONCURRENCY: usize = 4;
@@ -0,0 +1,41 @@
+//! Notify commands
+
+use crate::notify::{http_client, target, Message, Target};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Send a sample alert to the target named `name`
+pub async fn notify_test(targets: &BTreeMap<String, Target>, name: &str) -> Result<String> {
+    target(targets, name)?.send(&http_client()?, &Message::test()).await?;
+    Ok(format!("Sent a test notification to `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_notify_test() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "topic": "kk" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config: Config = toml::from_str(&format!(
+            "[notify.phone]\ntype = \"ntfy\"\nurl = \"{}/kk\"\n",
+            server.uri()
+        ))
+        .unwrap();
+        let output = notify_test(&config.notify, "phone").await.unwrap();
+        assert_eq!(output, "Sent a test notification to `phone`");
+
+        let err = notify_test(&config.notify, "team").await.unwrap_err();
+        assert_eq!(err.to_string(), "Unknown notification target `team` (configured: phone)");
+    }
+}
@@ -1,22 +1,28 @@
 //! Popular products command
 
+use super::ListOutcome;
 use crate::api::KuantoKustaClient;
 use crate::format::{format_products, OutputFormat};
 use anyhow::Result;
+use std::path::Path;
 
 /// Execute popular products command
 pub async fn popular(
     client: &KuantoKustaClient,
     category_id: u64,
     max: u32,
+    snapshot: Option<&Path>,
     format: OutputFormat,
-) -> Result<String> {
+) -> Result<ListOutcome> {
     let products = client.popular(category_id, max).await?;
 
     let header = format!("Popular products in category {category_id}:\n\n");
     let output = format_products(&products, format);
+    let items = products.iter().map(Into::into).collect();
+    let snapshot =
+        super::save_snapshot(snapshot, "popular", format!("category {category_id}"), items)?;
 
-    Ok(format!("{header}{output}"))
+    Ok(ListOutcome { output: format!("{header}{output}"), snapshot })
 }
 
 #[cfg(test)]
@@ -47,10 +53,10 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = popular(&client, 155, 10, OutputFormat::Table).await;
+        let result = popular(&client, 155, 10, None, OutputFormat::Table).await;
 
         assert!(result.is_ok());
-        let output = result.unwrap();
+        let output = result.unwrap().output;
         assert!(output.contains("Popular products in category 155"));
         assert!(output.contains("Popular Product"));
     }
@@ -0,0 +1,58 @@
+//! Ad-hoc SQL query command
+
+use crate::format::{format_query, format_views, OutputFormat};
+use crate::store::query::VIEWS;
+use crate::store::Store;
+use anyhow::Result;
+use std::path::Path;
+
+/// Execute a read-only SQL query against the local store, or list its views
+pub fn query(db: &Path, sql: Option<&str>, schema: bool, format: OutputFormat) -> Result<String> {
+    if schema {
+        return Ok(format_views(VIEWS, format));
+    }
+    let Some(sql) = sql else {
+        anyhow::bail!("Missing SQL query (or use --schema to list the views)");
+    };
+
+    let store = Store::open_read_only(db)?;
+    let result = store.query(sql)?;
+
+    Ok(format_query(&result, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{PriceHistory, PricePoint};
+
+    #[test]
+    fn test_query_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("prices.db");
+        let mut store = Store::open(&db).unwrap();
+        let history = PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: vec![PricePoint { date: "2024-01-01".parse().unwrap(), avg: 650.0, min: 600.0 }],
+        };
+        store.record_history(12345, &history).unwrap();
+        drop(store);
+
+        let output = query(
+            &db,
+            Some("SELECT product_id, date, min FROM product_prices"),
+            false,
+            OutputFormat::Compact,
+        )
+        .unwrap();
+        assert_eq!(output, "12345\t2024-01-01\t600.0");
+
+        let schema = query(&db, None, true, OutputFormat::Table).unwrap();
+        assert!(schema.contains("product_prices"));
+        assert!(schema.contains("deals_seen"));
+        assert!(schema.contains("category_snapshots"));
+
+        assert!(query(&db, None, false, OutputFormat::Table).is_err());
+    }
+}
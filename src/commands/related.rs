@@ -1,23 +1,28 @@
 //! Related products command
 
+use super::ListOutcome;
 use crate::api::KuantoKustaClient;
 use crate::format::{format_products, OutputFormat};
 use anyhow::Result;
+use std::path::Path;
 
 /// Execute related products command
 pub async fn related(
     client: &KuantoKustaClient,
     product_id: u64,
     max: u32,
+    snapshot: Option<&Path>,
     format: OutputFormat,
-) -> Result<String> {
+) -> Result<ListOutcome> {
     let response = client.related(product_id).await?;
 
     let products: Vec<_> = response.data.into_iter().take(max as usize).collect();
     let header = format!("Related products for {} ({} total):\n\n", product_id, response.count);
     let output = format_products(&products, format);
+    let items = products.iter().map(Into::into).collect();
+    let snapshot = super::save_snapshot(snapshot, "related", format!("to {product_id}"), items)?;
 
-    Ok(format!("{header}{output}"))
+    Ok(ListOutcome { output: format!("{header}{output}"), snapshot })
 }
 
 #[cfg(test)]
@@ -51,10 +56,10 @@ mod tests {
             .await;
 
         let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
-        let result = related(&client, 12345, 10, OutputFormat::Table).await;
+        let result = related(&client, 12345, 10, None, OutputFormat::Table).await;
 
         assert!(result.is_ok());
-        let output = result.unwrap();
+        let output = result.unwrap().output;
         assert!(output.contains("Related products for 12345"));
         assert!(output.contains("1 total"));
         assert!(output.contains("Related Product"));
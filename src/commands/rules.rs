@@ -0,0 +1,106 @@
+//! Rules commands
+
+use crate::api::{history_window, KuantoKustaClient};
+use crate::check::CheckState;
+use crate::format::{format_rules, OutputFormat};
+use crate::rules::{AlertsConfig, Condition, Facts, ScopedRule};
+use crate::store;
+use crate::watchlist::Watchlist;
+use anyhow::Result;
+use std::path::Path;
+
+/// Validate a condition and, with `product`, evaluate it against the product's current data
+///
+/// `drop` compares against the price recorded by the last `kk check` in `state_path`.
+pub async fn rules_test(
+    client: &KuantoKustaClient,
+    condition: &Condition,
+    product: Option<u64>,
+    state_path: &Path,
+) -> Result<String> {
+    let expr = condition.expr();
+    let Some(id) = product else {
+        return Ok(format!("OK: {expr}"));
+    };
+
+    let product = client.product(id).await?;
+    let history = match expr.history_days() {
+        Some(days) => Some(client.price_history(id, history_window(days)).await?),
+        None => None,
+    };
+    let previous = CheckState::load(state_path)?.products.get(&id).map(|o| o.price_min);
+
+    let mut facts = Facts::from_product(&product).with_previous(previous);
+    if let Some(history) = &history {
+        facts = facts.with_history(history, store::today());
+    }
+    let verdict = if expr.matches(&facts) { "matches" } else { "does not match" };
+    Ok(format!("{id} ({}) at {:.2}€: {verdict}", product.name, product.price_min))
+}
+
+/// List the global rules and the rules of each watched product
+pub fn rules_list(alerts: &AlertsConfig, watchlist: &Path, format: OutputFormat) -> Result<String> {
+    let list = Watchlist::load(watchlist)?;
+    let global = alerts
+        .rules
+        .iter()
+        .map(|rule| ScopedRule { scope: "global".to_string(), rule: rule.clone() });
+    let scoped = list.entries.iter().flat_map(|entry| {
+        entry
+            .rules
+            .iter()
+            .map(|rule| ScopedRule { scope: entry.id.to_string(), rule: rule.clone() })
+    });
+    let rules: Vec<ScopedRule> = global.chain(scoped).collect();
+    Ok(format_rules(&rules, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_rules_test() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 12345,
+                "name": "PlayStation 5",
+                "priceMin": 449.99,
+                "totalOffers": 12,
+                "badges": {},
+                "tags": {}
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "minAxis": 400,
+                "maxAxis": 500,
+                "data": [{ "date": store::today().to_string(), "avg": 470.0, "min": 449.99 }]
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let state = Path::new("/nonexistent/check-state.json");
+
+        let condition: Condition = "stores>=10 and price<=90d min".parse().unwrap();
+        assert_eq!(
+            rules_test(&client, &condition, None, state).await.unwrap(),
+            "OK: stores >= 10 and price <= 90d min"
+        );
+        assert_eq!(
+            rules_test(&client, &condition, Some(12345), state).await.unwrap(),
+            "12345 (PlayStation 5) at 449.99€: matches"
+        );
+        let condition: Condition = "drop > 5".parse().unwrap();
+        assert!(rules_test(&client, &condition, Some(12345), state)
+            .await
+            .unwrap()
+            .ends_with("does not match"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Saved search commands
+
+use crate::api::{KuantoKustaClient, Product};
+use crate::config::Config;
+use crate::format::{format_saved_searches, format_search_diffs, OutputFormat};
+use crate::notify::{notify_alerts, Alert};
+use crate::searches::{SavedSearch, SavedSearches, SearchDiff};
+use crate::store::{self, Store};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// Save a named search
+pub fn saved_add(path: &Path, search: SavedSearch) -> Result<String> {
+    let mut searches = SavedSearches::load(path)?;
+    let output = format!("Saved search `{}` for \"{}\"", search.name, search.query);
+    searches.add(search)?;
+    searches.save(path)?;
+    Ok(output)
+}
+
+/// List the saved searches
+pub fn saved_list(path: &Path, format: OutputFormat) -> Result<String> {
+    Ok(format_saved_searches(&SavedSearches::load(path)?.entries, format))
+}
+
+/// Delete a saved search; its recorded results stay in the local database
+pub fn saved_remove(path: &Path, name: &str) -> Result<String> {
+    let mut searches = SavedSearches::load(path)?;
+    let removed = searches.remove(name)?;
+    searches.save(path)?;
+    Ok(format!("Removed saved search `{}`", removed.name))
+}
+
+/// Run the search called `name` (or all of them) and report what changed since
+/// the previous run
+///
+/// New products and price drops go to the configured notification targets,
+/// unless the run isn't saved.
+pub async fn saved_run(
+    client: &KuantoKustaClient,
+    path: &Path,
+    name: Option<&str>,
+    save: bool,
+    config: &Config,
+    format: OutputFormat,
+) -> Result<String> {
+    let searches = SavedSearches::load(path)?;
+    let selected = match name {
+        Some(name) => vec![searches.get(name)?.clone()],
+        None => searches.entries,
+    };
+    if selected.is_empty() {
+        return Ok("No saved searches. Add one with `kk saved add <name> <query>`.".to_string());
+    }
+
+    let diffs = run_searches(client, &selected, save).await?;
+    if save {
+        let alerts: Vec<Alert> = diffs.iter().flat_map(Alert::from_search).collect();
+        super::log_alerts(client, &alerts);
+        notify_alerts(client, &config.notify, alerts).await;
+    }
+    Ok(format_search_diffs(&diffs, format))
+}
+
+/// Run `searches` and compare each with its previous run, recording the
+/// results when `save` is set
+pub async fn run_searches(
+    client: &KuantoKustaClient,
+    searches: &[SavedSearch],
+    save: bool,
+) -> Result<Vec<SearchDiff>> {
+    let Some(shared) = client.store() else {
+        anyhow::bail!("Saved searches keep their results in the local price database (--no-store)");
+    };
+    let mut diffs = Vec::with_capacity(searches.len());
+    for search in searches {
+        let result = client.search(&search.query, search.max).await?;
+        let diff = record_search(
+            &mut store::lock(shared),
+            search,
+            &result.products,
+            save,
+            store::today(),
+        )?;
+        diffs.push(diff);
+    }
+    Ok(diffs)
+}
+
+/// Compare fetched `products` with the search's latest recorded results, then
+/// record the filtered ones for `date` when `save` is set
+pub fn record_search(
+    store: &mut Store,
+    search: &SavedSearch,
+    products: &[Product],
+    save: bool,
+    date: NaiveDate,
+) -> Result<SearchDiff> {
+    let products: Vec<_> = products.iter().filter(|p| search.matches(p)).cloned().collect();
+    let previous = store.search_results(&search.name)?;
+    if save {
+        store.record_search_results(&search.name, &products, date)?;
+    }
+    Ok(SearchDiff::new(&search.name, previous, &products))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(id: u64, price: f64) -> Product {
+        Product::fixture(id, price)
+            .with_name(&format!("RTX 4070 #{id}"))
+            .with_brand("MSI")
+            .with_category("Placas Gráficas")
+            .with_offers(4)
+    }
+
+    #[test]
+    fn test_saved_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("searches.toml");
+        let search: SavedSearch = toml::from_str(
+            "name = \"rtx\"\nquery = \"rtx 4070\"\nmax_price = 700\nadded = \"2024-06-01\"\n",
+        )
+        .unwrap();
+
+        let output = saved_add(&path, search.clone()).unwrap();
+        assert_eq!(output, "Saved search `rtx` for \"rtx 4070\"");
+        assert!(saved_add(&path, search).is_err());
+        let output = saved_list(&path, OutputFormat::Compact).unwrap();
+        assert_eq!(output, "rtx\trtx 4070\t20\t≤ 700.00€");
+
+        assert_eq!(saved_remove(&path, "rtx").unwrap(), "Removed saved search `rtx`");
+        assert!(saved_remove(&path, "rtx").is_err());
+    }
+
+    #[test]
+    fn test_record_search() {
+        let mut store = Store::open_in_memory().unwrap();
+        let search: SavedSearch = toml::from_str(
+            "name = \"rtx\"\nquery = \"rtx 4070\"\nmax_price = 700\nadded = \"2024-06-01\"\n",
+        )
+        .unwrap();
+        let day = |d: &str| d.parse::<NaiveDate>().unwrap();
+
+        let first = [product(1, 650.0), product(2, 600.0), product(3, 900.0)];
+        let diff = record_search(&mut store, &search, &first, true, day("2024-06-01")).unwrap();
+        assert_eq!(diff.summary(), "2 result(s), first run");
+
+        // Unsaved runs compare against the same results
+        let second = [product(2, 580.0), product(4, 690.0)];
+        let diff = record_search(&mut store, &search, &second, false, day("2024-06-02")).unwrap();
+        assert_eq!(diff.summary(), "2 result(s), 1 new, 1 removed, 1 price change(s)");
+        let diff = record_search(&mut store, &search, &second, true, day("2024-06-02")).unwrap();
+        assert_eq!(diff.previous, Some(day("2024-06-01")));
+
+        let alerts = Alert::from_search(&diff);
+        let reasons: Vec<_> = alerts.iter().map(|a| a.reason.as_str()).collect();
+        assert_eq!(reasons, ["New in `rtx`", "Price drop (-3.3%) in `rtx`"]);
+        assert_eq!(alerts[1].old_price, Some(600.0));
+
+        let lines = format_search_diffs(&[diff], OutputFormat::Compact);
+        assert_eq!(
+            lines,
+            "rtx\tnew\t4\t\t690.00\tRTX 4070 #4\n\
+             rtx\tremoved\t1\t650.00\t\tRTX 4070 #1\n\
+             rtx\tprice\t2\t600.00\t580.00\tRTX 4070 #2"
+        );
+    }
+}
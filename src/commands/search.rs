@@ -1,18 +1,23 @@
 //! Search command (via HTML scraping)
 
+use super::ListOutcome;
 use crate::api::{KuantoKustaClient, SearchResult};
 use crate::format::{format_products, OutputFormat};
 use anyhow::Result;
+use std::path::Path;
 
 /// Execute search command
 pub async fn search(
     client: &KuantoKustaClient,
     query: &str,
     max: usize,
+    snapshot: Option<&Path>,
     format: OutputFormat,
-) -> Result<String> {
+) -> Result<ListOutcome> {
     let result = client.search(query, max).await?;
-    format_search_result(&result, query, format)
+    let items = result.products.iter().map(Into::into).collect();
+    let snapshot = super::save_snapshot(snapshot, "search", format!("\"{query}\""), items)?;
+    Ok(ListOutcome { output: format_search_result(&result, query, format)?, snapshot })
 }
 
 /// Format search result (exported for testing)
@@ -0,0 +1,76 @@
+//! Snapshot and diff commands
+
+use crate::format::{format_snapshot_diff, OutputFormat};
+use crate::snapshot::{Snapshot, SnapshotDiff, SnapshotItem};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Output of a listing command and the snapshot file it wrote, if any
+///
+/// The caller notes the file on stderr, so the output stays parseable.
+#[derive(Debug, Clone)]
+pub struct ListOutcome {
+    pub output: String,
+    pub snapshot: Option<PathBuf>,
+}
+
+/// Save the listed `items` to `path` when `--snapshot` is given; returns the
+/// file written
+pub fn save_snapshot(
+    path: Option<&Path>,
+    command: &str,
+    args: impl Into<String>,
+    items: Vec<SnapshotItem>,
+) -> Result<Option<PathBuf>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    Snapshot::new(command, args, items).save(path).map(Some)
+}
+
+/// Compare two snapshot files
+pub fn diff(from: &Path, to: &Path, format: OutputFormat) -> Result<String> {
+    let diff = SnapshotDiff::new(&Snapshot::load(from)?, &Snapshot::load(to)?);
+    Ok(format_snapshot_diff(&diff, format))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::WEB_BASE;
+
+    fn item(id: u64, price: f64) -> SnapshotItem {
+        SnapshotItem {
+            id,
+            name: format!("Product {id}"),
+            brand: String::new(),
+            price_min: price,
+            total_offers: 3,
+            discount: None,
+            url: format!("{WEB_BASE}/p/{id}"),
+        }
+    }
+
+    #[test]
+    fn test_diff_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.json"), dir.path().join("b.json"));
+        assert_eq!(save_snapshot(None, "deals", "", vec![]).unwrap(), None);
+        let saved = save_snapshot(Some(&a), "deals", "", vec![item(1, 100.0), item(2, 50.0)]);
+        assert_eq!(saved.unwrap(), Some(a.clone()));
+        save_snapshot(Some(&b), "deals", "", vec![item(1, 89.5), item(3, 20.0)]).unwrap();
+
+        let output = diff(&a, &b, OutputFormat::Compact).unwrap();
+        assert_eq!(
+            output,
+            "added\t3\t\t20.00\tProduct 3\n\
+             removed\t2\t50.00\t\tProduct 2\n\
+             repriced\t1\t100.00\t89.50\tProduct 1"
+        );
+        let output = diff(&a, &b, OutputFormat::Table).unwrap();
+        assert!(output.contains("1 added, 1 removed, 1 re-priced, 0 unchanged"));
+        assert!(output.contains("100.00€ → 89.50€ (-10.50€, -10.5%)"));
+
+        assert!(diff(&a, &dir.path().join("missing.json"), OutputFormat::Table).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Price statistics command
+
+use crate::analysis::PriceStats;
+use crate::api::KuantoKustaClient;
+use crate::format::chart::sparkline;
+use crate::format::{format_stats, OutputFormat};
+use anyhow::Result;
+
+/// Execute price statistics command
+pub async fn stats(
+    client: &KuantoKustaClient,
+    product_id: u64,
+    days: u32,
+    buy_percentile: f64,
+    format: OutputFormat,
+) -> Result<String> {
+    let history = client.price_history(product_id, days).await?;
+
+    let Some(stats) = PriceStats::compute(product_id, &history, buy_percentile) else {
+        anyhow::bail!("No price history available for product {product_id}");
+    };
+
+    let header = format!("Price statistics for product {product_id} ({days} days):\n\n");
+    let output = format_stats(&stats, format);
+
+    if matches!(format, OutputFormat::Table) {
+        let prices: Vec<f64> = history.data.iter().map(|p| p.min).collect();
+        return Ok(format!("{header}{output}{:<16} {}\n", "Sparkline", sparkline(&prices)));
+    }
+
+    Ok(format!("{header}{output}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn mock_history_response() -> serde_json::Value {
+        serde_json::json!({
+            "minAxis": 500.0,
+            "maxAxis": 800.0,
+            "data": [
+                {"date": "2024-01-01", "avg": 700.0, "min": 650.0},
+                {"date": "2024-01-02", "avg": 690.0, "min": 600.0},
+                {"date": "2024-01-03", "avg": 720.0, "min": 680.0}
+            ]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_stats_command() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .and(query_param("days", "90"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = stats(&client, 12345, 90, 25.0, OutputFormat::Table).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("Price statistics for product 12345"));
+        assert!(output.contains("680.00€"));
+        assert!(output.contains("WAIT"));
+    }
+
+    #[tokio::test]
+    async fn test_stats_command_json() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_history_response()))
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = stats(&client, 12345, 90, 100.0, OutputFormat::Json).await;
+
+        assert!(result.is_ok());
+        let output = result.unwrap();
+        assert!(output.contains("\"median\": 650.0"));
+        assert!(output.contains("\"signal\": \"buy\""));
+    }
+
+    #[tokio::test]
+    async fn test_stats_command_empty_history() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/products/12345/price-history"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"minAxis": 0.0, "maxAxis": 0.0, "data": []})),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let result = stats(&client, 12345, 30, 25.0, OutputFormat::Table).await;
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("No price history"));
+    }
+}
@@ -0,0 +1,365 @@
+//! Watchlist commands
+
+use super::FETCH_CONCURRENCY;
+use crate::api::KuantoKustaClient;
+use crate::format::{format_watchlist, OutputFormat};
+use crate::rules::{Condition, Rule};
+use crate::store;
+use crate::watchlist::{normalize_tags, parse_product_ref, WatchEntry, WatchStatus, Watchlist};
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Changes applied by `kk watch edit`
+#[derive(Debug, Clone, Default)]
+pub struct WatchEdit {
+    pub label: Option<String>,
+    pub target_price: Option<f64>,
+    pub max_drop: Option<f64>,
+    pub clear_target: bool,
+    pub clear_max_drop: bool,
+    /// Alert rules to add
+    pub rules: Vec<Condition>,
+    /// Remove existing rules (before adding `rules`)
+    pub clear_rules: bool,
+}
+
+/// Add a product to the watchlist
+pub async fn watch_add(
+    client: &KuantoKustaClient,
+    path: &Path,
+    reference: &str,
+    label: Option<String>,
+    tags: Vec<String>,
+    target_price: Option<f64>,
+    max_drop: Option<f64>,
+) -> Result<String> {
+    let id = parse_product_ref(reference)?;
+    validate_thresholds(target_price, max_drop)?;
+
+    let mut list = Watchlist::load(path)?;
+    if list.get(id).is_some() {
+        anyhow::bail!("Product {id} is already on the watchlist; use `kk watch edit`");
+    }
+
+    let label = match label {
+        Some(label) => label,
+        None => match client.product(id).await {
+            Ok(product) => product.name,
+            Err(e) => {
+                tracing::warn!("Couldn't fetch the name of product {id}: {e:#}");
+                format!("Product {id}")
+            }
+        },
+    };
+
+    list.add(WatchEntry {
+        id,
+        label: label.clone(),
+        tags: normalize_tags(tags),
+        target_price,
+        max_drop,
+        rules: vec![],
+        added: store::today(),
+    })?;
+    list.save(path)?;
+
+    Ok(format!("Watching {id} ({label})"))
+}
+
+/// List watched products with their current prices
+pub async fn watch_list(
+    client: &KuantoKustaClient,
+    path: &Path,
+    tag: Option<&str>,
+    format: OutputFormat,
+) -> Result<String> {
+    let list = Watchlist::load(path)?;
+    let entries: Vec<WatchEntry> = list.filtered(tag).into_iter().cloned().collect();
+    if entries.is_empty() {
+        return Ok("The watchlist is empty. Add products with `kk watch add <id|url>`.".to_string());
+    }
+
+    let statuses = fetch_statuses(client, entries).await;
+    Ok(format_watchlist(&statuses, format))
+}
+
+/// Fetch current prices for watchlist entries, at most [`FETCH_CONCURRENCY`]
+/// at a time, preserving order
+pub async fn fetch_statuses(
+    client: &KuantoKustaClient,
+    entries: Vec<WatchEntry>,
+) -> Vec<WatchStatus> {
+    let semaphore = Arc::new(Semaphore::new(FETCH_CONCURRENCY));
+    let handles: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let (client, semaphore) = (client.clone(), Arc::clone(&semaphore));
+            let id = entry.id;
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                client.product(id).await
+            })
+        })
+        .collect();
+
+    let mut statuses = Vec::with_capacity(entries.len());
+    for (handle, entry) in handles.into_iter().zip(entries) {
+        let product = match handle.await {
+            Ok(Ok(product)) => Some(product),
+            Ok(Err(e)) => {
+                tracing::warn!("{e:#}");
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Price fetch task for product {} failed: {e}", entry.id);
+                None
+            }
+        };
+        statuses.push(WatchStatus {
+            entry,
+            price_min: product.as_ref().map(|p| p.price_min),
+            total_offers: product.as_ref().map(|p| p.total_offers),
+        });
+    }
+    statuses
+}
+
+/// Remove a product from the watchlist
+pub fn watch_remove(path: &Path, reference: &str) -> Result<String> {
+    let id = parse_product_ref(reference)?;
+    let mut list = Watchlist::load(path)?;
+    let entry = list.remove(id)?;
+    list.save(path)?;
+    Ok(format!("Stopped watching {id} ({})", entry.label))
+}
+
+/// Change the label or thresholds of a watched product
+pub fn watch_edit(path: &Path, reference: &str, edit: WatchEdit) -> Result<String> {
+    let id = parse_product_ref(reference)?;
+    validate_thresholds(edit.target_price, edit.max_drop)?;
+
+    let mut list = Watchlist::load(path)?;
+    let entry = list.get_mut(id)?;
+    if let Some(label) = edit.label {
+        entry.label = label;
+    }
+    if edit.clear_target {
+        entry.target_price = None;
+    }
+    if edit.clear_max_drop {
+        entry.max_drop = None;
+    }
+    if edit.clear_rules {
+        entry.rules.clear();
+    }
+    entry.target_price = edit.target_price.or(entry.target_price);
+    entry.max_drop = edit.max_drop.or(entry.max_drop);
+    entry.rules.extend(edit.rules.into_iter().map(Rule::new));
+    let label = entry.label.clone();
+    list.save(path)?;
+
+    Ok(format!("Updated {id} ({label})"))
+}
+
+/// Add tags to (or with `remove`, remove tags from) a watched product
+pub fn watch_tag(path: &Path, reference: &str, tags: Vec<String>, remove: bool) -> Result<String> {
+    let id = parse_product_ref(reference)?;
+    let tags = normalize_tags(tags);
+
+    let mut list = Watchlist::load(path)?;
+    let entry = list.get_mut(id)?;
+    if remove {
+        entry.tags.retain(|t| !tags.contains(t));
+    } else {
+        entry.tags = normalize_tags(entry.tags.drain(..).chain(tags));
+    }
+    let summary = if entry.tags.is_empty() { "no tags".to_string() } else { entry.tags.join(", ") };
+    list.save(path)?;
+
+    Ok(format!("{id}: {summary}"))
+}
+
+/// Export the watchlist (optionally only one tag) as TOML; returns it when `output` is `None`
+pub fn watch_export(path: &Path, output: Option<&Path>, tag: Option<&str>) -> Result<String> {
+    let list = Watchlist::load(path)?;
+    let subset = Watchlist { entries: list.filtered(tag).into_iter().cloned().collect() };
+
+    let Some(output) = output else {
+        return subset.to_toml();
+    };
+    std::fs::write(output, subset.to_toml()?)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok(format!("Exported {} product(s) to {}", subset.entries.len(), output.display()))
+}
+
+/// Merge a shared watchlist file into ours
+pub fn watch_import(path: &Path, file: &Path, replace: bool) -> Result<String> {
+    let raw = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let theirs: Watchlist =
+        toml::from_str(&raw).with_context(|| format!("Invalid watchlist in {}", file.display()))?;
+    for entry in &theirs.entries {
+        validate_thresholds(entry.target_price, entry.max_drop)
+            .with_context(|| format!("Invalid product {} in {}", entry.id, file.display()))?;
+    }
+
+    let mut list = Watchlist::load(path)?;
+    let report = list.merge(theirs, replace);
+    list.save(path)?;
+
+    Ok(format!(
+        "Imported from {}: {} added, {} replaced, {} already watched",
+        file.display(),
+        report.added,
+        report.replaced,
+        report.skipped
+    ))
+}
+
+fn validate_thresholds(target_price: Option<f64>, max_drop: Option<f64>) -> Result<()> {
+    if target_price.is_some_and(|p| !p.is_finite() || p <= 0.0) {
+        anyhow::bail!("Target price must be positive");
+    }
+    if max_drop.is_some_and(|d| !(d > 0.0 && d < 100.0)) {
+        anyhow::bail!("Max drop must be a percentage between 0 and 100");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_server() -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/12345"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": 12345,
+                "name": "PlayStation 5",
+                "priceMin": 449.99,
+                "totalOffers": 12,
+                "badges": {},
+                "tags": {}
+            })))
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[tokio::test]
+    async fn test_watch_add_and_list() {
+        let mock_server = mock_server().await;
+        let client = KuantoKustaClient::with_base_url(&mock_server.uri()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("watchlist.toml");
+
+        let output = watch_add(
+            &client,
+            &file,
+            "https://www.kuantokusta.pt/p/12345/ps5",
+            None,
+            vec!["Console".to_string()],
+            Some(450.0),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, "Watching 12345 (PlayStation 5)");
+
+        let output =
+            watch_add(&client, &file, "999", None, vec![], None, Some(10.0)).await.unwrap();
+        assert_eq!(output, "Watching 999 (Product 999)");
+        assert!(watch_add(&client, &file, "999", None, vec![], None, None).await.is_err());
+
+        let output = watch_list(&client, &file, None, OutputFormat::Compact).await.unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "12345\t449.99\t450.00\tPlayStation 5");
+        assert_eq!(lines[1], "999\t-\t-\tProduct 999");
+
+        let output =
+            watch_list(&client, &file, Some("console"), OutputFormat::Table).await.unwrap();
+        assert!(output.contains("PlayStation 5"));
+        assert!(!output.contains("Product 999"));
+    }
+
+    #[tokio::test]
+    async fn test_watch_edit_tag_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("watchlist.toml");
+        let client = KuantoKustaClient::with_base_url("http://127.0.0.1:1").unwrap();
+        watch_add(&client, &file, "1", Some("Phone".to_string()), vec![], Some(300.0), None)
+            .await
+            .unwrap();
+
+        let edit = WatchEdit {
+            max_drop: Some(15.0),
+            clear_target: true,
+            rules: vec!["price <= 90d min".parse().unwrap()],
+            ..WatchEdit::default()
+        };
+        watch_edit(&file, "1", edit).unwrap();
+        let entry = Watchlist::load(&file).unwrap().entries[0].clone();
+        assert_eq!(entry.target_price, None);
+        assert_eq!(entry.max_drop, Some(15.0));
+        assert_eq!(entry.rules[0].label(), "price <= 90d min");
+        assert!(watch_edit(
+            &file,
+            "1",
+            WatchEdit { max_drop: Some(150.0), ..WatchEdit::default() }
+        )
+        .is_err());
+
+        let tags = vec!["Phones".to_string(), "gift".to_string()];
+        assert_eq!(watch_tag(&file, "1", tags, false).unwrap(), "1: phones, gift");
+        assert_eq!(watch_tag(&file, "1", vec!["GIFT".to_string()], true).unwrap(), "1: phones");
+
+        assert_eq!(watch_remove(&file, "1").unwrap(), "Stopped watching 1 (Phone)");
+        assert!(watch_remove(&file, "1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_export_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mine = dir.path().join("mine.toml");
+        let theirs = dir.path().join("theirs.toml");
+        let shared = dir.path().join("shared.toml");
+        let client = KuantoKustaClient::with_base_url("http://127.0.0.1:1").unwrap();
+
+        for (file, id, label) in
+            [(&mine, "1", "Mine"), (&theirs, "1", "Theirs"), (&theirs, "2", "Two")]
+        {
+            watch_add(&client, file, id, Some(label.to_string()), vec![], None, None)
+                .await
+                .unwrap();
+        }
+
+        watch_export(&theirs, Some(&shared), None).unwrap();
+        let output = watch_import(&mine, &shared, false).unwrap();
+        assert!(output.ends_with("1 added, 0 replaced, 1 already watched"));
+        assert_eq!(Watchlist::load(&mine).unwrap().get(1).unwrap().label, "Mine");
+
+        assert!(watch_export(&mine, None, None).unwrap().contains("label = \"Two\""));
+    }
+
+    #[test]
+    fn test_watch_import_validates_thresholds() {
+        let dir = tempfile::tempdir().unwrap();
+        let mine = dir.path().join("mine.toml");
+        let shared = dir.path().join("shared.toml");
+
+        for threshold in ["target_price = -5.0", "target_price = nan", "max_drop = 150.0"] {
+            let raw = format!(
+                "[[products]]\nid = 7\nlabel = \"Seven\"\nadded = \"2024-01-01\"\n{threshold}\n"
+            );
+            std::fs::write(&shared, raw).unwrap();
+            let err = watch_import(&mine, &shared, false).unwrap_err();
+            assert!(format!("{err:#}").contains("Invalid product 7"), "{threshold}");
+        }
+        assert!(!mine.exists());
+    }
+}
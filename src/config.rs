@@ -0,0 +1,111 @@
+//! User configuration (`~/.config/kk/config.toml`)
+
+use crate::daemon::{DaemonConfig, RequestConfig};
+use crate::mqtt::MqttConfig;
+use crate::notify::{HooksConfig, Target};
+use crate::rules::AlertsConfig;
+use crate::store::RetentionPolicy;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the config file; every section is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Retention policy of the local price store
+    pub retention: RetentionPolicy,
+    /// Global alert rules, cooldown and quiet hours
+    pub alerts: AlertsConfig,
+    /// API request pacing
+    pub request: RequestConfig,
+    /// Jobs and status file of `kk daemon`
+    pub daemon: DaemonConfig,
+    /// Named notification targets, e.g. `[notify.team]`
+    pub notify: BTreeMap<String, Target>,
+    /// Broker to publish the watched products to after each check
+    pub mqtt: Option<MqttConfig>,
+    /// Limits of the exec hooks of alert rules
+    pub hooks: HooksConfig,
+}
+
+impl Config {
+    /// Default config file location
+    pub fn default_path() -> PathBuf {
+        crate::paths::config_dir().join("config.toml")
+    }
+
+    /// Load a config file, falling back to defaults if it doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .with_context(|| format!("Invalid config in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+/// A password given in the config, or read from the environment variable named by `env`
+pub fn password(value: Option<&String>, env: Option<&String>) -> Result<Option<String>> {
+    match (value, env) {
+        (_, Some(var)) => std::env::var(var)
+            .map(Some)
+            .with_context(|| format!("Password variable `{var}` is not set")),
+        (value, None) => Ok(value.cloned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        let config = Config::load(Path::new("/nonexistent/kk/config.toml")).unwrap();
+        assert_eq!(config.retention, RetentionPolicy::default());
+    }
+
+    #[test]
+    fn parses_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "format = \"table\"\n[retention]\ndaily_days = 90\nmax_days = 730\nauto = true\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.retention.daily_days, Some(90));
+        assert_eq!(config.retention.weekly_days, RetentionPolicy::default().weekly_days);
+        assert_eq!(config.retention.max_days, Some(730));
+        assert!(config.retention.auto);
+    }
+
+    #[test]
+    fn parses_example_config() {
+        let raw = include_str!("../config.example.toml");
+        let config: Config = toml::from_str(raw).unwrap();
+        assert_eq!(config.alerts.rules.len(), 2);
+        assert_eq!(config.alerts.rules[1].label(), "90-day low");
+        assert!(config.alerts.rules[1].exec.as_deref().unwrap().starts_with("notify-send"));
+        assert_eq!(config.hooks.concurrency, 4);
+        assert_eq!(config.daemon.deals.map(|d| d.rules.len()), Some(1));
+        let digest = config.daemon.digest.unwrap();
+        assert_eq!(digest.period, crate::analysis::Period::Week);
+        assert_eq!(digest.notify, ["email"]);
+        assert_eq!(config.daemon.searches.unwrap().schedule.to_string(), "every 6h");
+        assert_eq!(config.notify.keys().collect::<Vec<_>>(), ["email", "phone", "team"]);
+        assert_eq!(config.mqtt.map(|m| m.topic), Some("kk/product/{id}".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[retention]\ndaily_days = \"a year\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Configuration and status of the long-running tracker (`kk daemon`)
+
+mod schedule;
+
+pub use schedule::Schedule;
+
+use crate::analysis::Period;
+use crate::rules::{Interval, Rule};
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The `[daemon]` config section
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Watchlist check (`kk check`), hourly unless configured
+    pub check: CheckJob,
+    /// Deal scan; off unless configured
+    pub deals: Option<DealsJob>,
+    /// Digest sent to notification targets; off unless configured
+    pub digest: Option<DigestJob>,
+    /// Saved search runs; off unless configured
+    pub searches: Option<SearchesJob>,
+    /// Static dashboard regeneration; off unless configured
+    pub dashboard: Option<DashboardJob>,
+    /// Status file read by `kk daemon status` (default: ~/.local/share/kk/daemon.json)
+    pub status_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CheckJob {
+    pub enabled: bool,
+    pub schedule: Schedule,
+    /// Only check products with this tag
+    pub tag: Option<String>,
+}
+
+impl Default for CheckJob {
+    fn default() -> Self {
+        Self { enabled: true, schedule: Schedule::Every(Interval(TimeDelta::hours(1))), tag: None }
+    }
+}
+
+/// Fetches current deals (recording them in the local store) and alerts on
+/// deals matching `rules`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DealsJob {
+    pub schedule: Schedule,
+    #[serde(default = "default_deals_max")]
+    pub max: u32,
+    #[serde(default)]
+    pub min_discount: Option<u8>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+const fn default_deals_max() -> u32 {
+    50
+}
+
+/// Builds a digest (`kk digest`) and sends it to notification targets
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestJob {
+    pub schedule: Schedule,
+    #[serde(default = "default_digest_period")]
+    pub period: Period,
+    /// Only include products with this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Targets to send it to (default: every target)
+    #[serde(default)]
+    pub notify: Vec<String>,
+}
+
+const fn default_digest_period() -> Period {
+    Period::Week
+}
+
+/// Runs saved searches (`kk saved run`), recording their results and alerting
+/// on new products and price drops
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchesJob {
+    pub schedule: Schedule,
+    /// Searches to run (default: every saved search)
+    #[serde(default)]
+    pub searches: Vec<String>,
+}
+
+/// Regenerates the static dashboard (`kk dashboard`) from the local store
+#[derive(Debug, Clone, Deserialize)]
+pub struct DashboardJob {
+    pub schedule: Schedule,
+    /// Only include products with this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Directory to write the site to (default: ~/.local/share/kk/www)
+    #[serde(default)]
+    pub out: Option<PathBuf>,
+}
+
+/// The `[request]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RequestConfig {
+    /// Minimum delay between API requests of `kk check` and long-running commands
+    pub delay_ms: u64,
+    /// Seconds long-running commands reuse a fetched product for (0: off)
+    pub cache_secs: u64,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self { delay_ms: 100, cache_secs: 0 }
+    }
+}
+
+/// Snapshot of a running daemon, written after every job and on shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub running: bool,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub config: String,
+    pub jobs: Vec<JobStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub name: String,
+    pub schedule: String,
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u64>,
+    /// Summary of the last run, or its error
+    pub last_result: Option<String>,
+    pub last_ok: bool,
+    pub runs: u64,
+    pub failures: u64,
+    pub alerts: u64,
+}
+
+impl JobStatus {
+    pub fn new(name: &str, schedule: &Schedule) -> Self {
+        Self {
+            name: name.to_string(),
+            schedule: schedule.to_string(),
+            next_run: None,
+            last_run: None,
+            last_duration_ms: None,
+            last_result: None,
+            last_ok: true,
+            runs: 0,
+            failures: 0,
+            alerts: 0,
+        }
+    }
+}
+
+impl DaemonStatus {
+    /// Default status file location
+    pub fn default_path() -> PathBuf {
+        crate::paths::data_dir().join("daemon.json")
+    }
+
+    /// Load the status written by a daemon
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!("kk daemon has not run yet (no status file at {})", path.display())
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid daemon status in {}", path.display()))
+    }
+
+    /// Save the status
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Whether the daemon that wrote this status is still alive
+    pub fn is_alive(&self) -> bool {
+        self.running && process_exists(self.pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_exists(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Without `/proc`, trust the status file
+#[cfg(not(target_os = "linux"))]
+const fn process_exists(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn defaults_and_jobs() {
+        let config = DaemonConfig::default();
+        assert!(config.check.enabled);
+        assert_eq!(config.check.schedule.to_string(), "every 1h");
+        assert!(config.deals.is_none());
+
+        let config: Config = toml::from_str(
+            "[request]\ndelay_ms = 500\n\
+             [daemon.check]\nschedule = \"every 15m\"\ntag = \"console\"\n\
+             [daemon.deals]\nschedule = \"0 8 * * *\"\n\
+             [[daemon.deals.rules]]\nwhen = \"discount >= 40 and stores >= 10\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.request.delay_ms, 500);
+        assert_eq!(config.daemon.check.tag.as_deref(), Some("console"));
+        let deals = config.daemon.deals.unwrap();
+        assert_eq!((deals.max, deals.rules.len()), (50, 1));
+
+        assert!(toml::from_str::<Config>("[daemon.check]\nschedule = \"often\"\n").is_err());
+    }
+
+    #[test]
+    fn status_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("daemon.json");
+        assert!(DaemonStatus::load(&path).unwrap_err().to_string().contains("has not run yet"));
+
+        let status = DaemonStatus {
+            pid: std::process::id(),
+            running: true,
+            started_at: Utc::now(),
+            updated_at: Utc::now(),
+            config: "config.toml".to_string(),
+            jobs: vec![JobStatus::new("check", &"every 1h".parse().unwrap())],
+        };
+        status.save(&path).unwrap();
+        let loaded = DaemonStatus::load(&path).unwrap();
+        assert!(loaded.is_alive());
+        assert_eq!(loaded.jobs[0].schedule, "every 1h");
+        assert!(!DaemonStatus { running: false, ..loaded }.is_alive());
+    }
+}
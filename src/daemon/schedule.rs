@@ -0,0 +1,103 @@
+//! Job schedules: fixed intervals or cron expressions
+
+use crate::rules::Interval;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// When a job runs: `every 30m` (or just `30m`), or a cron expression in local
+/// time such as `0 8,20 * * *` or `@daily`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Schedule {
+    Every(Interval),
+    Cron(Box<Cron>),
+}
+
+impl Schedule {
+    /// First run strictly after `now`
+    pub fn next_after(&self, now: DateTime<Local>) -> Result<DateTime<Local>> {
+        match self {
+            Self::Every(interval) => Ok(now + interval.0),
+            Self::Cron(cron) => cron
+                .find_next_occurrence(&now, false)
+                .with_context(|| format!("No upcoming run for schedule `{self}`")),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let every = s.strip_prefix("every ").unwrap_or(s);
+        if let Ok(interval) = every.parse::<Interval>() {
+            if interval.0 <= chrono::TimeDelta::zero() {
+                anyhow::bail!("Schedule interval must be positive: `{s}`");
+            }
+            return Ok(Self::Every(interval));
+        }
+        let cron = Cron::new(s)
+            .parse()
+            .with_context(|| format!("Invalid schedule `{s}`; expected e.g. `every 30m` or a cron expression like `0 8 * * *`"))?;
+        Ok(Self::Cron(Box::new(cron)))
+    }
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.to_string()
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Every(interval) => write!(f, "every {interval}"),
+            Self::Cron(cron) => f.write_str(cron.pattern.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone, Timelike};
+
+    #[test]
+    fn intervals() {
+        let now = Local::now();
+        let schedule: Schedule = "every 30m".parse().unwrap();
+        assert_eq!(schedule.next_after(now).unwrap() - now, TimeDelta::minutes(30));
+        assert_eq!(schedule.to_string(), "every 30m");
+        assert!(matches!("2h".parse::<Schedule>().unwrap(), Schedule::Every(_)));
+        assert!("every 0s".parse::<Schedule>().is_err());
+    }
+
+    #[test]
+    fn cron_expressions() {
+        let now = Local.with_ymd_and_hms(2024, 6, 1, 9, 30, 0).unwrap();
+        let schedule: Schedule = "0 8,20 * * *".parse().unwrap();
+        let next = schedule.next_after(now).unwrap();
+        assert_eq!((next.hour(), next.minute()), (20, 0));
+        assert_eq!(schedule.to_string(), "0 8,20 * * *");
+
+        let at_eight = Local.with_ymd_and_hms(2024, 6, 1, 20, 0, 0).unwrap();
+        assert!(schedule.next_after(at_eight).unwrap() > at_eight);
+
+        let err = "every tuesday".parse::<Schedule>().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid schedule `every tuesday`"));
+    }
+}
@@ -0,0 +1,394 @@
+//! Static HTML dashboard of the watchlist, built from the local store (`kk dashboard`)
+//!
+//! Every page is self-contained: styles are inlined and charts are inline
+//! SVG, so the site can be copied anywhere and needs no JavaScript.
+
+use crate::api::{web_url, PriceHistory, PricePoint};
+use crate::format::svg::{history_svg, sparkline_svg, SvgSeries};
+use crate::html::escape;
+use crate::store::{OfferSnapshot, SeenDeal, Store};
+use crate::watchlist::WatchEntry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, Local, NaiveDate};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Days of deals highlighted on the overview
+const DEAL_DAYS: u64 = 7;
+/// Most deals highlighted on the overview
+const DEAL_LIMIT: usize = 12;
+/// Days of prices in the overview sparklines
+const SPARKLINE_DAYS: u64 = 90;
+/// Most days listed in a product's offer table
+const OFFER_LIMIT: usize = 60;
+
+const STYLE: &str = concat!(
+    "body{font-family:sans-serif;color:#1f2937;margin:2em auto;max-width:60em;padding:0 1em}",
+    "a{color:#2563eb}table{border-collapse:collapse;width:100%;margin-bottom:2em}",
+    "th,td{text-align:left;padding:.4em .6em;border-bottom:1px solid #e5e7eb}",
+    "td.num,th.num{text-align:right}tr.hit{background:#ecfdf5}",
+    ".up{color:#dc2626}.down{color:#16a34a}.muted{color:#6b7280}svg{max-width:100%;height:auto}",
+);
+
+/// A watched product with everything the store knows about it
+#[derive(Debug, Clone)]
+pub struct DashboardProduct {
+    pub entry: WatchEntry,
+    pub name: String,
+    /// Product page on `KuantoKusta` (empty if the product was never recorded)
+    pub url: String,
+    /// Oldest first
+    pub history: Vec<PricePoint>,
+    /// Newest first
+    pub offers: Vec<OfferSnapshot>,
+}
+
+impl DashboardProduct {
+    /// Latest recorded price
+    pub fn price(&self) -> Option<f64> {
+        self.history.last().map(|p| p.min)
+    }
+
+    /// Lowest recorded price
+    pub fn low(&self) -> Option<&PricePoint> {
+        self.history.iter().min_by(|a, b| a.min.total_cmp(&b.min))
+    }
+
+    /// Highest recorded price
+    pub fn high(&self) -> Option<&PricePoint> {
+        self.history.iter().max_by(|a, b| a.min.total_cmp(&b.min))
+    }
+
+    /// Price change in percent since the last price on or before `since`
+    pub fn change_since(&self, since: NaiveDate) -> Option<f64> {
+        let old = self.history.iter().rev().find(|p| p.date <= since)?.min;
+        let price = self.price()?;
+        (old > 0.0).then(|| (price - old) / old * 100.0)
+    }
+
+    fn on_target(&self) -> bool {
+        matches!((self.price(), self.entry.target_price), (Some(p), Some(t)) if p <= t)
+    }
+}
+
+/// The dashboard of a watchlist
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    pub generated: DateTime<Local>,
+    pub products: Vec<DashboardProduct>,
+    /// Deals first listed in the last week, biggest discount first
+    pub deals: Vec<SeenDeal>,
+}
+
+impl Dashboard {
+    /// Gather `entries` and the latest deals from the store
+    pub fn build(
+        store: &Store,
+        entries: &[WatchEntry],
+        generated: DateTime<Local>,
+    ) -> Result<Self> {
+        let products = entries
+            .iter()
+            .map(|entry| {
+                let stored = store.product(entry.id)?;
+                Ok(DashboardProduct {
+                    entry: entry.clone(),
+                    name: stored.as_ref().map_or_else(|| entry.label.clone(), |p| p.name.clone()),
+                    url: stored.map_or_else(String::new, |p| web_url(&p.url)),
+                    history: store.history(entry.id)?,
+                    offers: store.offers(entry.id)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let today = generated.date_naive();
+        let mut deals = store.new_deals(today - Days::new(DEAL_DAYS), today)?;
+        deals.truncate(DEAL_LIMIT);
+        Ok(Self { generated, products, deals })
+    }
+
+    /// Write `index.html` and a page per product under `out`; returns the
+    /// number of pages written
+    pub fn write(&self, out: &Path) -> Result<usize> {
+        let pages = out.join("products");
+        std::fs::create_dir_all(&pages)
+            .with_context(|| format!("Failed to create {}", pages.display()))?;
+        write_page(&out.join("index.html"), &self.index_html())?;
+        for product in &self.products {
+            let path = pages.join(format!("{}.html", product.entry.id));
+            write_page(&path, &self.product_html(product))?;
+        }
+        Ok(self.products.len() + 1)
+    }
+
+    /// The overview: watchlist table with sparklines, then deal highlights
+    pub fn index_html(&self) -> String {
+        let mut out = page_start("kk dashboard");
+        let _ = writeln!(
+            out,
+            "<h1>kk dashboard</h1>\n<p class=\"muted\">Generated {} from the local price database</p>",
+            self.generated.format("%Y-%m-%d %H:%M")
+        );
+
+        out.push_str("<h2>Watchlist</h2>\n");
+        if self.products.is_empty() {
+            out.push_str(
+                "<p>No watched products yet; add some with <code>kk watch add</code>.</p>\n",
+            );
+        } else {
+            out.push_str(
+                "<table>\n<tr><th>Product</th><th class=\"num\">Price</th><th class=\"num\">30 days</th>\
+                 <th class=\"num\">Lowest</th><th class=\"num\">Target</th><th class=\"num\">Stores</th>\
+                 <th>90 days</th></tr>\n",
+            );
+            let today = self.generated.date_naive();
+            for product in &self.products {
+                let since = today - Days::new(SPARKLINE_DAYS);
+                let recent: Vec<f64> =
+                    product.history.iter().filter(|p| p.date >= since).map(|p| p.min).collect();
+                let _ = writeln!(
+                    out,
+                    "<tr{}><td><a href=\"products/{}.html\">{}</a></td><td class=\"num\">{}</td>\
+                     <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+                     <td class=\"num\">{}</td><td>{}</td></tr>",
+                    if product.on_target() { " class=\"hit\"" } else { "" },
+                    product.entry.id,
+                    escape(&product.name),
+                    euros(product.price()),
+                    change(product.change_since(today - Days::new(30))),
+                    euros(product.low().map(|p| p.min)),
+                    euros(product.entry.target_price),
+                    product
+                        .offers
+                        .first()
+                        .map_or_else(|| "–".to_string(), |o| o.total_offers.to_string()),
+                    if recent.len() > 1 {
+                        sparkline_svg(&recent, 120.0, 24.0)
+                    } else {
+                        String::new()
+                    },
+                );
+            }
+            out.push_str("</table>\n");
+        }
+
+        let _ = writeln!(out, "<h2>Deal highlights</h2>");
+        if self.deals.is_empty() {
+            let _ = writeln!(out, "<p>No new deals in the last {DEAL_DAYS} days.</p>");
+        } else {
+            out.push_str(
+                "<table>\n<tr><th>Deal</th><th class=\"num\">Price</th><th class=\"num\">Discount</th>\
+                 <th>First listed</th></tr>\n",
+            );
+            for deal in &self.deals {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"num\">{:.2}€</td><td class=\"num\">{}</td><td>{}</td></tr>",
+                    link(&deal.product.name, &web_url(&deal.product.url)),
+                    deal.price_min,
+                    deal.discount.map_or_else(|| "–".to_string(), |d| format!("-{d}%")),
+                    deal.first_seen
+                );
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    /// A product's page: price summary, history chart and daily offers
+    pub fn product_html(&self, product: &DashboardProduct) -> String {
+        let mut out = page_start(&product.name);
+        let _ = writeln!(
+            out,
+            "<p><a href=\"../index.html\">← Watchlist</a></p>\n<h1>{}</h1>",
+            escape(&product.name)
+        );
+        if !product.url.is_empty() {
+            let _ = writeln!(out, "<p>{}</p>", link("View on KuantoKusta", &product.url));
+        }
+
+        let dated = |p: Option<&PricePoint>| {
+            p.map_or_else(|| "–".to_string(), |p| format!("{:.2}€ ({})", p.min, p.date))
+        };
+        let _ = writeln!(
+            out,
+            "<table>\n<tr><th>Current</th><td>{}</td></tr>\n<tr><th>Lowest</th><td>{}</td></tr>\n\
+             <tr><th>Highest</th><td>{}</td></tr>\n<tr><th>Target</th><td>{}</td></tr>\n</table>",
+            dated(product.history.last()),
+            dated(product.low()),
+            dated(product.high()),
+            euros(product.entry.target_price)
+        );
+
+        out.push_str("<h2>Price history</h2>\n");
+        if product.history.is_empty() {
+            out.push_str("<p>No prices recorded yet.</p>\n");
+        } else {
+            let history =
+                PriceHistory { min_axis: 0.0, max_axis: 0.0, data: product.history.clone() };
+            let series = [SvgSeries { label: "Price", history: &history }];
+            out.push_str(&history_svg(&product.name, &series));
+        }
+
+        out.push_str("<h2>Offers</h2>\n");
+        if product.offers.is_empty() {
+            out.push_str("<p>No listings recorded yet.</p>\n");
+        } else {
+            out.push_str(
+                "<table>\n<tr><th>Date</th><th class=\"num\">Lowest price</th>\
+                 <th class=\"num\">Stores</th><th class=\"num\">Discount</th></tr>\n",
+            );
+            for offer in product.offers.iter().take(OFFER_LIMIT) {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"num\">{:.2}€</td><td class=\"num\">{}</td>\
+                     <td class=\"num\">{}</td></tr>",
+                    offer.date,
+                    offer.price_min,
+                    offer.total_offers,
+                    offer.discount.map_or_else(|| "–".to_string(), |d| format!("-{d}%"))
+                );
+            }
+            out.push_str("</table>\n");
+            if !product.url.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "<p class=\"muted\">The offers of each store are listed on {}.</p>",
+                    link("KuantoKusta", &product.url)
+                );
+            }
+        }
+        let _ = writeln!(
+            out,
+            "<p class=\"muted\">Generated {}</p>\n</body></html>",
+            self.generated.format("%Y-%m-%d %H:%M")
+        );
+        out
+    }
+}
+
+fn page_start(title: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title>\n<style>{STYLE}</style></head>\n<body>\n",
+        escape(title)
+    )
+}
+
+fn write_page(path: &Path, html: &str) -> Result<()> {
+    std::fs::write(path, html).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn euros(price: Option<f64>) -> String {
+    price.map_or_else(|| "–".to_string(), |p| format!("{p:.2}€"))
+}
+
+fn change(pct: Option<f64>) -> String {
+    match pct {
+        Some(pct) if pct.abs() >= 0.05 => {
+            let class = if pct < 0.0 { "down" } else { "up" };
+            format!("<span class=\"{class}\">{pct:+.1}%</span>")
+        }
+        Some(_) => "0.0%".to_string(),
+        None => "–".to_string(),
+    }
+}
+
+fn link(text: &str, url: &str) -> String {
+    if url.is_empty() {
+        escape(text)
+    } else {
+        format!("<a href=\"{}\">{}</a>", escape(url), escape(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Badges, Deal, Product, Tags};
+    use chrono::TimeZone;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn product(id: u64, name: &str, price: f64) -> Product {
+        Product::fixture(id, price).with_name(name).with_category("Consoles").with_offers(12)
+    }
+
+    fn entry(id: u64, target: Option<f64>) -> WatchEntry {
+        WatchEntry {
+            id,
+            label: format!("#{id}"),
+            tags: vec![],
+            target_price: target,
+            max_drop: None,
+            rules: vec![],
+            added: date("2024-01-01"),
+        }
+    }
+
+    fn dashboard() -> Dashboard {
+        let mut store = Store::open_in_memory().unwrap();
+        store.record_products(&[product(1, "PS5 <Slim>", 500.0)], date("2024-05-01")).unwrap();
+        store.record_products(&[product(1, "PS5 <Slim>", 480.0)], date("2024-05-20")).unwrap();
+        store.record_products(&[product(1, "PS5 <Slim>", 440.0)], date("2024-06-07")).unwrap();
+        let deal = Deal {
+            id: 9,
+            name: "Switch".to_string(),
+            images: vec![],
+            price_min: 249.0,
+            total_offers: 8,
+            url: "/p/9".to_string(),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(30), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        };
+        store.record_deals(&[deal], date("2024-06-05")).unwrap();
+
+        let generated = Local.with_ymd_and_hms(2024, 6, 8, 9, 0, 0).unwrap();
+        Dashboard::build(&store, &[entry(1, Some(450.0)), entry(2, None)], generated).unwrap()
+    }
+
+    #[test]
+    fn builds_from_store() {
+        let dashboard = dashboard();
+        let ps5 = &dashboard.products[0];
+        assert_eq!(ps5.name, "PS5 <Slim>");
+        assert_eq!(ps5.url, "https://www.kuantokusta.pt/p/1");
+        assert_eq!(ps5.offers.len(), 3);
+        assert!(ps5.on_target());
+        let change = ps5.change_since(date("2024-05-09")).unwrap();
+        assert!((change + 12.0).abs() < 1e-9);
+        assert_eq!(ps5.low().unwrap().date, date("2024-06-07"));
+
+        let unknown = &dashboard.products[1];
+        assert_eq!((unknown.name.as_str(), unknown.price()), ("#2", None));
+        assert_eq!(dashboard.deals.len(), 1);
+    }
+
+    #[test]
+    fn writes_static_site() {
+        let dashboard = dashboard();
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(dashboard.write(dir.path()).unwrap(), 3);
+
+        let index = std::fs::read_to_string(dir.path().join("index.html")).unwrap();
+        assert!(index
+            .contains("<tr class=\"hit\"><td><a href=\"products/1.html\">PS5 &lt;Slim&gt;</a>"));
+        assert!(index.contains("<td class=\"num\">440.00€</td>"));
+        assert!(index.contains("<polyline"));
+        assert!(index.contains("<a href=\"https://www.kuantokusta.pt/p/9\">Switch</a>"));
+        assert!(index.contains("-30%"));
+        assert!(!index.contains("<script"));
+
+        let page = std::fs::read_to_string(dir.path().join("products/1.html")).unwrap();
+        assert!(page.contains("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(page.contains("<tr><th>Lowest</th><td>440.00€ (2024-06-07)</td></tr>"));
+        assert!(page.contains("<tr><td>2024-06-07</td><td class=\"num\">440.00€</td>"));
+        let empty = std::fs::read_to_string(dir.path().join("products/2.html")).unwrap();
+        assert!(empty.contains("No prices recorded yet."));
+    }
+}
@@ -0,0 +1,65 @@
+//! Added, removed and re-priced products between two lists, matched by ID
+
+/// How the `new` list differs from the `old` one
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyedDiff<'a, T> {
+    /// In `new` only, in its order
+    pub added: Vec<&'a T>,
+    /// In `old` only, in its order
+    pub removed: Vec<&'a T>,
+    /// In both at prices at least a cent apart: the `new` item and the old price
+    pub repriced: Vec<(&'a T, f64)>,
+    pub unchanged: usize,
+}
+
+impl<'a, T> KeyedDiff<'a, T> {
+    /// Compare lists whose items are identified by `id` and priced by `price`
+    pub fn new(
+        old: &'a [T],
+        new: &'a [T],
+        id: impl Fn(&T) -> u64,
+        price: impl Fn(&T) -> f64,
+    ) -> Self {
+        let mut diff =
+            Self { added: Vec::new(), removed: Vec::new(), repriced: Vec::new(), unchanged: 0 };
+        for item in new {
+            match old.iter().find(|o| id(o) == id(item)) {
+                None => diff.added.push(item),
+                Some(o) if (price(o) - price(item)).abs() >= 0.005 => {
+                    diff.repriced.push((item, price(o)));
+                }
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        diff.removed = old.iter().filter(|o| !new.iter().any(|n| id(n) == id(o))).collect();
+        diff
+    }
+}
+
+/// Change from `old` to `new` in percent (0 when `old` is not positive)
+pub fn change_pct(old: f64, new: f64) -> f64 {
+    if old > 0.0 {
+        (new - old) / old * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_by_id() {
+        let old = [(1, 10.0), (2, 20.0), (3, 30.0)];
+        let new = [(4, 40.0), (3, 30.001), (1, 8.0)];
+        let diff = KeyedDiff::new(&old, &new, |i| i.0, |i| i.1);
+
+        assert_eq!(diff.added, [&(4, 40.0)]);
+        assert_eq!(diff.removed, [&(2, 20.0)]);
+        assert_eq!(diff.repriced, [(&(1, 8.0), 10.0)]);
+        assert_eq!(diff.unchanged, 1);
+        assert!((change_pct(10.0, 8.0) + 20.0).abs() < 1e-9);
+        assert!(change_pct(0.0, 8.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,589 @@
+//! Periodic digest of the watchlist and deals, built from the local store
+
+use crate::analysis::Period;
+use crate::api::web_url;
+use crate::html::escape;
+use crate::notify::Message;
+use crate::store::Store;
+use crate::watchlist::WatchEntry;
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+/// Most entries listed per section
+const SECTION_LIMIT: usize = 10;
+
+/// What happened to the watched products and deals over a period
+///
+/// Prices on `until` are compared with the last price on or before `since`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Digest {
+    pub period: Period,
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    /// Watched products with a price recorded during the period
+    pub products: usize,
+    /// Biggest price drops first
+    pub drops: Vec<PriceDrop>,
+    /// Products at their lowest recorded price, furthest below the previous low first
+    pub lows: Vec<AllTimeLow>,
+    /// Furthest below the target first
+    pub targets: Vec<TargetHit>,
+    /// Deals first listed during the period in the categories of watched products
+    pub deals: Vec<NewDeal>,
+    /// Products that first showed up in saved search results during the period
+    pub listings: Vec<NewListing>,
+    /// Cost of buying every watched product, then and now
+    pub basket: Option<Basket>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceDrop {
+    pub product_id: u64,
+    pub name: String,
+    pub url: String,
+    pub old_price: f64,
+    pub price: f64,
+    pub change_pct: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllTimeLow {
+    pub product_id: u64,
+    pub name: String,
+    pub url: String,
+    pub price: f64,
+    /// Lowest price recorded before the current one
+    pub previous_low: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetHit {
+    pub product_id: u64,
+    pub name: String,
+    pub url: String,
+    pub target_price: f64,
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewDeal {
+    pub product_id: u64,
+    pub name: String,
+    pub category: String,
+    pub url: String,
+    pub price: f64,
+    pub discount: Option<u8>,
+    pub first_seen: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewListing {
+    pub search: String,
+    pub product_id: u64,
+    pub name: String,
+    pub url: String,
+    pub price: f64,
+    pub first_seen: NaiveDate,
+}
+
+/// Total price of the products priced both at the start and the end of the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Basket {
+    pub products: usize,
+    pub old_total: f64,
+    pub total: f64,
+    pub change_pct: f64,
+}
+
+impl Digest {
+    /// Summarize `entries` and new deals over the `period` ending on `until`
+    pub fn build(
+        store: &Store,
+        entries: &[WatchEntry],
+        period: Period,
+        until: NaiveDate,
+    ) -> Result<Self> {
+        let since = period.before(until);
+        let mut digest = Self {
+            period,
+            since,
+            until,
+            products: 0,
+            drops: Vec::new(),
+            lows: Vec::new(),
+            targets: Vec::new(),
+            deals: Vec::new(),
+            listings: Vec::new(),
+            basket: None,
+        };
+        let mut categories = BTreeSet::new();
+        let (mut old_total, mut total, mut priced) = (0.0, 0.0, 0);
+
+        for entry in entries {
+            let stored = store.product(entry.id)?;
+            let url = stored.as_ref().map_or_else(String::new, |p| web_url(&p.url));
+            if let Some(product) = stored.filter(|p| !p.category.is_empty()) {
+                categories.insert(product.category);
+            }
+
+            let history: Vec<_> =
+                store.history(entry.id)?.into_iter().filter(|p| p.date <= until).collect();
+            let Some((current, earlier)) = history.split_last().filter(|(p, _)| p.date > since)
+            else {
+                continue;
+            };
+            digest.products += 1;
+            let price = current.min;
+            let name = entry.label.clone();
+
+            // Products first recorded during the period start from their first price
+            let old = earlier.iter().rev().find(|p| p.date <= since).unwrap_or(&history[0]).min;
+            if old > 0.0 {
+                if price < old {
+                    digest.drops.push(PriceDrop {
+                        product_id: entry.id,
+                        name: name.clone(),
+                        url: url.clone(),
+                        old_price: old,
+                        price,
+                        change_pct: (price - old) / old * 100.0,
+                    });
+                }
+                old_total += old;
+                total += price;
+                priced += 1;
+            }
+
+            let previous_low = earlier.iter().map(|p| p.min).reduce(f64::min);
+            if let Some(previous_low) = previous_low.filter(|&low| price <= low) {
+                digest.lows.push(AllTimeLow {
+                    product_id: entry.id,
+                    name: name.clone(),
+                    url: url.clone(),
+                    price,
+                    previous_low,
+                });
+            }
+
+            if let Some(target) = entry.target_price.filter(|&t| price <= t) {
+                digest.targets.push(TargetHit {
+                    product_id: entry.id,
+                    name,
+                    url,
+                    target_price: target,
+                    price,
+                });
+            }
+        }
+
+        digest.rank();
+        digest.deals = store
+            .new_deals(since, until)?
+            .into_iter()
+            .filter(|d| categories.contains(&d.product.category))
+            .take(SECTION_LIMIT)
+            .map(|d| NewDeal {
+                product_id: d.product.id,
+                url: web_url(&d.product.url),
+                name: d.product.name,
+                category: d.product.category,
+                price: d.price_min,
+                discount: d.discount,
+                first_seen: d.first_seen,
+            })
+            .collect();
+        digest.listings = new_listings(store, since, until)?;
+        if priced > 0 {
+            digest.basket = Some(Basket {
+                products: priced,
+                old_total,
+                total,
+                change_pct: (total - old_total) / old_total * 100.0,
+            });
+        }
+        Ok(digest)
+    }
+
+    /// e.g. "kk weekly digest (2024-06-01 – 2024-06-08)"
+    pub fn title(&self) -> String {
+        format!("kk {} digest ({} – {})", self.period.adjective(), self.since, self.until)
+    }
+
+    /// Order the product sections best first and keep the top [`SECTION_LIMIT`]
+    fn rank(&mut self) {
+        let below = |price: f64, mark: f64| if mark > 0.0 { price / mark } else { 1.0 };
+        self.drops.sort_by(|a, b| a.change_pct.total_cmp(&b.change_pct));
+        self.drops.truncate(SECTION_LIMIT);
+        self.lows.sort_by(|a, b| {
+            below(a.price, a.previous_low).total_cmp(&below(b.price, b.previous_low))
+        });
+        self.lows.truncate(SECTION_LIMIT);
+        self.targets.sort_by(|a, b| {
+            below(a.price, a.target_price).total_cmp(&below(b.price, b.target_price))
+        });
+        self.targets.truncate(SECTION_LIMIT);
+    }
+
+    /// One line with the section counts, as shown by `kk daemon status`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} product(s), {} drop(s), {} at all-time low, {} target(s) reached, {} new deal(s), \
+             {} new in saved searches",
+            self.products,
+            self.drops.len(),
+            self.lows.len(),
+            self.targets.len(),
+            self.deals.len(),
+            self.listings.len()
+        )
+    }
+
+    fn is_empty(&self) -> bool {
+        self.drops.is_empty()
+            && self.lows.is_empty()
+            && self.targets.is_empty()
+            && self.deals.is_empty()
+            && self.listings.is_empty()
+    }
+
+    /// Markdown rendering, also the text body of notifications
+    pub fn to_markdown(&self) -> String {
+        let link = |name: &str, url: &str| {
+            if url.is_empty() {
+                name.to_string()
+            } else {
+                format!("[{name}]({url})")
+            }
+        };
+        let mut out = format!("# {}\n", self.title());
+        if let Some(basket) = &self.basket {
+            let _ = write!(out, "\n**Basket:** {}\n", basket_line(basket));
+        }
+        if self.is_empty() {
+            let _ = writeln!(out, "\nNothing to report this {}.", self.period.noun());
+            return out;
+        }
+        let mut section = |title: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                let _ = write!(out, "\n## {title}\n\n");
+                for item in items {
+                    let _ = writeln!(out, "- {item}");
+                }
+            }
+        };
+        section(
+            "Biggest drops",
+            self.drops
+                .iter()
+                .map(|d| format!("{}: {}", link(&d.name, &d.url), drop_line(d)))
+                .collect(),
+        );
+        section(
+            "At all-time lows",
+            self.lows
+                .iter()
+                .map(|l| format!("{}: {}", link(&l.name, &l.url), low_line(l)))
+                .collect(),
+        );
+        section(
+            "Targets reached",
+            self.targets
+                .iter()
+                .map(|t| format!("{}: {}", link(&t.name, &t.url), target_line(t)))
+                .collect(),
+        );
+        section(
+            "New deals in watched categories",
+            self.deals
+                .iter()
+                .map(|d| format!("{} ({}): {}", link(&d.name, &d.url), d.category, deal_line(d)))
+                .collect(),
+        );
+        section(
+            "New in saved searches",
+            self.listings
+                .iter()
+                .map(|l| format!("{} (`{}`): {:.2}€", link(&l.name, &l.url), l.search, l.price))
+                .collect(),
+        );
+        out
+    }
+
+    /// Standalone HTML rendering, used as the body of digest emails
+    pub fn to_html(&self) -> String {
+        let link = |name: &str, url: &str| {
+            if url.is_empty() {
+                escape(name)
+            } else {
+                format!("<a href=\"{}\">{}</a>", escape(url), escape(name))
+            }
+        };
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html><body style=\"font-family: sans-serif; color: #222\">\n",
+        );
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&self.title()));
+        if let Some(basket) = &self.basket {
+            let _ = writeln!(out, "<p><b>Basket:</b> {}</p>", escape(&basket_line(basket)));
+        }
+        if self.is_empty() {
+            let _ = writeln!(out, "<p>Nothing to report this {}.</p>", self.period.noun());
+        }
+        let mut section = |title: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                let _ = writeln!(out, "<h3>{title}</h3>\n<ul>");
+                for item in items {
+                    let _ = writeln!(out, "<li>{item}</li>");
+                }
+                out.push_str("</ul>\n");
+            }
+        };
+        section(
+            "Biggest drops",
+            self.drops
+                .iter()
+                .map(|d| format!("{}: {}", link(&d.name, &d.url), escape(&drop_line(d))))
+                .collect(),
+        );
+        section(
+            "At all-time lows",
+            self.lows
+                .iter()
+                .map(|l| format!("{}: {}", link(&l.name, &l.url), escape(&low_line(l))))
+                .collect(),
+        );
+        section(
+            "Targets reached",
+            self.targets
+                .iter()
+                .map(|t| format!("{}: {}", link(&t.name, &t.url), escape(&target_line(t))))
+                .collect(),
+        );
+        section(
+            "New deals in watched categories",
+            self.deals
+                .iter()
+                .map(|d| {
+                    let details = format!("({}): {}", d.category, deal_line(d));
+                    format!("{} {}", link(&d.name, &d.url), escape(&details))
+                })
+                .collect(),
+        );
+        section(
+            "New in saved searches",
+            self.listings
+                .iter()
+                .map(|l| {
+                    let details = format!("({}): {:.2}€", l.search, l.price);
+                    format!("{} {}", link(&l.name, &l.url), escape(&details))
+                })
+                .collect(),
+        );
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    /// A notification with the Markdown as text and the HTML for email
+    pub fn message(&self) -> Message {
+        Message {
+            title: self.title(),
+            text: self.to_markdown(),
+            alerts: Vec::new(),
+            html: Some(self.to_html()),
+        }
+    }
+}
+
+/// Products first listed by saved searches after `since`
+fn new_listings(store: &Store, since: NaiveDate, until: NaiveDate) -> Result<Vec<NewListing>> {
+    Ok(store
+        .new_search_results(since, until)?
+        .into_iter()
+        .take(SECTION_LIMIT)
+        .map(|l| NewListing {
+            search: l.search,
+            product_id: l.product.id,
+            url: web_url(&l.product.url),
+            name: l.product.name,
+            price: l.price_min,
+            first_seen: l.first_seen,
+        })
+        .collect())
+}
+
+fn basket_line(basket: &Basket) -> String {
+    format!(
+        "{:.2}€ → {:.2}€ ({:+.1}%) for {} product(s)",
+        basket.old_total, basket.total, basket.change_pct, basket.products
+    )
+}
+
+fn drop_line(drop: &PriceDrop) -> String {
+    format!("{:.2}€ → {:.2}€ ({:+.1}%)", drop.old_price, drop.price, drop.change_pct)
+}
+
+fn low_line(low: &AllTimeLow) -> String {
+    format!("{:.2}€ (previous low {:.2}€)", low.price, low.previous_low)
+}
+
+fn target_line(hit: &TargetHit) -> String {
+    format!("{:.2}€ (target {:.2}€)", hit.price, hit.target_price)
+}
+
+fn deal_line(deal: &NewDeal) -> String {
+    let mut out = format!("{:.2}€", deal.price);
+    if let Some(discount) = deal.discount {
+        let _ = write!(out, " · -{discount}%");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Badges, Deal, Product, Tags};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn product(id: u64, category: &str, price: f64) -> Product {
+        Product::fixture(id, price).with_category(category)
+    }
+
+    fn entry(id: u64, label: &str, target: Option<f64>) -> WatchEntry {
+        let mut entry: WatchEntry =
+            toml::from_str(&format!("id = {id}\nlabel = \"{label}\"\nadded = \"2024-06-01\"\n"))
+                .unwrap();
+        entry.target_price = target;
+        entry
+    }
+
+    fn store() -> Store {
+        let mut store = Store::open_in_memory().unwrap();
+        let prices = [
+            ("2024-05-20", [500.0, 100.0, 80.0]),
+            ("2024-06-01", [480.0, 90.0, 85.0]),
+            ("2024-06-05", [450.0, 95.0, 85.0]),
+            ("2024-06-08", [440.0, 100.0, 79.0]),
+        ];
+        for (day, [a, b, c]) in prices {
+            let products =
+                [product(1, "Consoles", a), product(2, "Consoles", b), product(3, "Phones", c)];
+            store.record_products(&products, date(day)).unwrap();
+        }
+        let deal = |id, discount| Deal {
+            id,
+            name: format!("Deal {id}"),
+            images: vec![],
+            price_min: 199.0,
+            total_offers: 4,
+            url: format!("/p/{id}"),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(discount), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        };
+        store.record_products(&[product(8, "Consoles", 250.0)], date("2024-05-01")).unwrap();
+        store.record_products(&[product(9, "TVs", 250.0)], date("2024-05-01")).unwrap();
+        store.record_deals(&[deal(7, 50), deal(8, 20), deal(9, 30)], date("2024-06-03")).unwrap();
+        store.record_deals(&[deal(7, 50)], date("2024-05-01")).unwrap();
+        store
+            .record_search_results("ps5", &[product(1, "Consoles", 500.0)], date("2024-05-20"))
+            .unwrap();
+        let results = [product(1, "Consoles", 440.0), product(4, "Consoles", 399.0)];
+        store.record_search_results("ps5", &results, date("2024-06-08")).unwrap();
+        store
+    }
+
+    #[test]
+    fn builds_sections_from_store() {
+        let entries =
+            [entry(1, "PS5", Some(450.0)), entry(2, "Controller", None), entry(3, "Phone", None)];
+        let digest = Digest::build(&store(), &entries, Period::Week, date("2024-06-08")).unwrap();
+        assert_eq!(digest.since, date("2024-06-01"));
+        assert_eq!(digest.products, 3);
+
+        let drops: Vec<_> = digest.drops.iter().map(|d| (d.product_id, d.old_price)).collect();
+        assert_eq!(drops, [(1, 480.0), (3, 85.0)]);
+        let lows: Vec<_> = digest.lows.iter().map(|l| (l.product_id, l.previous_low)).collect();
+        assert_eq!(lows, [(1, 450.0), (3, 80.0)]);
+        assert_eq!(digest.targets.len(), 1);
+        assert_eq!(digest.targets[0].name, "PS5");
+
+        // Deal 7 was listed before the period and deal 9 isn't in a watched category
+        let deals: Vec<_> = digest.deals.iter().map(|d| d.product_id).collect();
+        assert_eq!(deals, [8]);
+        assert_eq!(digest.deals[0].url, "https://www.kuantokusta.pt/p/8");
+        let listings: Vec<_> = digest.listings.iter().map(|l| (l.product_id, l.price)).collect();
+        assert_eq!(listings, [(4, 399.0)]);
+
+        let basket = digest.basket.as_ref().unwrap();
+        assert_eq!(basket.products, 3);
+        assert!((basket.old_total - 655.0).abs() < 1e-9);
+        assert!((basket.total - 619.0).abs() < 1e-9);
+        assert_eq!(
+            digest.summary(),
+            "3 product(s), 2 drop(s), 2 at all-time low, 1 target(s) reached, 1 new deal(s), \
+             1 new in saved searches"
+        );
+    }
+
+    #[test]
+    fn limits_lows_and_targets() {
+        let mut store = Store::open_in_memory().unwrap();
+        let ids = 1..=12;
+        let products: Vec<_> = ids.clone().map(|id| product(id, "Consoles", 100.0)).collect();
+        store.record_products(&products, date("2024-05-20")).unwrap();
+        let products: Vec<_> =
+            ids.clone().map(|id| product(id, "Consoles", 100.0 - id as f64)).collect();
+        store.record_products(&products, date("2024-06-08")).unwrap();
+        let entries: Vec<_> = ids.map(|id| entry(id, "P", Some(100.0))).collect();
+
+        let digest = Digest::build(&store, &entries, Period::Week, date("2024-06-08")).unwrap();
+        let lows: Vec<_> = digest.lows.iter().map(|l| l.product_id).collect();
+        assert_eq!(lows, [12, 11, 10, 9, 8, 7, 6, 5, 4, 3]);
+        let targets: Vec<_> = digest.targets.iter().map(|t| t.product_id).collect();
+        assert_eq!(targets, lows);
+    }
+
+    #[test]
+    fn renders_markdown_and_html() {
+        let entries = [entry(1, "PS5 <Slim>", Some(450.0))];
+        let digest = Digest::build(&store(), &entries, Period::Week, date("2024-06-08")).unwrap();
+
+        let markdown = digest.to_markdown();
+        assert!(markdown.starts_with("# kk weekly digest (2024-06-01 – 2024-06-08)\n"));
+        assert!(markdown.contains("**Basket:** 480.00€ → 440.00€ (-8.3%) for 1 product(s)"));
+        assert!(markdown.contains(
+            "## Biggest drops\n\n- [PS5 <Slim>](https://www.kuantokusta.pt/p/1): \
+             480.00€ → 440.00€ (-8.3%)\n"
+        ));
+        assert!(markdown
+            .contains("- [Deal 8](https://www.kuantokusta.pt/p/8) (Consoles): 199.00€ · -20%\n"));
+        assert!(markdown.contains(
+            "## New in saved searches\n\n- [Product 4](https://www.kuantokusta.pt/p/4) (`ps5`): 399.00€\n"
+        ));
+
+        let html = digest.to_html();
+        assert!(html.contains("<h3>Targets reached</h3>"));
+        assert!(html.contains(">PS5 &lt;Slim&gt;</a>: 440.00€ (target 450.00€)</li>"));
+
+        let message = digest.message();
+        assert_eq!(message.title, digest.title());
+        assert!(message.alerts.is_empty());
+
+        let quiet = Digest::build(&store(), &entries, Period::Week, date("2024-07-20")).unwrap();
+        assert_eq!(quiet.products, 0);
+        assert!(quiet.to_markdown().ends_with("\nNothing to report this week.\n"));
+    }
+}
@@ -0,0 +1,176 @@
+//! Prometheus metrics of the watched products and the API client (`kk exporter`)
+
+use crate::api::metrics::{ClientMetrics, EndpointStats, LATENCY_BUCKETS};
+use crate::api::Product;
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Name, help and value of a per-product gauge
+type Gauge = (&'static str, &'static str, fn(&Product) -> Option<f64>);
+/// Name, help and value of a per-endpoint counter
+type Counter = (&'static str, &'static str, fn(&EndpointStats) -> u64);
+
+/// The latest prices of the watched products
+#[derive(Debug, Clone, Default)]
+pub struct PriceGauges {
+    /// In watchlist order
+    pub products: Vec<Product>,
+    pub refreshed: Option<DateTime<Utc>>,
+}
+
+impl PriceGauges {
+    /// Replace the products with `fetched`, in watchlist order; a product
+    /// that couldn't be fetched keeps its previous values
+    pub fn update(&mut self, watched: &[u64], mut fetched: Vec<Product>, now: DateTime<Utc>) {
+        let mut previous = std::mem::take(&mut self.products);
+        self.products = watched
+            .iter()
+            .filter_map(|id| {
+                let take = |list: &mut Vec<Product>| {
+                    list.iter().position(|p| p.id == *id).map(|i| list.swap_remove(i))
+                };
+                take(&mut fetched).or_else(|| take(&mut previous))
+            })
+            .collect();
+        self.refreshed = Some(now);
+    }
+
+    /// Render the gauges and the client's counters
+    pub fn render(&self, client: &ClientMetrics) -> String {
+        let mut out = String::new();
+        let gauges: [Gauge; 3] = [
+            ("kk_price_min_euros", "Lowest price of a watched product", |p| Some(p.price_min)),
+            ("kk_total_offers", "Number of stores selling a watched product", |p| {
+                Some(f64::from(p.total_offers))
+            }),
+            ("kk_discount_percent", "Advertised discount of a watched product", |p| {
+                p.discount().map(f64::from)
+            }),
+        ];
+        for (name, help, value) in gauges {
+            header(&mut out, name, help, "gauge");
+            for product in &self.products {
+                if let Some(value) = value(product) {
+                    let labels = format!(
+                        "id=\"{}\",name=\"{}\",brand=\"{}\"",
+                        product.id,
+                        label(&product.name),
+                        label(&product.brand)
+                    );
+                    let _ = writeln!(out, "{name}{{{labels}}} {value}");
+                }
+            }
+        }
+        if let Some(refreshed) = self.refreshed {
+            let name = "kk_last_refresh_timestamp_seconds";
+            header(&mut out, name, "Time of the last price refresh", "gauge");
+            let _ = writeln!(out, "{name} {}", refreshed.timestamp());
+        }
+        render_client(&mut out, client);
+        out
+    }
+}
+
+fn render_client(out: &mut String, client: &ClientMetrics) {
+    let endpoints = client.endpoints();
+    let counters: [Counter; 2] = [
+        ("kk_client_requests_total", "API requests by endpoint", |s| s.requests),
+        ("kk_client_request_errors_total", "Failed API requests by endpoint", |s| s.errors),
+    ];
+    for (name, help, value) in counters {
+        header(out, name, help, "counter");
+        for (endpoint, stats) in &endpoints {
+            let _ = writeln!(out, "{name}{{endpoint=\"{endpoint}\"}} {}", value(stats));
+        }
+    }
+
+    let name = "kk_client_request_duration_seconds";
+    header(out, name, "API request latency by endpoint", "histogram");
+    for (endpoint, stats) in &endpoints {
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+            let _ =
+                writeln!(out, "{name}_bucket{{endpoint=\"{endpoint}\",le=\"{bound}\"}} {count}");
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{endpoint=\"{endpoint}\",le=\"+Inf\"}} {}",
+            stats.requests
+        );
+        let _ = writeln!(out, "{name}_sum{{endpoint=\"{endpoint}\"}} {}", stats.seconds_total);
+        let _ = writeln!(out, "{name}_count{{endpoint=\"{endpoint}\"}} {}", stats.requests);
+    }
+
+    let (hits, misses) = client.cache();
+    header(out, "kk_client_cache_hits_total", "Product lookups answered from the cache", "counter");
+    let _ = writeln!(out, "kk_client_cache_hits_total {hits}");
+    header(out, "kk_client_cache_misses_total", "Product lookups sent to the API", "counter");
+    let _ = writeln!(out, "kk_client_cache_misses_total {misses}");
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+}
+
+/// Escape a label value
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Badges;
+    use std::time::Duration;
+
+    fn product(id: u64, price: f64) -> Product {
+        Product::fixture(id, price)
+            .with_name(&format!("Product \"{id}\""))
+            .with_brand("Acme")
+            .with_offers(7)
+    }
+
+    #[test]
+    fn keeps_previous_values_of_failed_fetches() {
+        let mut gauges = PriceGauges::default();
+        let now = Utc::now();
+        gauges.update(&[1, 2], vec![product(2, 20.0), product(1, 10.0)], now);
+        gauges.update(&[2, 1, 3], vec![product(1, 9.0)], now);
+        let prices: Vec<_> = gauges.products.iter().map(|p| (p.id, p.price_min)).collect();
+        assert_eq!(prices, [(2, 20.0), (1, 9.0)]);
+        gauges.update(&[1], vec![], now);
+        assert_eq!(gauges.products.len(), 1);
+    }
+
+    #[test]
+    fn renders_text_format() {
+        let mut discounted = product(1, 449.99);
+        discounted.badges = Badges { discount_percentage: Some(12), ..Badges::default() };
+        let gauges = PriceGauges {
+            products: vec![discounted, product(2, 20.0)],
+            refreshed: Some("2024-06-01T08:00:00Z".parse().unwrap()),
+        };
+        let client = ClientMetrics::default();
+        client.record("product", Duration::from_millis(300), true);
+        client.cache_hit();
+
+        let text = gauges.render(&client);
+        assert!(text.contains("# TYPE kk_price_min_euros gauge\n"));
+        assert!(text.contains(
+            "kk_price_min_euros{id=\"1\",name=\"Product \\\"1\\\"\",brand=\"Acme\"} 449.99\n"
+        ));
+        assert!(text
+            .contains("kk_total_offers{id=\"2\",name=\"Product \\\"2\\\"\",brand=\"Acme\"} 7\n"));
+        assert_eq!(text.matches("kk_discount_percent{").count(), 1);
+        assert!(text.contains("kk_last_refresh_timestamp_seconds 1717228800\n"));
+        assert!(text.contains("kk_client_requests_total{endpoint=\"product\"} 1\n"));
+        assert!(text.contains(
+            "kk_client_request_duration_seconds_bucket{endpoint=\"product\",le=\"0.25\"} 0\n\
+             kk_client_request_duration_seconds_bucket{endpoint=\"product\",le=\"0.5\"} 1\n"
+        ));
+        assert!(text.contains("kk_client_request_duration_seconds_count{endpoint=\"product\"} 1\n"));
+        assert!(text.contains("kk_client_cache_hits_total 1\n"));
+    }
+}
@@ -0,0 +1,263 @@
+//! Atom and RSS 2.0 feeds of deals and fired alerts (`kk feed`)
+
+use crate::api::{web_url, Deal};
+use crate::html::escape;
+use crate::store::LoggedAlert;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write as _;
+
+/// Feed syntax
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+}
+
+/// A feed, rendered with [`Feed::render`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feed {
+    /// Stable feed ID, e.g. `urn:kuantokusta:deals`
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub link: String,
+    pub updated: DateTime<Utc>,
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEntry {
+    /// Stable per product and price, so readers show a product again only
+    /// when its price changes
+    pub guid: String,
+    pub title: String,
+    pub link: String,
+    pub summary: String,
+    pub updated: DateTime<Utc>,
+    /// Image URL, added as an enclosure
+    pub image: Option<String>,
+}
+
+impl FeedEntry {
+    /// Entry for a current deal
+    pub fn from_deal(deal: &Deal, updated: DateTime<Utc>) -> Self {
+        let discount = deal.discount();
+        let mut title = format!("{} – {:.2}€", deal.name, deal.price_min);
+        let mut summary = format!("{:.2}€ at {} store(s)", deal.price_min, deal.total_offers);
+        if let Some(discount) = discount {
+            let _ = write!(title, " (-{discount}%)");
+            let _ = write!(summary, ", {discount}% off");
+        }
+        Self {
+            guid: guid(deal.id, deal.price_min),
+            title,
+            link: web_url(&deal.url),
+            summary,
+            updated,
+            image: deal.images.first().cloned(),
+        }
+    }
+
+    /// Entry for an alert kept in the local store
+    pub fn from_alert(logged: &LoggedAlert) -> Self {
+        let alert = &logged.alert;
+        let mut summary = alert.old_price.map_or_else(
+            || format!("{:.2}€", alert.new_price),
+            |old| format!("{old:.2}€ → {:.2}€", alert.new_price),
+        );
+        if let Some(discount) = alert.discount {
+            let _ = write!(summary, " (-{discount}%)");
+        }
+        Self {
+            guid: guid(alert.product_id, alert.new_price),
+            title: format!("{}: {}", alert.name, alert.reason),
+            link: web_url(&alert.url),
+            summary,
+            updated: logged.fired_at,
+            image: logged.image.clone(),
+        }
+    }
+}
+
+/// e.g. `urn:kuantokusta:product:12345:44999` for 449.99€
+fn guid(product_id: u64, price: f64) -> String {
+    format!("urn:kuantokusta:product:{product_id}:{:.0}", price * 100.0)
+}
+
+impl Feed {
+    pub fn render(&self, format: FeedFormat) -> String {
+        match format {
+            FeedFormat::Atom => self.to_atom(),
+            FeedFormat::Rss => self.to_rss(),
+        }
+    }
+
+    /// Atom 1.0 (RFC 4287)
+    pub fn to_atom(&self) -> String {
+        let date = |d: &DateTime<Utc>| d.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+        );
+        let _ = writeln!(out, "  <id>{}</id>", escape(&self.id));
+        let _ = writeln!(out, "  <title>{}</title>", escape(&self.title));
+        let _ = writeln!(out, "  <subtitle>{}</subtitle>", escape(&self.description));
+        let _ = writeln!(out, "  <link href=\"{}\"/>", escape(&self.link));
+        let _ = writeln!(out, "  <updated>{}</updated>", date(&self.updated));
+        out.push_str("  <author><name>kk</name></author>\n  <generator>kk</generator>\n");
+        for entry in &self.entries {
+            out.push_str("  <entry>\n");
+            let _ = writeln!(out, "    <id>{}</id>", escape(&entry.guid));
+            let _ = writeln!(out, "    <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(out, "    <link href=\"{}\"/>", escape(&entry.link));
+            if let Some(image) = &entry.image {
+                let _ = writeln!(
+                    out,
+                    "    <link rel=\"enclosure\" type=\"{}\" href=\"{}\"/>",
+                    image_type(image),
+                    escape(image)
+                );
+            }
+            let _ = writeln!(out, "    <updated>{}</updated>", date(&entry.updated));
+            let _ = writeln!(out, "    <summary>{}</summary>", escape(&entry.summary));
+            out.push_str("  </entry>\n");
+        }
+        out.push_str("</feed>\n");
+        out
+    }
+
+    /// RSS 2.0
+    pub fn to_rss(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rss version=\"2.0\">\n<channel>\n",
+        );
+        let _ = writeln!(out, "  <title>{}</title>", escape(&self.title));
+        let _ = writeln!(out, "  <link>{}</link>", escape(&self.link));
+        let _ = writeln!(out, "  <description>{}</description>", escape(&self.description));
+        let _ = writeln!(out, "  <lastBuildDate>{}</lastBuildDate>", self.updated.to_rfc2822());
+        out.push_str("  <generator>kk</generator>\n");
+        for entry in &self.entries {
+            out.push_str("  <item>\n");
+            let _ = writeln!(out, "    <title>{}</title>", escape(&entry.title));
+            let _ = writeln!(out, "    <link>{}</link>", escape(&entry.link));
+            let _ = writeln!(out, "    <description>{}</description>", escape(&entry.summary));
+            let _ = writeln!(out, "    <guid isPermaLink=\"false\">{}</guid>", escape(&entry.guid));
+            let _ = writeln!(out, "    <pubDate>{}</pubDate>", entry.updated.to_rfc2822());
+            if let Some(image) = &entry.image {
+                // The size isn't known without fetching the image; 0 is the usual placeholder
+                let _ = writeln!(
+                    out,
+                    "    <enclosure url=\"{}\" length=\"0\" type=\"{}\"/>",
+                    escape(image),
+                    image_type(image)
+                );
+            }
+            out.push_str("  </item>\n");
+        }
+        out.push_str("</channel>\n</rss>\n");
+        out
+    }
+}
+
+/// MIME type of an image from its extension, defaulting to JPEG
+fn image_type(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    match path.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => "image/jpeg",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::WEB_BASE;
+    use crate::api::{Badges, Tags};
+    use crate::notify::Alert;
+
+    fn deal() -> Deal {
+        Deal {
+            id: 7,
+            name: "TV <55\">".to_string(),
+            images: vec!["https://img.kuantokusta.pt/7.png?w=200".to_string()],
+            price_min: 499.9,
+            total_offers: 15,
+            url: "/p/7/tv".to_string(),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(45), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        }
+    }
+
+    fn feed() -> Feed {
+        let updated: DateTime<Utc> = "2024-06-01T08:00:00Z".parse().unwrap();
+        Feed {
+            id: "urn:kuantokusta:deals".to_string(),
+            title: "KuantoKusta deals".to_string(),
+            description: "Current deals".to_string(),
+            link: format!("{WEB_BASE}/deals"),
+            updated,
+            entries: vec![FeedEntry::from_deal(&deal(), updated)],
+        }
+    }
+
+    #[test]
+    fn entries_from_deals_and_alerts() {
+        let entry = &feed().entries[0];
+        assert_eq!(entry.guid, "urn:kuantokusta:product:7:49990");
+        assert_eq!(entry.title, "TV <55\"> – 499.90€ (-45%)");
+        assert_eq!(entry.link, "https://www.kuantokusta.pt/p/7/tv");
+        assert_eq!(entry.summary, "499.90€ at 15 store(s), 45% off");
+
+        let logged = LoggedAlert {
+            fired_at: "2024-06-02T09:30:00Z".parse().unwrap(),
+            alert: Alert {
+                product_id: 12345,
+                name: "PS5".to_string(),
+                reason: "Target 450.00€ reached".to_string(),
+                rule: None,
+                old_price: Some(499.99),
+                new_price: 449.99,
+                discount: None,
+                url: "https://www.kuantokusta.pt/p/12345".to_string(),
+                history: vec![],
+                sparkline: None,
+                exec: None,
+            },
+            image: None,
+        };
+        let entry = FeedEntry::from_alert(&logged);
+        assert_eq!(entry.guid, "urn:kuantokusta:product:12345:44999");
+        assert_eq!(entry.title, "PS5: Target 450.00€ reached");
+        assert_eq!(entry.summary, "499.99€ → 449.99€");
+        assert_eq!(entry.updated, logged.fired_at);
+    }
+
+    #[test]
+    fn renders_atom_and_rss() {
+        let atom = feed().render(FeedFormat::Atom);
+        assert!(atom.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns="));
+        assert!(atom.contains("  <updated>2024-06-01T08:00:00Z</updated>\n"));
+        assert!(atom.contains("<title>TV &lt;55&quot;&gt; – 499.90€ (-45%)</title>"));
+        assert!(atom.contains(
+            "<link rel=\"enclosure\" type=\"image/png\" \
+             href=\"https://img.kuantokusta.pt/7.png?w=200\"/>"
+        ));
+        assert!(atom.ends_with("</entry>\n</feed>\n"));
+
+        let rss = feed().render(FeedFormat::Rss);
+        assert!(rss.contains("<lastBuildDate>Sat, 1 Jun 2024 08:00:00 +0000</lastBuildDate>"));
+        assert!(rss.contains("<guid isPermaLink=\"false\">urn:kuantokusta:product:7:49990</guid>"));
+        assert!(rss.contains(
+            "<enclosure url=\"https://img.kuantokusta.pt/7.png?w=200\" \
+                              length=\"0\" type=\"image/png\"/>"
+        ));
+        assert!(rss.ends_with("</item>\n</channel>\n</rss>\n"));
+        assert_eq!(image_type("https://x/a.JPG"), "image/jpeg");
+    }
+}
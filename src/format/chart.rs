@@ -0,0 +1,295 @@
+//! Terminal charts: sparklines and line charts (braille or ASCII)
+
+use std::fmt::Write;
+
+const SPARK_TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Width assumed when the terminal width is unknown
+const DEFAULT_WIDTH: usize = 80;
+
+/// Default chart height in text rows
+pub const DEFAULT_HEIGHT: usize = 12;
+
+/// Line chart rendering style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChartStyle {
+    /// Unicode braille dots (2x4 dots per cell)
+    #[default]
+    Braille,
+    /// Plain ASCII characters
+    Ascii,
+}
+
+/// A named data series drawn on a line chart
+#[derive(Debug, Clone, Copy)]
+pub struct Series<'a> {
+    pub label: &'a str,
+    pub values: &'a [f64],
+}
+
+/// Line chart options
+#[derive(Debug, Clone, Copy)]
+pub struct ChartOptions<'a> {
+    pub style: ChartStyle,
+    /// Total width in columns, including the axis labels
+    pub width: usize,
+    /// Plot height in rows
+    pub height: usize,
+    /// Lower and upper bounds of the y axis
+    pub y_range: (f64, f64),
+    /// Labels printed under the left and right ends of the x axis
+    pub x_labels: (&'a str, &'a str),
+}
+
+/// Current terminal width: `$COLUMNS` if set, else the size of the tty on
+/// stdout (falls back to 80 when not a terminal)
+pub fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.trim().parse().ok())
+        .or_else(|| terminal_size::terminal_size().map(|(w, _)| usize::from(w.0)))
+        .filter(|&w: &usize| w >= 20)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Render values as a one-line Unicode sparkline
+pub fn sparkline(values: &[f64]) -> String {
+    let (lo, hi) = bounds(values);
+    let span = hi - lo;
+
+    values
+        .iter()
+        .map(|&v| {
+            if span <= 0.0 {
+                SPARK_TICKS[SPARK_TICKS.len() / 2]
+            } else {
+                let idx = ((v - lo) / span * (SPARK_TICKS.len() - 1) as f64).round() as usize;
+                SPARK_TICKS[idx.min(SPARK_TICKS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Min/max of the finite values in a slice (`(0, 0)` when empty)
+pub fn bounds(values: &[f64]) -> (f64, f64) {
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let (lo, hi) =
+        finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if lo > hi {
+        (0.0, 0.0)
+    } else {
+        (lo, hi)
+    }
+}
+
+/// Render one or more series as a multi-line chart with a euro y axis
+///
+/// The first series is drawn as a solid line; later series are drawn dotted
+/// (braille) or with distinct characters (ASCII) so they stay distinguishable.
+pub fn line_chart(series: &[Series], opts: &ChartOptions) -> String {
+    let (mut lo, mut hi) = opts.y_range;
+    if !(lo.is_finite() && hi.is_finite()) || lo >= hi {
+        let all: Vec<f64> = series.iter().flat_map(|s| s.values.iter().copied()).collect();
+        (lo, hi) = bounds(&all);
+    }
+    if lo >= hi {
+        lo -= 1.0;
+        hi += 1.0;
+    }
+
+    let label_width = format!("{hi:.2}€").chars().count().max(format!("{lo:.2}€").chars().count());
+    let cols = opts.width.saturating_sub(label_width + 2).max(10);
+    let rows = opts.height.max(2);
+
+    let grid = match opts.style {
+        ChartStyle::Braille => render_braille(series, cols, rows, lo, hi),
+        ChartStyle::Ascii => render_ascii(series, cols, rows, lo, hi),
+    };
+
+    let mut out = String::new();
+    for (row, line) in grid.iter().enumerate() {
+        let label = if row == 0 {
+            format!("{hi:.2}€")
+        } else if row == rows - 1 {
+            format!("{lo:.2}€")
+        } else if row == rows / 2 {
+            format!("{:.2}€", (lo + hi) / 2.0)
+        } else {
+            String::new()
+        };
+        let tick = if label.is_empty() { '│' } else { '┤' };
+        let _ = writeln!(out, "{label:>label_width$} {tick}{line}");
+    }
+
+    let _ = writeln!(out, "{:>label_width$} └{}", "", "─".repeat(cols));
+    let (left, right) = opts.x_labels;
+    let gap = cols.saturating_sub(left.chars().count() + right.chars().count()).max(1);
+    let _ = writeln!(out, "{:>label_width$}  {left}{}{right}", "", " ".repeat(gap));
+
+    let legend: Vec<String> = series
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{} {}", legend_marker(opts.style, i), s.label))
+        .collect();
+    let _ = write!(out, "{:>label_width$}  {}", "", legend.join("   "));
+
+    out
+}
+
+const fn legend_marker(style: ChartStyle, index: usize) -> &'static str {
+    match (style, index) {
+        (ChartStyle::Braille, 0) => "━",
+        (ChartStyle::Braille, _) => "┈",
+        (ChartStyle::Ascii, 0) => "*",
+        (ChartStyle::Ascii, _) => ".",
+    }
+}
+
+/// Sample a series at `x` of `width` positions, interpolating linearly
+fn sample(values: &[f64], x: usize, width: usize) -> Option<f64> {
+    match values.len() {
+        0 => None,
+        1 => Some(values[0]),
+        n => {
+            let t = if width <= 1 { 0.0 } else { x as f64 / (width - 1) as f64 * (n - 1) as f64 };
+            let i = (t.floor() as usize).min(n - 2);
+            let frac = t - i as f64;
+            Some((values[i + 1] - values[i]).mul_add(frac, values[i]))
+        }
+    }
+}
+
+/// Map a value to a row index (0 = top) on a grid of `height` rows
+fn scale(value: f64, lo: f64, hi: f64, height: usize) -> usize {
+    let pos = ((hi - value) / (hi - lo) * (height - 1) as f64).round();
+    (pos.max(0.0) as usize).min(height - 1)
+}
+
+/// Plot a series as `(x, y)` dots, filling vertical gaps between neighbours
+fn plot(values: &[f64], width: usize, height: usize, lo: f64, hi: f64) -> Vec<(usize, usize)> {
+    let mut dots = Vec::new();
+    let mut prev: Option<usize> = None;
+
+    for x in 0..width {
+        let Some(v) = sample(values, x, width) else { break };
+        let y = scale(v, lo, hi, height);
+        let (from, to) = match prev {
+            Some(p) if p < y => (p + 1, y),
+            Some(p) if p > y => (y, p - 1),
+            _ => (y, y),
+        };
+        dots.extend((from..=to).map(|fill| (x, fill)));
+        prev = Some(y);
+    }
+
+    dots
+}
+
+fn render_braille(series: &[Series], cols: usize, rows: usize, lo: f64, hi: f64) -> Vec<String> {
+    const BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    let mut cells = vec![0u8; cols * rows];
+    for (i, s) in series.iter().enumerate() {
+        for (x, y) in plot(s.values, cols * 2, rows * 4, lo, hi) {
+            // Secondary series are dotted
+            if i > 0 && x % 2 == 1 {
+                continue;
+            }
+            cells[(y / 4) * cols + x / 2] |= BITS[x % 2][y % 4];
+        }
+    }
+
+    cells
+        .chunks(cols)
+        .map(|row| {
+            row.iter().map(|&b| char::from_u32(0x2800 + u32::from(b)).unwrap_or(' ')).collect()
+        })
+        .collect()
+}
+
+fn render_ascii(series: &[Series], cols: usize, rows: usize, lo: f64, hi: f64) -> Vec<String> {
+    let mut cells = vec![' '; cols * rows];
+
+    // Draw secondary series first so the primary one wins overlaps
+    for (i, s) in series.iter().enumerate().rev() {
+        let mark = if i == 0 { '*' } else { '.' };
+        for (x, y) in plot(s.values, cols, rows, lo, hi) {
+            cells[y * cols + x] = mark;
+        }
+    }
+
+    cells.chunks(cols).map(|row| row.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(style: ChartStyle) -> ChartOptions<'static> {
+        ChartOptions {
+            style,
+            width: 40,
+            height: 4,
+            y_range: (0.0, 10.0),
+            x_labels: ("2024-01-01", "2024-01-31"),
+        }
+    }
+
+    #[test]
+    fn sparkline_scales_to_ticks() {
+        assert_eq!(sparkline(&[1.0, 5.0, 9.0]), "▁▅█");
+    }
+
+    #[test]
+    fn sparkline_flat_and_empty() {
+        assert_eq!(sparkline(&[3.0, 3.0]), "▅▅");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn bounds_ignores_non_finite() {
+        assert_eq!(bounds(&[2.0, f64::NAN, 5.0]), (2.0, 5.0));
+        assert_eq!(bounds(&[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn line_chart_ascii_layout() {
+        let values = [0.0, 10.0];
+        let series = [Series { label: "min", values: &values }];
+        let chart = line_chart(&series, &options(ChartStyle::Ascii));
+        let lines: Vec<&str> = chart.lines().collect();
+
+        // 4 plot rows, axis, x labels, legend
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("10.00€ ┤"));
+        assert!(lines[0].ends_with('*'));
+        assert!(lines[3].starts_with(" 0.00€ ┤*"));
+        assert!(lines[5].contains("2024-01-01"));
+        assert!(lines[5].ends_with("2024-01-31"));
+        assert!(lines[6].contains("* min"));
+    }
+
+    #[test]
+    fn line_chart_braille_uses_braille_cells() {
+        let min = [2.0, 4.0, 6.0];
+        let avg = [3.0, 5.0, 7.0];
+        let series = [Series { label: "min", values: &min }, Series { label: "avg", values: &avg }];
+        let chart = line_chart(&series, &options(ChartStyle::Braille));
+
+        assert!(chart.chars().any(|c| ('\u{2801}'..='\u{28FF}').contains(&c)));
+        assert!(chart.contains("━ min"));
+        assert!(chart.contains("┈ avg"));
+    }
+
+    #[test]
+    fn line_chart_derives_range_when_axis_missing() {
+        let values = [5.0, 5.0];
+        let series = [Series { label: "min", values: &values }];
+        let mut opts = options(ChartStyle::Ascii);
+        opts.y_range = (0.0, 0.0);
+        let chart = line_chart(&series, &opts);
+
+        assert!(chart.contains("6.00€"));
+        assert!(chart.contains("4.00€"));
+    }
+}
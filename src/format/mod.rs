@@ -1,6 +1,20 @@
 //! Output formatting for CLI
 
+pub mod chart;
+pub mod svg;
+
+use crate::analysis::{Comparison, Forecast, PriceMove, PriceStats, Signal};
 use crate::api::{Category, Deal, PriceHistory, Product};
+use crate::check::{ChangeKind, CheckReport};
+use crate::daemon::DaemonStatus;
+use crate::digest::Digest;
+use crate::rules::ScopedRule;
+use crate::searches::{SavedSearch, SearchDiff};
+use crate::snapshot::{SnapshotDiff, SnapshotInfo};
+use crate::store::query::{QueryResult, View};
+use crate::store::DbStats;
+use crate::watchlist::WatchStatus;
+use chart::{ChartOptions, ChartStyle, Series};
 use serde::Serialize;
 
 /// Output format
@@ -34,7 +48,80 @@ pub fn format_deals(deals: &[Deal], format: OutputFormat) -> String {
 pub fn format_history(history: &PriceHistory, format: OutputFormat) -> String {
     match format {
         OutputFormat::Json => to_json(history),
-        _ => format_history_table(history),
+        OutputFormat::Compact => format_history_compact(history),
+        OutputFormat::Table => format_history_table(history),
+    }
+}
+
+/// Render price history as a line chart of the `min` and `avg` series
+pub fn format_history_chart(history: &PriceHistory, style: ChartStyle, width: usize) -> String {
+    let (Some(first), Some(last)) = (history.data.first(), history.data.last()) else {
+        return "No price history available.".to_string();
+    };
+
+    let min: Vec<f64> = history.data.iter().map(|p| p.min).collect();
+    let avg: Vec<f64> = history.data.iter().map(|p| p.avg).collect();
+    let series = [Series { label: "min", values: &min }, Series { label: "avg", values: &avg }];
+    let (start, end) = (first.date.to_string(), last.date.to_string());
+
+    chart::line_chart(
+        &series,
+        &ChartOptions {
+            style,
+            width,
+            height: chart::DEFAULT_HEIGHT,
+            y_range: (history.min_axis, history.max_axis),
+            x_labels: (&start, &end),
+        },
+    )
+}
+
+/// Format a multi-product price comparison for display
+pub fn format_comparison(comparison: &Comparison, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(comparison),
+        OutputFormat::Compact => format_comparison_compact(comparison),
+        OutputFormat::Table => format_comparison_table(comparison),
+    }
+}
+
+/// Render a multi-product price comparison as CSV (one `min` column per product)
+pub fn comparison_csv(comparison: &Comparison) -> String {
+    let ids: Vec<String> = comparison.product_ids.iter().map(ToString::to_string).collect();
+    let mut out = format!("date,{}\n", ids.join(","));
+
+    let cell = |price: Option<f64>| price.map(|p| format!("{p:.2}")).unwrap_or_default();
+    for row in &comparison.rows {
+        let prices: Vec<String> = row.prices.iter().map(|&p| cell(p)).collect();
+        out.push_str(&format!("{},{}\n", row.date, prices.join(",")));
+    }
+
+    let summary = &comparison.summary;
+    let lowest: Vec<String> = summary.iter().map(|s| cell(s.lowest)).collect();
+    let latest: Vec<String> = summary.iter().map(|s| cell(s.latest)).collect();
+    let cheapest: Vec<String> = summary.iter().map(|s| s.cheapest_days.to_string()).collect();
+    out.push_str(&format!("lowest,{}\n", lowest.join(",")));
+    out.push_str(&format!("latest,{}\n", latest.join(",")));
+    out.push_str(&format!("cheapest_days,{}\n", cheapest.join(",")));
+
+    out
+}
+
+/// Format price statistics for display
+pub fn format_stats(stats: &PriceStats, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(stats),
+        OutputFormat::Compact => format_stats_compact(stats),
+        OutputFormat::Table => format_stats_table(stats),
+    }
+}
+
+/// Format a price forecast for display
+pub fn format_forecast(forecast: &Forecast, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(forecast),
+        OutputFormat::Compact => format_forecast_compact(forecast),
+        OutputFormat::Table => format_forecast_table(forecast),
     }
 }
 
@@ -47,6 +134,192 @@ pub fn format_categories(categories: &[Category], format: OutputFormat) -> Strin
     }
 }
 
+/// Format ad-hoc query results for display
+pub fn format_query(result: &QueryResult, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(&result.records()),
+        OutputFormat::Compact => format_query_compact(result),
+        OutputFormat::Table => format_query_table(result),
+    }
+}
+
+/// Format the documented query views for display
+pub fn format_views(views: &[View], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(views),
+        OutputFormat::Compact => views
+            .iter()
+            .flat_map(|v| v.columns.iter().map(move |(c, _)| format!("{}\t{c}", v.name)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => {
+            let mut out = String::new();
+            for view in views {
+                out.push_str(&format!("{} - {}\n", view.name, view.description));
+                for (column, description) in view.columns {
+                    out.push_str(&format!("  {column:<14} {description}\n"));
+                }
+                out.push('\n');
+            }
+            out
+        }
+    }
+}
+
+/// Format watched products for display
+pub fn format_watchlist(statuses: &[WatchStatus], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(statuses),
+        OutputFormat::Compact => format_watchlist_compact(statuses),
+        OutputFormat::Table => format_watchlist_table(statuses),
+    }
+}
+
+/// Format the changes found by `kk check`
+pub fn format_check(report: &CheckReport, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(report),
+        OutputFormat::Compact => format_check_compact(report),
+        OutputFormat::Table => format_check_table(report),
+    }
+}
+
+/// Format alert rules for display
+pub fn format_rules(rules: &[ScopedRule], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(rules),
+        OutputFormat::Compact => rules
+            .iter()
+            .map(|r| format!("{}\t{}\t{}", r.scope, r.rule.label(), r.rule.when.source()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => format_rules_table(rules),
+    }
+}
+
+/// Format a digest as JSON, or as Markdown unless `html` is set
+pub fn format_digest(digest: &Digest, format: OutputFormat, html: bool) -> String {
+    match format {
+        OutputFormat::Json => to_json(digest),
+        _ if html => digest.to_html(),
+        OutputFormat::Compact | OutputFormat::Table => digest.to_markdown(),
+    }
+}
+
+/// Format saved searches for display
+pub fn format_saved_searches(searches: &[SavedSearch], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(searches),
+        OutputFormat::Compact => searches
+            .iter()
+            .map(|s| format!("{}\t{}\t{}\t{}", s.name, s.query, s.max, s.filters()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => format_saved_searches_table(searches),
+    }
+}
+
+/// Format what changed in saved searches since their previous runs
+pub fn format_search_diffs(diffs: &[SearchDiff], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(diffs),
+        OutputFormat::Compact => format_search_diffs_compact(diffs),
+        OutputFormat::Table => format_search_diffs_table(diffs),
+    }
+}
+
+/// Format the differences between two snapshots
+pub fn format_snapshot_diff(diff: &SnapshotDiff, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(diff),
+        OutputFormat::Compact => format_snapshot_diff_compact(diff),
+        OutputFormat::Table => format_snapshot_diff_table(diff),
+    }
+}
+
+/// Format the status of `kk daemon`
+pub fn format_daemon_status(status: &DaemonStatus, format: OutputFormat) -> String {
+    let time = |t: Option<chrono::DateTime<chrono::Utc>>| {
+        t.map_or_else(
+            || "-".to_string(),
+            |t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string(),
+        )
+    };
+    match format {
+        OutputFormat::Json => to_json(status),
+        OutputFormat::Compact => status
+            .jobs
+            .iter()
+            .map(|j| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    j.name,
+                    if j.last_ok { "ok" } else { "failed" },
+                    time(j.last_run),
+                    time(j.next_run),
+                    j.last_result.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => {
+            let state = if status.is_alive() {
+                format!("running (pid {})", status.pid)
+            } else if status.running {
+                format!("not running (pid {} exited without stopping)", status.pid)
+            } else {
+                "stopped".to_string()
+            };
+            let mut out = String::new();
+            out.push_str(&format!("{:<10} {state}\n", "Daemon"));
+            out.push_str(&format!("{:<10} {}\n", "Started", time(Some(status.started_at))));
+            out.push_str(&format!("{:<10} {}\n", "Updated", time(Some(status.updated_at))));
+            out.push_str(&format!("{:<10} {}\n\n", "Config", status.config));
+
+            out.push_str(&format!(
+                "{:<8} {:<16} {:<16} {:<16} {:>6} {:>6} {}\n",
+                "Job", "Schedule", "Last run", "Next run", "Runs", "Alerts", "Result"
+            ));
+            out.push_str(&format!(
+                "{:-<8} {:-<16} {:-<16} {:-<16} {:->6} {:->6} {:-<30}\n",
+                "", "", "", "", "", "", ""
+            ));
+            for j in &status.jobs {
+                let result = j.last_result.as_deref().unwrap_or("-");
+                let mark = if j.last_ok { "" } else { "✗ " };
+                out.push_str(&format!(
+                    "{:<8} {:<16} {:<16} {:<16} {:>6} {:>6} {mark}{result}\n",
+                    j.name,
+                    truncate(&j.schedule, 16),
+                    time(j.last_run),
+                    time(j.next_run),
+                    j.runs,
+                    j.alerts
+                ));
+            }
+            out
+        }
+    }
+}
+
+/// Format local database statistics for display
+pub fn format_db_stats(stats: &DbStats, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => to_json(stats),
+        OutputFormat::Compact => stats
+            .tables
+            .iter()
+            .map(|t| {
+                let date =
+                    |d: Option<chrono::NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+                format!("{}\t{}\t{}\t{}", t.name, t.rows, date(t.first_date), date(t.last_date))
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Table => format_db_stats_table(stats),
+    }
+}
+
 fn to_json<T: Serialize + ?Sized>(data: &T) -> String {
     serde_json::to_string_pretty(data).unwrap_or_else(|_| "{}".to_string())
 }
@@ -106,12 +379,7 @@ fn format_deals_table(deals: &[Deal]) -> String {
 
     for d in deals {
         let name = truncate(&d.name, 43);
-        let discount = d
-            .badges
-            .discount_percentage
-            .or(d.tags.discount_percentage)
-            .map(|d| format!("-{d}%"))
-            .unwrap_or_default();
+        let discount = d.discount().map(|d| format!("-{d}%")).unwrap_or_default();
 
         out.push_str(&format!(
             "{:<10} {:<45} {:>9.2}€ {:>5} {:>6}\n",
@@ -126,7 +394,7 @@ fn format_deals_compact(deals: &[Deal]) -> String {
     deals
         .iter()
         .map(|d| {
-            let discount = d.badges.discount_percentage.or(d.tags.discount_percentage).unwrap_or(0);
+            let discount = d.discount().unwrap_or(0);
             format!("{}\t{:.2}€\t-{}%\t{}", d.id, d.price_min, discount, d.name)
         })
         .collect::<Vec<_>>()
@@ -144,12 +412,231 @@ fn format_history_table(history: &PriceHistory) -> String {
     out.push_str(&format!("{:-<12} {:->10} {:->10}\n", "", "", ""));
 
     for point in &history.data {
-        out.push_str(&format!("{:<12} {:>9.2}€ {:>9.2}€\n", point.date, point.min, point.avg));
+        out.push_str(&format!(
+            "{:<12} {:>9.2}€ {:>9.2}€\n",
+            point.date.to_string(),
+            point.min,
+            point.avg
+        ));
     }
 
     out
 }
 
+fn format_comparison_table(comparison: &Comparison) -> String {
+    if comparison.rows.is_empty() {
+        return "No price history available.".to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!("{:<12}", "Date"));
+    for id in &comparison.product_ids {
+        out.push_str(&format!(" {:>12}", format!("#{id}")));
+    }
+    out.push_str(&format!(
+        "\n{:-<12}{}\n",
+        "",
+        format!(" {:->12}", "").repeat(comparison.product_ids.len())
+    ));
+
+    let euros = |price: Option<f64>| price.map_or_else(|| "-".to_string(), |p| format!("{p:.2}€"));
+    for row in &comparison.rows {
+        let cheapest = row.cheapest();
+        out.push_str(&format!("{:<12}", row.date.to_string()));
+        for (col, &price) in row.prices.iter().enumerate() {
+            let mark = if cheapest.contains(&col) && cheapest.len() < row.prices.len() {
+                "*"
+            } else {
+                " "
+            };
+            out.push_str(&format!(" {:>11}{mark}", euros(price)));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&format!(
+        "{:-<12}{}\n",
+        "",
+        format!(" {:->12}", "").repeat(comparison.product_ids.len())
+    ));
+    out.push_str(&format!("{:<12}", "Lowest"));
+    for s in &comparison.summary {
+        out.push_str(&format!(" {:>11} ", euros(s.lowest)));
+    }
+    out.push_str(&format!("\n{:<12}", "Latest"));
+    for s in &comparison.summary {
+        out.push_str(&format!(" {:>11} ", euros(s.latest)));
+    }
+    out.push_str(&format!("\n{:<12}", "Cheapest on"));
+    for s in &comparison.summary {
+        out.push_str(&format!(" {:>11} ", format!("{} days", s.cheapest_days)));
+    }
+    out.push_str("\n\n* cheapest that day\n");
+
+    out
+}
+
+fn format_comparison_compact(comparison: &Comparison) -> String {
+    let euros = |price: Option<f64>| price.map_or_else(|| "-".to_string(), |p| format!("{p:.2}€"));
+    let line = |label: String, cells: Vec<String>| format!("{label}\t{}", cells.join("\t"));
+
+    let mut lines: Vec<String> = comparison
+        .rows
+        .iter()
+        .map(|row| line(row.date.to_string(), row.prices.iter().map(|&p| euros(p)).collect()))
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    let summary = &comparison.summary;
+    lines.push(line("lowest".to_string(), summary.iter().map(|s| euros(s.lowest)).collect()));
+    lines.push(line("latest".to_string(), summary.iter().map(|s| euros(s.latest)).collect()));
+    lines.push(line(
+        "cheapest_days".to_string(),
+        summary.iter().map(|s| s.cheapest_days.to_string()).collect(),
+    ));
+    lines.join("\n")
+}
+
+fn format_stats_table(stats: &PriceStats) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "Window: {} - {} ({} points)\n\n",
+        stats.first_date, stats.last_date, stats.points
+    ));
+    out.push_str(&format!(
+        "{:<16} {:.2}€ (percentile {:.0}%)\n",
+        "Current", stats.current, stats.percentile
+    ));
+    out.push_str(&format!("{:<16} {:.2}€ / {:.2}€\n", "Min / Max", stats.min, stats.max));
+    out.push_str(&format!("{:<16} {:.2}€ / {:.2}€\n", "Mean / Median", stats.mean, stats.median));
+    out.push_str(&format!("{:<16} {:.2}% per day\n", "Volatility", stats.volatility));
+    out.push_str(&format!("{:<16} {}\n", "Largest drop", format_move(stats.largest_drop.as_ref())));
+    out.push_str(&format!(
+        "{:<16} {}\n",
+        "Largest spike",
+        format_move(stats.largest_spike.as_ref())
+    ));
+    out.push_str(&format!("{:<16} {}\n", "Days since low", stats.days_since_low));
+    out.push_str(&format!("{:<16} {:+.2}€/day\n", "Trend", stats.trend_slope));
+
+    let signal = match stats.signal {
+        Signal::Buy => "BUY",
+        Signal::Wait => "WAIT",
+    };
+    out.push_str(&format!(
+        "\n{:<16} {signal} (buy below percentile {:.0}%)\n",
+        "Signal", stats.buy_percentile
+    ));
+
+    out
+}
+
+fn format_move(price_move: Option<&PriceMove>) -> String {
+    price_move.map_or_else(
+        || "-".to_string(),
+        |m| format!("{:+.2}€ ({:+.1}%) {} → {}", m.change, m.change_pct, m.from_date, m.to_date),
+    )
+}
+
+fn format_stats_compact(stats: &PriceStats) -> String {
+    let signal = match stats.signal {
+        Signal::Buy => "buy",
+        Signal::Wait => "wait",
+    };
+    format!(
+        "{}\t{:.2}€\t{:.2}€\t{:.2}€\t{:.2}€\t{:.0}%\t{}",
+        stats.product_id,
+        stats.current,
+        stats.min,
+        stats.max,
+        stats.median,
+        stats.percentile,
+        signal
+    )
+}
+
+fn format_history_compact(history: &PriceHistory) -> String {
+    let (Some(first), Some(last)) = (history.data.first(), history.data.last()) else {
+        return String::new();
+    };
+
+    let min: Vec<f64> = history.data.iter().map(|p| p.min).collect();
+    let (lo, hi) = chart::bounds(&min);
+    format!(
+        "{}\t{}\t{:.2}€\t{:.2}€\t{:.2}€\t{}",
+        first.date,
+        last.date,
+        lo,
+        hi,
+        last.min,
+        chart::sparkline(&min)
+    )
+}
+
+fn format_forecast_table(forecast: &Forecast) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("Current price: {:.2}€ ({})\n\n", forecast.current, forecast.last_date));
+    out.push_str(&format!(
+        "{:<16} {:>10} {:>23} {:>9} {:>7}\n",
+        "Model", "Day +N", "95% band", "MAE", "MAPE"
+    ));
+    out.push_str(&format!("{:-<16} {:->10} {:->23} {:->9} {:->7}\n", "", "", "", "", ""));
+
+    for m in &forecast.models {
+        let Some(end) = m.points.last() else { continue };
+        let band = format!("{:.2}€ - {:.2}€", end.lower, end.upper);
+        let abs_err = m.mae.map_or_else(|| "-".to_string(), |e| format!("{e:.2}€"));
+        let pct_err = m.mape.map_or_else(|| "-".to_string(), |e| format!("{e:.1}%"));
+        let best = if m.model == forecast.best { " ◀" } else { "" };
+        out.push_str(&format!(
+            "{:<16} {:>9.2}€ {:>23} {:>9} {:>7}{best}\n",
+            m.model.name(),
+            end.value,
+            band,
+            abs_err,
+            pct_err
+        ));
+    }
+
+    let verdict = match forecast.signal {
+        Signal::Wait => format!(
+            "WAIT - expected to drop to {:.2}€ (save {:.2}€)",
+            forecast.expected_low, forecast.expected_saving
+        ),
+        Signal::Buy => "BUY - no meaningful drop expected".to_string(),
+    };
+    out.push_str(&format!(
+        "\nBest model: {} (lowest backtest error)\n{:<16} {verdict}\n",
+        forecast.best.name(),
+        format!("Next {} days:", forecast.horizon)
+    ));
+
+    out
+}
+
+fn format_forecast_compact(forecast: &Forecast) -> String {
+    let signal = match forecast.signal {
+        Signal::Buy => "buy",
+        Signal::Wait => "wait",
+    };
+    let (lower, upper) = forecast
+        .best_forecast()
+        .and_then(|m| m.points.last())
+        .map_or((forecast.current, forecast.current), |p| (p.lower, p.upper));
+    format!(
+        "{}\t{:.2}€\t{:.2}€\t{:.2}€-{:.2}€\t{}\t{}",
+        forecast.product_id,
+        forecast.current,
+        forecast.expected_low,
+        lower,
+        upper,
+        forecast.best.name(),
+        signal
+    )
+}
+
 fn format_categories_table(categories: &[Category]) -> String {
     if categories.is_empty() {
         return "No categories found.".to_string();
@@ -187,17 +674,388 @@ fn format_categories_compact(categories: &[Category]) -> String {
         .join("\n")
 }
 
+fn format_watchlist_table(statuses: &[WatchStatus]) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{:<10} {:<40} {:>10} {:>10} {:>8} {}\n",
+        "ID", "Label", "Price", "Target", "Drop", "Tags"
+    ));
+    out.push_str(&format!(
+        "{:-<10} {:-<40} {:->10} {:->10} {:->8} {:-<12}\n",
+        "", "", "", "", "", ""
+    ));
+
+    let euros = |price: Option<f64>| price.map_or_else(|| "-".to_string(), |p| format!("{p:.2}€"));
+    for s in statuses {
+        let mark = if s.on_target() { "*" } else { " " };
+        out.push_str(&format!(
+            "{:<10} {:<40} {:>10}{mark}{:>10} {:>8} {}\n",
+            s.entry.id,
+            truncate(&s.entry.label, 38),
+            euros(s.price_min),
+            euros(s.entry.target_price),
+            s.entry.max_drop.map_or_else(|| "-".to_string(), |d| format!("{d}%")),
+            s.entry.tags.join(", ")
+        ));
+    }
+
+    if statuses.iter().any(WatchStatus::on_target) {
+        out.push_str("\n* at or below target\n");
+    }
+
+    out
+}
+
+fn format_watchlist_compact(statuses: &[WatchStatus]) -> String {
+    let price = |p: Option<f64>| p.map_or_else(|| "-".to_string(), |p| format!("{p:.2}"));
+    statuses
+        .iter()
+        .map(|s| {
+            format!(
+                "{}\t{}\t{}\t{}",
+                s.entry.id,
+                price(s.price_min),
+                price(s.entry.target_price),
+                s.entry.label
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_check_table(report: &CheckReport) -> String {
+    let mut out = String::new();
+    if report.changes.is_empty() {
+        out.push_str(&format!("No changes ({} product(s) checked).\n", report.checked));
+    } else {
+        out.push_str(&format!("{:<10} {:<40} {:<15} {}\n", "ID", "Label", "Change", "Details"));
+        out.push_str(&format!("{:-<10} {:-<40} {:-<15} {:-<28}\n", "", "", "", ""));
+        for c in &report.changes {
+            let (kind, details) = match &c.kind {
+                ChangeKind::PriceDrop { from, to, change_pct }
+                | ChangeKind::PriceIncrease { from, to, change_pct } => {
+                    let kind = if to < from { "Price drop" } else { "Price increase" };
+                    (kind, format!("{from:.2}€ → {to:.2}€ ({change_pct:+.1}%)"))
+                }
+                ChangeKind::TargetReached { target, price } => {
+                    ("Target reached", format!("{price:.2}€ ≤ {target:.2}€"))
+                }
+                ChangeKind::OffersChanged { from, to } => ("Stores", format!("{from} → {to}")),
+                ChangeKind::RuleMatched { rule, price } => {
+                    ("Rule", format!("{rule} ({price:.2}€)"))
+                }
+            };
+            let mark = if c.alert { "!" } else { " " };
+            out.push_str(&format!(
+                "{:<10} {:<40} {mark}{:<14} {details}\n",
+                c.product_id,
+                truncate(&c.label, 38),
+                kind
+            ));
+        }
+    }
+
+    if !report.failed.is_empty() {
+        let ids: Vec<String> = report.failed.iter().map(u64::to_string).collect();
+        out.push_str(&format!("\nCouldn't fetch: {}\n", ids.join(", ")));
+    }
+    if report.alerts() > 0 {
+        out.push_str(&format!("\n! {} alert(s)\n", report.alerts()));
+    }
+
+    out
+}
+
+fn format_saved_searches_table(searches: &[SavedSearch]) -> String {
+    if searches.is_empty() {
+        return "No saved searches. Add one with `kk saved add <name> <query>`.".to_string();
+    }
+    let mut out = format!("{:<16} {:<30} {:>4}  {}\n", "Name", "Query", "Max", "Filters");
+    out.push_str(&format!("{:-<16} {:-<30} {:->4}  {:-<24}\n", "", "", "", ""));
+    for s in searches {
+        out.push_str(&format!(
+            "{:<16} {:<30} {:>4}  {}\n",
+            truncate(&s.name, 16),
+            truncate(&s.query, 30),
+            s.max,
+            s.filters()
+        ));
+    }
+    out
+}
+
+fn format_search_diffs_table(diffs: &[SearchDiff]) -> String {
+    let mut out = String::new();
+    for diff in diffs {
+        out.push_str(&format!("{}: {}", diff.search, diff.summary()));
+        if let Some(previous) = diff.previous {
+            out.push_str(&format!(" since {previous}"));
+        }
+        out.push('\n');
+        for hit in &diff.new {
+            let price = format!("{:.2}€", hit.price_min);
+            out.push_str(&format!(
+                "  + {:<10} {:<40} {price}\n",
+                hit.product_id,
+                truncate(&hit.name, 40)
+            ));
+        }
+        for hit in &diff.removed {
+            let price = format!("{:.2}€", hit.price_min);
+            out.push_str(&format!(
+                "  - {:<10} {:<40} {price}\n",
+                hit.product_id,
+                truncate(&hit.name, 40)
+            ));
+        }
+        for c in &diff.changed {
+            out.push_str(&format!(
+                "  ~ {:<10} {:<40} {:.2}€ → {:.2}€ ({:+.1}%)\n",
+                c.hit.product_id,
+                truncate(&c.hit.name, 40),
+                c.old_price,
+                c.hit.price_min,
+                c.change_pct
+            ));
+        }
+    }
+    out
+}
+
+fn format_search_diffs_compact(diffs: &[SearchDiff]) -> String {
+    let mut lines = Vec::new();
+    for diff in diffs {
+        let line = |kind, hit: &crate::searches::SearchHit, from: String, to: String| {
+            format!("{}\t{kind}\t{}\t{from}\t{to}\t{}", diff.search, hit.product_id, hit.name)
+        };
+        lines.extend(
+            diff.new.iter().map(|h| line("new", h, String::new(), format!("{:.2}", h.price_min))),
+        );
+        lines.extend(
+            diff.removed
+                .iter()
+                .map(|h| line("removed", h, format!("{:.2}", h.price_min), String::new())),
+        );
+        lines.extend(diff.changed.iter().map(|c| {
+            line("price", &c.hit, format!("{:.2}", c.old_price), format!("{:.2}", c.hit.price_min))
+        }));
+    }
+    lines.join("\n")
+}
+
+fn format_snapshot_diff_table(diff: &SnapshotDiff) -> String {
+    let side = |info: &SnapshotInfo| {
+        format!(
+            "{} ({}, {} items)",
+            info.source,
+            info.taken_at.format("%Y-%m-%d %H:%M UTC"),
+            info.items
+        )
+    };
+    let mut out = format!("{} → {}\n{}\n", side(&diff.from), side(&diff.to), diff.summary());
+    if !diff.added.is_empty() || !diff.removed.is_empty() || !diff.repriced.is_empty() {
+        out.push('\n');
+    }
+    for item in &diff.added {
+        let price = format!("{:.2}€", item.price_min);
+        out.push_str(&format!("  + {:<10} {:<40} {price}\n", item.id, truncate(&item.name, 40)));
+    }
+    for item in &diff.removed {
+        let price = format!("{:.2}€", item.price_min);
+        out.push_str(&format!("  - {:<10} {:<40} {price}\n", item.id, truncate(&item.name, 40)));
+    }
+    for r in &diff.repriced {
+        out.push_str(&format!(
+            "  ~ {:<10} {:<40} {:.2}€ → {:.2}€ ({:+.2}€, {:+.1}%)\n",
+            r.item.id,
+            truncate(&r.item.name, 40),
+            r.old_price,
+            r.item.price_min,
+            r.delta,
+            r.change_pct
+        ));
+    }
+    out
+}
+
+fn format_snapshot_diff_compact(diff: &SnapshotDiff) -> String {
+    let added =
+        diff.added.iter().map(|i| format!("added\t{}\t\t{:.2}\t{}", i.id, i.price_min, i.name));
+    let removed =
+        diff.removed.iter().map(|i| format!("removed\t{}\t{:.2}\t\t{}", i.id, i.price_min, i.name));
+    let repriced = diff.repriced.iter().map(|r| {
+        format!(
+            "repriced\t{}\t{:.2}\t{:.2}\t{}",
+            r.item.id, r.old_price, r.item.price_min, r.item.name
+        )
+    });
+    added.chain(removed).chain(repriced).collect::<Vec<_>>().join("\n")
+}
+
+fn format_check_compact(report: &CheckReport) -> String {
+    report
+        .changes
+        .iter()
+        .map(|c| {
+            let (kind, from, to, pct) = match &c.kind {
+                ChangeKind::PriceDrop { from, to, change_pct } => {
+                    ("drop", format!("{from:.2}"), format!("{to:.2}"), format!("{change_pct:.1}%"))
+                }
+                ChangeKind::PriceIncrease { from, to, change_pct } => (
+                    "increase",
+                    format!("{from:.2}"),
+                    format!("{to:.2}"),
+                    format!("{change_pct:+.1}%"),
+                ),
+                ChangeKind::TargetReached { target, price } => {
+                    ("target", format!("{target:.2}"), format!("{price:.2}"), String::new())
+                }
+                ChangeKind::OffersChanged { from, to } => {
+                    ("offers", from.to_string(), to.to_string(), String::new())
+                }
+                ChangeKind::RuleMatched { rule, price } => {
+                    ("rule", rule.clone(), format!("{price:.2}"), String::new())
+                }
+            };
+            let alert = if c.alert { "!" } else { "" };
+            format!("{}\t{kind}\t{from}\t{to}\t{pct}\t{alert}\t{}", c.product_id, c.label)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_rules_table(rules: &[ScopedRule]) -> String {
+    if rules.is_empty() {
+        return "No alert rules. Add them under [alerts] in the config or with `kk watch edit --rule`."
+            .to_string();
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{:<10} {:<20} {:<40} {:>8} {:<11}\n",
+        "Scope", "Name", "Condition", "Cooldown", "Quiet"
+    ));
+    out.push_str(&format!("{:-<10} {:-<20} {:-<40} {:->8} {:-<11}\n", "", "", "", "", ""));
+    for r in rules {
+        out.push_str(&format!(
+            "{:<10} {:<20} {:<40} {:>8} {:<11}\n",
+            r.scope,
+            truncate(r.rule.name.as_deref().unwrap_or("-"), 18),
+            truncate(r.rule.when.source(), 38),
+            r.rule.cooldown.map_or_else(|| "-".to_string(), |c| c.to_string()),
+            r.rule.quiet_hours.map_or_else(|| "-".to_string(), String::from)
+        ));
+    }
+
+    out
+}
+
+fn format_db_stats_table(stats: &DbStats) -> String {
+    let mut out = String::new();
+    if let Some(path) = &stats.path {
+        out.push_str(&format!("{:<16} {path}\n", "Database"));
+    }
+    out.push_str(&format!("{:<16} {}\n", "Schema version", stats.schema_version));
+    out.push_str(&format!("{:<16} {}\n", "Size", format_bytes(stats.size_bytes)));
+    out.push_str(&format!("{:<16} {}\n\n", "Products", stats.products));
+
+    out.push_str(&format!("{:<20} {:>10} {:<12} {:<12}\n", "Table", "Rows", "From", "To"));
+    out.push_str(&format!("{:-<20} {:->10} {:-<12} {:-<12}\n", "", "", "", ""));
+    let date = |d: Option<chrono::NaiveDate>| d.map_or_else(|| "-".to_string(), |d| d.to_string());
+    for t in &stats.tables {
+        out.push_str(&format!(
+            "{:<20} {:>10} {:<12} {:<12}\n",
+            t.name,
+            t.rows,
+            date(t.first_date),
+            date(t.last_date)
+        ));
+    }
+
+    out
+}
+
+/// Human-readable byte size
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn format_query_table(result: &QueryResult) -> String {
+    if result.rows.is_empty() {
+        return "No rows.".to_string();
+    }
+
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|v| truncate(&query_cell(v), 40)).collect())
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            cells.iter().map(|row| row[i].chars().count()).fold(name.chars().count(), usize::max)
+        })
+        .collect();
+
+    let line = |values: Vec<&str>| {
+        let padded: Vec<String> =
+            values.iter().zip(&widths).map(|(v, &w)| format!("{v:<w$}")).collect();
+        format!("{}\n", padded.join(" ").trim_end())
+    };
+
+    let mut out = line(result.columns.iter().map(String::as_str).collect());
+    let rules: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+    out.push_str(&line(rules.iter().map(String::as_str).collect()));
+    for row in &cells {
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out.push_str(&format!("\n{} row(s)\n", result.rows.len()));
+
+    out
+}
+
+fn format_query_compact(result: &QueryResult) -> String {
+    result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(query_cell).collect::<Vec<_>>().join("\t"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn query_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
 fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
+    if s.chars().count() <= max {
         s.to_string()
     } else {
-        format!("{}…", &s[..max - 1])
+        format!("{}…", s.chars().take(max - 1).collect::<String>())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::analysis::stats::DEFAULT_BUY_PERCENTILE;
     use crate::api::{Badges, PricePoint, Rating, Tags};
 
     fn sample_product() -> Product {
@@ -258,8 +1116,8 @@ mod tests {
             min_axis: 500.0,
             max_axis: 800.0,
             data: vec![
-                PricePoint { date: "2024-01-01".to_string(), avg: 650.0, min: 600.0 },
-                PricePoint { date: "2024-01-02".to_string(), avg: 640.0, min: 590.0 },
+                PricePoint { date: "2024-01-01".parse().unwrap(), avg: 650.0, min: 600.0 },
+                PricePoint { date: "2024-01-02".parse().unwrap(), avg: 640.0, min: 590.0 },
             ],
         }
     }
@@ -282,6 +1140,39 @@ mod tests {
         assert_eq!(result.chars().count(), 8);
     }
 
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn truncate_multibyte_string() {
+        assert_eq!(truncate("Câmara fotográfica", 8), "Câmara …");
+    }
+
+    #[test]
+    fn format_query_table_aligns_columns() {
+        let result = QueryResult {
+            columns: vec!["product_id".to_string(), "name".to_string()],
+            rows: vec![
+                vec![serde_json::json!(1), serde_json::json!("Phone")],
+                vec![serde_json::json!(22), serde_json::Value::Null],
+            ],
+        };
+        let output = format_query(&result, OutputFormat::Table);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "product_id name");
+        assert_eq!(lines[1], "---------- -----");
+        assert_eq!(lines[2], "1          Phone");
+        assert_eq!(lines[3], "22");
+        assert!(output.contains("2 row(s)"));
+
+        assert_eq!(format_query(&result, OutputFormat::Compact), "1\tPhone\n22\t");
+        assert!(format_query(&result, OutputFormat::Json).contains("\"product_id\": 22"));
+    }
+
     #[test]
     fn format_products_table_output() {
         let products = vec![sample_product()];
@@ -401,6 +1292,84 @@ mod tests {
         assert!(output.contains("\"data\""));
     }
 
+    #[test]
+    fn format_history_compact_output() {
+        let history = sample_history();
+        let output = format_history(&history, OutputFormat::Compact);
+
+        assert_eq!(output, "2024-01-01\t2024-01-02\t590.00€\t600.00€\t590.00€\t█▁");
+    }
+
+    #[test]
+    fn format_history_chart_output() {
+        let history = sample_history();
+        let output = format_history_chart(&history, ChartStyle::Ascii, 60);
+
+        assert!(output.contains("800.00€ ┤"));
+        assert!(output.contains("500.00€ ┤"));
+        assert!(output.contains("2024-01-01"));
+        assert!(output.contains("* min"));
+        assert!(output.contains(". avg"));
+    }
+
+    fn sample_comparison() -> Comparison {
+        let mut other = sample_history();
+        other.data[0].min = 580.0;
+        other.data.pop();
+        Comparison::new(&[(1, sample_history()), (2, other)])
+    }
+
+    #[test]
+    fn format_comparison_table_output() {
+        let output = format_comparison(&sample_comparison(), OutputFormat::Table);
+
+        assert!(output.contains("#1"));
+        assert!(output.contains("#2"));
+        assert!(output.contains("580.00€*"));
+        assert!(output.contains("Cheapest on"));
+        assert!(output.contains("1 days"));
+    }
+
+    #[test]
+    fn format_comparison_compact_output() {
+        let output = format_comparison(&sample_comparison(), OutputFormat::Compact);
+        assert_eq!(
+            output,
+            "2024-01-01\t600.00€\t580.00€\n2024-01-02\t590.00€\t-\n\
+             lowest\t590.00€\t580.00€\nlatest\t590.00€\t580.00€\ncheapest_days\t1\t1"
+        );
+    }
+
+    #[test]
+    fn comparison_csv_output() {
+        let csv = comparison_csv(&sample_comparison());
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "date,1,2");
+        assert_eq!(lines[1], "2024-01-01,600.00,580.00");
+        assert_eq!(lines[2], "2024-01-02,590.00,");
+        assert_eq!(lines[3], "lowest,590.00,580.00");
+        assert_eq!(lines[5], "cheapest_days,1,1");
+    }
+
+    #[test]
+    fn format_forecast_table_output() {
+        let mut history = sample_history();
+        history.data.push(PricePoint {
+            date: "2024-01-03".parse().unwrap(),
+            avg: 630.0,
+            min: 580.0,
+        });
+        let forecast = Forecast::compute(1, &history, 7).unwrap();
+        let output = format_forecast(&forecast, OutputFormat::Table);
+
+        assert!(output.contains("Current price: 580.00€ (2024-01-03)"));
+        assert!(output.contains("Moving average"));
+        assert!(output.contains("Linear trend"));
+        assert!(output.contains('◀'));
+        assert!(output.contains("Next 7 days:"));
+    }
+
     #[test]
     fn format_history_empty() {
         let history = PriceHistory { min_axis: 0.0, max_axis: 0.0, data: vec![] };
@@ -408,6 +1377,25 @@ mod tests {
         assert_eq!(output, "No price history available.");
     }
 
+    #[test]
+    fn format_stats_table_output() {
+        let stats = PriceStats::compute(1, &sample_history(), DEFAULT_BUY_PERCENTILE).unwrap();
+        let output = format_stats(&stats, OutputFormat::Table);
+
+        assert!(output.contains("Window: 2024-01-01 - 2024-01-02"));
+        assert!(output.contains("590.00€ / 600.00€"));
+        assert!(output.contains("-10.00€ (-1.7%)"));
+        assert!(output.contains("BUY"));
+    }
+
+    #[test]
+    fn format_stats_compact_output() {
+        let stats = PriceStats::compute(1, &sample_history(), DEFAULT_BUY_PERCENTILE).unwrap();
+        let output = format_stats(&stats, OutputFormat::Compact);
+
+        assert_eq!(output, "1\t590.00€\t590.00€\t600.00€\t595.00€\t0%\tbuy");
+    }
+
     #[test]
     fn format_categories_over_50_shows_truncation() {
         let categories: Vec<Category> = (0..60)
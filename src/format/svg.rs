@@ -0,0 +1,324 @@
+//! Standalone SVG line charts for price history
+
+use crate::api::{PriceHistory, PricePoint};
+use crate::html::escape;
+use chrono::{Days, NaiveDate};
+use std::fmt::Write;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 420.0;
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 24.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 80.0;
+const Y_TICKS: usize = 5;
+const X_TICKS: usize = 6;
+
+const PALETTE: [&str; 6] = ["#2563eb", "#dc2626", "#16a34a", "#9333ea", "#ea580c", "#0891b2"];
+
+/// A labelled price history drawn on an SVG chart
+#[derive(Debug, Clone, Copy)]
+pub struct SvgSeries<'a> {
+    pub label: &'a str,
+    pub history: &'a PriceHistory,
+}
+
+/// Render one or more price histories as a standalone SVG document
+///
+/// Each history gets its own colour: `min` is drawn solid, `avg` dashed, and
+/// the lowest `min` price is highlighted with a shaded marker.
+pub fn history_svg(title: &str, series: &[SvgSeries]) -> String {
+    let dates = series.iter().flat_map(|s| s.history.data.iter().map(|p| p.date));
+    let start = dates.clone().min().unwrap_or_default();
+    let end = dates.max().unwrap_or_default();
+
+    let plot = Plot { start, end, y_range: y_range(series) };
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="12">"#
+    );
+    let _ = writeln!(out, r##"<rect width="100%" height="100%" fill="#ffffff"/>"##);
+    let _ = writeln!(
+        out,
+        r##"<text x="{MARGIN_LEFT}" y="24" font-size="16" font-weight="bold" fill="#111827">{}</text>"##,
+        escape(title)
+    );
+
+    plot.write_axes(&mut out);
+    for (i, s) in series.iter().enumerate() {
+        plot.write_series(&mut out, s, PALETTE[i % PALETTE.len()]);
+    }
+    write_legend(&mut out, series);
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// A small inline SVG line of `values`, for tables; the last value gets a dot
+pub fn sparkline_svg(values: &[f64], width: f64, height: f64) -> String {
+    let (lo, hi) = super::chart::bounds(values);
+    // Flat lines sit in the middle
+    let y = |v: f64| {
+        if hi > lo {
+            ((hi - v) / (hi - lo)).mul_add(height - 4.0, 2.0)
+        } else {
+            height / 2.0
+        }
+    };
+    let step = if values.len() > 1 { (width - 4.0) / (values.len() - 1) as f64 } else { 0.0 };
+    let points: Vec<(f64, f64)> =
+        values.iter().enumerate().map(|(i, &v)| (step.mul_add(i as f64, 2.0), y(v))).collect();
+
+    let mut out = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    );
+    let line: Vec<_> = points.iter().map(|(x, y)| format!("{x:.1},{y:.1}")).collect();
+    let _ = write!(
+        out,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+        line.join(" "),
+        PALETTE[0]
+    );
+    if let Some((x, y)) = points.last() {
+        let _ = write!(out, r#"<circle cx="{x:.1}" cy="{y:.1}" r="2" fill="{}"/>"#, PALETTE[0]);
+    }
+    out.push_str("</svg>");
+    out
+}
+
+/// Coordinate mapping for the plot area
+struct Plot {
+    start: NaiveDate,
+    end: NaiveDate,
+    y_range: (f64, f64),
+}
+
+impl Plot {
+    const fn width() -> f64 {
+        WIDTH - MARGIN_LEFT - MARGIN_RIGHT
+    }
+
+    const fn height() -> f64 {
+        HEIGHT - MARGIN_TOP - MARGIN_BOTTOM
+    }
+
+    fn x(&self, date: NaiveDate) -> f64 {
+        let span = (self.end - self.start).num_days();
+        if span <= 0 {
+            return MARGIN_LEFT + Self::width() / 2.0;
+        }
+        let offset = (date - self.start).num_days();
+        (offset as f64 / span as f64).mul_add(Self::width(), MARGIN_LEFT)
+    }
+
+    fn y(&self, price: f64) -> f64 {
+        let (lo, hi) = self.y_range;
+        ((hi - price) / (hi - lo)).mul_add(Self::height(), MARGIN_TOP)
+    }
+
+    fn write_axes(&self, out: &mut String) {
+        let (lo, hi) = self.y_range;
+        let right = WIDTH - MARGIN_RIGHT;
+
+        // Y axis grid and euro labels
+        for i in 0..Y_TICKS {
+            let price = (hi - lo).mul_add(i as f64 / (Y_TICKS - 1) as f64, lo);
+            let y = self.y(price);
+            let _ = writeln!(
+                out,
+                r##"<line x1="{MARGIN_LEFT}" y1="{y:.1}" x2="{right:.1}" y2="{y:.1}" stroke="#e5e7eb"/>"##
+            );
+            let _ = writeln!(
+                out,
+                r##"<text x="{:.1}" y="{:.1}" text-anchor="end" fill="#374151">{price:.2} €</text>"##,
+                MARGIN_LEFT - 8.0,
+                y + 4.0
+            );
+        }
+
+        // X axis with date labels
+        let axis_y = MARGIN_TOP + Self::height();
+        let _ = writeln!(
+            out,
+            r##"<line x1="{MARGIN_LEFT}" y1="{axis_y:.1}" x2="{right:.1}" y2="{axis_y:.1}" stroke="#6b7280"/>"##
+        );
+        for date in tick_dates(self.start, self.end) {
+            let x = self.x(date);
+            let _ = writeln!(
+                out,
+                r##"<line x1="{x:.1}" y1="{axis_y:.1}" x2="{x:.1}" y2="{:.1}" stroke="#6b7280"/>"##,
+                axis_y + 5.0
+            );
+            let _ = writeln!(
+                out,
+                r##"<text x="{x:.1}" y="{:.1}" text-anchor="middle" fill="#374151">{}</text>"##,
+                axis_y + 20.0,
+                date
+            );
+        }
+    }
+
+    fn write_series(&self, out: &mut String, series: &SvgSeries, color: &str) {
+        let points = &series.history.data;
+        let polyline = |value: fn(&PricePoint) -> f64| {
+            points
+                .iter()
+                .map(|p| format!("{:.1},{:.1}", self.x(p.date), self.y(value(p))))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let _ = writeln!(
+            out,
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="1.5" stroke-dasharray="6 4" opacity="0.7"/>"#,
+            polyline(|p| p.avg)
+        );
+        let _ = writeln!(
+            out,
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+            polyline(|p| p.min)
+        );
+
+        // All-time low marker
+        if let Some(low) = points.iter().min_by(|a, b| a.min.total_cmp(&b.min)) {
+            let (x, y) = (self.x(low.date), self.y(low.min));
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{x:.1}" cy="{y:.1}" r="9" fill="{color}" fill-opacity="0.2"/>"#
+            );
+            let _ = writeln!(out, r#"<circle cx="{x:.1}" cy="{y:.1}" r="3.5" fill="{color}"/>"#);
+            let _ = writeln!(
+                out,
+                r#"<text x="{x:.1}" y="{:.1}" text-anchor="middle" fill="{color}">{:.2} €</text>"#,
+                y + 22.0,
+                low.min
+            );
+        }
+    }
+}
+
+fn write_legend(out: &mut String, series: &[SvgSeries]) {
+    let legend_y = HEIGHT - 24.0;
+    let mut legend_x = MARGIN_LEFT;
+
+    for (i, s) in series.iter().enumerate() {
+        let color = PALETTE[i % PALETTE.len()];
+        let _ = writeln!(
+            out,
+            r#"<line x1="{legend_x:.1}" y1="{legend_y:.1}" x2="{:.1}" y2="{legend_y:.1}" stroke="{color}" stroke-width="2"/>"#,
+            legend_x + 18.0
+        );
+        let label = format!("{} (min; avg dashed)", s.label);
+        let _ = writeln!(
+            out,
+            r##"<text x="{:.1}" y="{:.1}" fill="#111827">{}</text>"##,
+            legend_x + 24.0,
+            legend_y + 4.0,
+            escape(&label)
+        );
+        legend_x += (label.chars().count() as f64).mul_add(7.0, 40.0);
+    }
+}
+
+/// Y axis bounds covering all series, padded by 5%
+fn y_range(series: &[SvgSeries]) -> (f64, f64) {
+    let values = series.iter().flat_map(|s| s.history.data.iter().flat_map(|p| [p.min, p.avg]));
+    let (lo, hi) = super::chart::bounds(&values.collect::<Vec<_>>());
+    let pad = ((hi - lo) * 0.05).max(1.0);
+    ((lo - pad).max(0.0), hi + pad)
+}
+
+/// Evenly spaced dates between `start` and `end` used as x axis ticks
+fn tick_dates(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    let span = (end - start).num_days().max(0) as u64;
+    if span < X_TICKS as u64 {
+        return (0..=span).map(|d| start + Days::new(d)).collect();
+    }
+    (0..X_TICKS as u64).map(|i| start + Days::new(i * span / (X_TICKS as u64 - 1))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(points: &[(&str, f64)]) -> PriceHistory {
+        PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: points
+                .iter()
+                .map(|&(date, min)| PricePoint {
+                    date: date.parse().unwrap(),
+                    avg: min + 20.0,
+                    min,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn history_svg_single_series() {
+        let h = history(&[("2024-01-01", 600.0), ("2024-01-02", 590.0), ("2024-01-03", 610.0)]);
+        let svg = history_svg("Product 1", &[SvgSeries { label: "#1", history: &h }]);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("2024-01-02"));
+        assert!(svg.contains("590.00 €"));
+        assert!(svg.contains("fill-opacity=\"0.2\""));
+    }
+
+    #[test]
+    fn history_svg_multiple_series_legend() {
+        let a = history(&[("2024-01-01", 600.0), ("2024-01-02", 590.0)]);
+        let b = history(&[("2024-01-02", 500.0), ("2024-01-03", 520.0)]);
+        let svg = history_svg(
+            "Compare",
+            &[SvgSeries { label: "#1", history: &a }, SvgSeries { label: "#2", history: &b }],
+        );
+
+        assert_eq!(svg.matches("<polyline").count(), 4);
+        assert!(svg.contains(PALETTE[0]));
+        assert!(svg.contains(PALETTE[1]));
+        assert!(svg.contains("#1 (min; avg dashed)"));
+        assert!(svg.contains("#2 (min; avg dashed)"));
+    }
+
+    #[test]
+    fn history_svg_escapes_text() {
+        let h = history(&[("2024-01-01", 10.0)]);
+        let svg = history_svg("A & B <C>", &[SvgSeries { label: "\"x\"", history: &h }]);
+
+        assert!(svg.contains("A &amp; B &lt;C&gt;"));
+        assert!(svg.contains("&quot;x&quot;"));
+    }
+
+    #[test]
+    fn sparkline_svg_scales_to_box() {
+        let svg = sparkline_svg(&[10.0, 20.0, 15.0], 104.0, 24.0);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"104\""));
+        assert!(svg.contains("points=\"2.0,22.0 52.0,2.0 102.0,12.0\""));
+        assert!(svg.contains("<circle cx=\"102.0\" cy=\"12.0\""));
+        assert!(sparkline_svg(&[5.0], 104.0, 24.0).contains("points=\"2.0,12.0\""));
+    }
+
+    #[test]
+    fn tick_dates_limits_labels() {
+        let start: NaiveDate = "2024-01-01".parse().unwrap();
+        let ticks = tick_dates(start, "2024-01-31".parse().unwrap());
+
+        assert_eq!(ticks.len(), X_TICKS);
+        assert_eq!(ticks[0], start);
+        assert_eq!(ticks[X_TICKS - 1].to_string(), "2024-01-31");
+    }
+
+    #[test]
+    fn tick_dates_short_span() {
+        let start: NaiveDate = "2024-01-01".parse().unwrap();
+        assert_eq!(tick_dates(start, start), vec![start]);
+        assert_eq!(tick_dates(start, "2024-01-03".parse().unwrap()).len(), 3);
+    }
+}
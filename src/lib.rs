@@ -2,9 +2,29 @@
 //!
 //! Query Portugal's largest price comparison site from the command line.
 
+pub mod analysis;
 pub mod api;
+pub mod check;
 pub mod commands;
+pub mod config;
+pub mod daemon;
+pub mod dashboard;
+pub mod diff;
+pub mod digest;
+pub mod exporter;
+pub mod feed;
 pub mod format;
+pub mod html;
+pub mod mqtt;
+pub mod notify;
+pub mod paths;
+pub mod rules;
+pub mod searches;
+pub mod seen;
+pub mod serve;
+pub mod snapshot;
+pub mod store;
+pub mod watchlist;
 
 pub use api::{
     Badges, Category, Deal, DealsResponse, KuantoKustaClient, PriceHistory, PricePoint, Product,
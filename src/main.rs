@@ -3,10 +3,26 @@
 //! Fast product search and price tracking for KuantoKusta.pt
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use kuantokusta::analysis::stats::DEFAULT_BUY_PERCENTILE;
+use kuantokusta::analysis::Period;
 use kuantokusta::api::KuantoKustaClient;
+use kuantokusta::check::CheckState;
 use kuantokusta::commands;
+use kuantokusta::config::Config;
+use kuantokusta::feed::FeedFormat;
+use kuantokusta::format::chart::ChartStyle;
 use kuantokusta::format::OutputFormat;
+use kuantokusta::rules::{Condition, Interval};
+use kuantokusta::searches::{SavedSearch, SavedSearches};
+use kuantokusta::seen::SeenDeals;
+use kuantokusta::store::dataset::{DataFormat, ExportFilter};
+use kuantokusta::store::{self, RetentionPolicy, Store};
+use kuantokusta::watchlist::{parse_product_ref, Watchlist};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::Level;
 use tracing_subscriber::EnvFilter;
 
@@ -26,6 +42,23 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Local price database (default: ~/.local/share/kk/prices.db)
+    #[arg(long, value_name = "PATH", global = true)]
+    db: Option<PathBuf>,
+
+    /// Don't record fetched prices in the local database
+    #[arg(long, global = true)]
+    no_store: bool,
+
+    /// Config file (default: ~/.config/kk/config.toml)
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// Save the listed products as a JSON snapshot for `kk diff` (a directory
+    /// gets a timestamped file); search, browse, deals, popular and related
+    #[arg(long, value_name = "PATH", global = true)]
+    snapshot: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -53,33 +86,38 @@ enum Commands {
 
     /// List current deals and discounts
     #[command(alias = "d")]
-    Deals {
-        /// Maximum number of results
-        #[arg(short, long, default_value = "20")]
-        max: u32,
+    Deals(DealsArgs),
 
-        /// Minimum discount percentage
-        #[arg(long)]
-        min_discount: Option<u8>,
+    /// Get price history for a product
+    #[command(alias = "h")]
+    History(HistoryArgs),
 
-        /// Minimum price filter
-        #[arg(long)]
-        min_price: Option<f64>,
+    /// Price statistics and buy/wait signal for a product
+    Stats {
+        /// Product ID
+        product_id: u64,
 
-        /// Maximum price filter
-        #[arg(long)]
-        max_price: Option<f64>,
+        /// Number of days of history
+        #[arg(short, long, default_value = "90")]
+        days: u32,
+
+        /// Signal "buy" when the current price is at or below this percentile
+        #[arg(long, default_value_t = DEFAULT_BUY_PERCENTILE)]
+        buy_percentile: f64,
     },
 
-    /// Get price history for a product
-    #[command(alias = "h")]
-    History {
+    /// Forecast prices and whether waiting is likely to pay off
+    Forecast {
         /// Product ID
         product_id: u64,
 
-        /// Number of days of history
-        #[arg(short, long, default_value = "30")]
+        /// Number of days to forecast
+        #[arg(short, long, default_value = "14")]
         days: u32,
+
+        /// Days of history to fit the models on
+        #[arg(short, long, default_value = "90")]
+        window: u32,
     },
 
     /// Get popular products in a category
@@ -104,6 +142,163 @@ enum Commands {
         max: u32,
     },
 
+    /// Compare two snapshots (--snapshot): added, removed and re-priced products
+    Diff {
+        /// Older snapshot file
+        from: PathBuf,
+
+        /// Newer snapshot file
+        to: PathBuf,
+    },
+
+    /// Run read-only SQL against the local price database
+    #[command(alias = "q")]
+    Query {
+        /// SQL statement (see --schema for the documented views)
+        #[arg(required_unless_present = "schema")]
+        sql: Option<String>,
+
+        /// List the documented views and their columns
+        #[arg(long)]
+        schema: bool,
+    },
+
+    /// Manage the watchlist
+    #[command(alias = "w")]
+    Watch {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE", global = true)]
+        list: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: WatchCommand,
+    },
+
+    /// Manage saved searches and report what changed since their last run
+    Saved {
+        /// Saved searches file (default: ~/.config/kk/searches.toml)
+        #[arg(long, value_name = "FILE", global = true)]
+        file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: SavedCommand,
+    },
+
+    /// Check the watchlist and report what changed since the last check
+    ///
+    /// Exits with status 2 when an alert fires (target reached, or a drop beyond `max_drop`).
+    Check {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE")]
+        list: Option<PathBuf>,
+
+        /// Only check products with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+
+        /// State file with the last observed prices (default: ~/.local/share/kk/check-state.json)
+        #[arg(long, value_name = "FILE")]
+        state: Option<PathBuf>,
+
+        /// Don't record this check or notify; the next one compares against the same state
+        #[arg(long)]
+        no_save: bool,
+    },
+
+    /// Generate a static HTML dashboard of the watchlist from the local database
+    ///
+    /// Writes an overview with sparklines and deal highlights, and a page per
+    /// product with its price history chart and daily offers. Host it with
+    /// `kk serve` or any web server.
+    Dashboard {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE")]
+        list: Option<PathBuf>,
+
+        /// Only include products with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+
+        /// Directory to write the site to (default: ~/.local/share/kk/www)
+        #[arg(short, long, value_name = "DIR")]
+        out: Option<PathBuf>,
+    },
+
+    /// Summarize the last week or month of the watchlist and deals from the local database
+    ///
+    /// Lists the biggest drops, all-time lows, reached targets, new deals in the
+    /// categories of watched products and the change in total basket cost.
+    Digest {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE")]
+        list: Option<PathBuf>,
+
+        /// Only include products with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+
+        /// Period to summarize, ending today
+        #[arg(short, long, value_enum, default_value = "week")]
+        period: Period,
+
+        /// Render HTML instead of Markdown (`--format json` gives JSON)
+        #[arg(long)]
+        html: bool,
+
+        /// Also send the digest to this notification target (repeatable)
+        #[arg(long, value_name = "TARGET")]
+        notify: Vec<String>,
+    },
+
+    /// Validate, try out and list alert rules
+    Rules {
+        #[command(subcommand)]
+        action: RulesCommand,
+    },
+
+    /// Run checks on a schedule in the foreground (see [daemon] in the config)
+    ///
+    /// Stops on SIGTERM or Ctrl-C; SIGHUP reloads the config.
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonCommand>,
+    },
+
+    /// Expose the watched products' prices and API client metrics to Prometheus
+    ///
+    /// Prices refresh on a schedule, paced by `delay_ms` in [request]; set
+    /// `cache_secs` there to reuse recently fetched products.
+    Exporter(ExporterArgs),
+
+    /// Write Atom or RSS feeds of deals and fired alerts
+    Feed {
+        #[command(subcommand)]
+        action: FeedCommand,
+    },
+
+    /// Serve feeds and other generated files over HTTP until Ctrl-C
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        /// Directory to serve (default: ~/.local/share/kk/www)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+
+    /// Manage notification targets ([notify] in the config)
+    Notify {
+        #[command(subcommand)]
+        action: NotifyCommand,
+    },
+
+    /// Manage the local price database
+    Db {
+        #[command(subcommand)]
+        action: DbCommand,
+    },
+
     /// List categories
     #[command(alias = "c")]
     Categories {
@@ -113,48 +308,874 @@ enum Commands {
     },
 }
 
+#[derive(Args)]
+#[command(group(ArgGroup::new("products").required(true).multiple(true).args(["product_ids", "watchlist"])))]
+struct HistoryArgs {
+    /// Product ID(s); several IDs are compared side by side
+    #[arg(num_args = 1..)]
+    product_ids: Vec<u64>,
+
+    /// Also compare the products of this watchlist file
+    #[arg(long, value_name = "FILE")]
+    watchlist: Option<PathBuf>,
+
+    /// Only take watchlist products with this tag
+    #[arg(short, long, requires = "watchlist")]
+    tag: Option<String>,
+
+    /// Number of days of history
+    #[arg(short, long, default_value = "30")]
+    days: u32,
+
+    /// Draw the min/avg series as a chart instead of a table (`--chart=ascii` for plain ASCII)
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "braille"
+    )]
+    chart: Option<ChartStyle>,
+
+    /// Write a standalone SVG chart to this file
+    #[arg(long, value_name = "FILE")]
+    svg: Option<PathBuf>,
+
+    /// Write date-aligned min prices (one column per product) to a CSV file
+    #[arg(long, value_name = "FILE")]
+    csv: Option<PathBuf>,
+
+    /// Only show prices from this date (YYYY-MM-DD; beyond 90 days ago needs --local)
+    #[arg(long, value_name = "DATE")]
+    since: Option<NaiveDate>,
+
+    /// Only show prices up to this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    until: Option<NaiveDate>,
+
+    /// Aggregate per week or month (lowest min, mean avg)
+    #[arg(long, value_enum)]
+    resample: Option<Period>,
+
+    /// Fill days with no data using the previous day's prices
+    #[arg(long)]
+    fill_gaps: bool,
+
+    /// Merge in prices recorded in the local database (extends history beyond the API window)
+    #[arg(long)]
+    local: bool,
+}
+
+impl HistoryArgs {
+    /// The given IDs followed by the (tag-filtered) watchlist's, without duplicates
+    fn product_ids(&self) -> Result<Vec<u64>> {
+        let mut ids = self.product_ids.clone();
+        if let Some(path) = &self.watchlist {
+            let list = Watchlist::load(path)?;
+            for entry in list.filtered(self.tag.as_deref()) {
+                if !ids.contains(&entry.id) {
+                    ids.push(entry.id);
+                }
+            }
+        }
+        if ids.is_empty() {
+            anyhow::bail!("The watchlist has no products to compare");
+        }
+        Ok(ids)
+    }
+
+    fn options(&self) -> commands::HistoryOptions {
+        commands::HistoryOptions {
+            days: self.days,
+            chart: self.chart,
+            svg: self.svg.clone(),
+            csv: self.csv.clone(),
+            since: self.since,
+            until: self.until,
+            resample: self.resample,
+            fill_gaps: self.fill_gaps,
+            local: self.local,
+        }
+    }
+}
+
+#[derive(Args)]
+struct DealsArgs {
+    /// Maximum number of results
+    #[arg(short, long, default_value = "20")]
+    max: u32,
+
+    /// Minimum discount percentage
+    #[arg(long)]
+    min_discount: Option<u8>,
+
+    /// Minimum price filter
+    #[arg(long)]
+    min_price: Option<f64>,
+
+    /// Maximum price filter
+    #[arg(long)]
+    max_price: Option<f64>,
+
+    /// Only list deals not listed before, or cheaper or more discounted since
+    #[arg(long)]
+    new_only: bool,
+
+    /// Seen-set used by --new-only (default: ~/.local/share/kk/seen-deals.json)
+    #[arg(long, value_name = "FILE")]
+    seen: Option<PathBuf>,
+
+    /// Forget deals not listed for this long, so they count as new again (0: never)
+    #[arg(long, value_name = "DURATION", default_value = "7d")]
+    expire: Interval,
+}
+
+impl DealsArgs {
+    fn into_options(self) -> commands::DealsOptions {
+        commands::DealsOptions {
+            max: self.max,
+            min_discount: self.min_discount,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            seen: self.new_only.then(|| self.seen.unwrap_or_else(SeenDeals::default_path)),
+            expire: self.expire.0,
+        }
+    }
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("output").required(true).multiple(true).args(["listen", "textfile"])))]
+struct ExporterArgs {
+    /// Serve the metrics at /metrics on this address, e.g. 127.0.0.1:9716
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<SocketAddr>,
+
+    /// Write the metrics to this file after every refresh (`node_exporter` textfile collector)
+    #[arg(long, value_name = "FILE")]
+    textfile: Option<PathBuf>,
+
+    /// Refresh once, write the textfile and exit (for cron)
+    #[arg(long, requires = "textfile", conflicts_with = "listen")]
+    once: bool,
+
+    /// Time between price refreshes
+    #[arg(long, value_name = "DURATION", default_value = "5m")]
+    every: Interval,
+
+    /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+    #[arg(long, value_name = "FILE")]
+    list: Option<PathBuf>,
+}
+
+impl ExporterArgs {
+    fn into_options(self) -> Result<commands::ExporterOptions> {
+        let refresh = self.every.0.to_std().ok().filter(|d| !d.is_zero());
+        let Some(refresh) = refresh else {
+            anyhow::bail!("--every must be positive");
+        };
+        Ok(commands::ExporterOptions {
+            watchlist: self.list.unwrap_or_else(Watchlist::default_path),
+            refresh,
+            listen: self.listen,
+            textfile: self.textfile,
+            once: self.once,
+        })
+    }
+}
+
+#[derive(Subcommand)]
+enum RulesCommand {
+    /// Check a condition's syntax, and optionally evaluate it against a product
+    Test {
+        /// Condition, e.g. "stores >= 10 and price <= 90d min"
+        condition: Condition,
+
+        /// Evaluate against this product's current data
+        #[arg(short, long)]
+        product: Option<String>,
+    },
+
+    /// List the global rules and the rules of each watched product
+    #[command(alias = "ls")]
+    List {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE")]
+        list: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Run the scheduled jobs (the default)
+    Run {
+        /// Watchlist file (default: ~/.config/kk/watchlist.toml)
+        #[arg(long, value_name = "FILE")]
+        list: Option<PathBuf>,
+
+        /// State file with the last observed prices (default: ~/.local/share/kk/check-state.json)
+        #[arg(long, value_name = "FILE")]
+        state: Option<PathBuf>,
+
+        /// Saved searches file (default: ~/.config/kk/searches.toml)
+        #[arg(long, value_name = "FILE")]
+        searches: Option<PathBuf>,
+
+        /// Run every job once and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Show the jobs of the running (or last) daemon
+    Status,
+}
+
+#[derive(Subcommand)]
+enum SavedCommand {
+    /// Save a search under a name
+    Add {
+        /// Name used by `kk saved run`
+        name: String,
+
+        /// Search query
+        query: String,
+
+        /// Results fetched per run, before the filters
+        #[arg(short, long, default_value = "20")]
+        max: usize,
+
+        /// Only keep products from this price, in euros
+        #[arg(long, value_name = "EUR")]
+        min_price: Option<f64>,
+
+        /// Only keep products up to this price, in euros
+        #[arg(long, value_name = "EUR")]
+        max_price: Option<f64>,
+
+        /// Only keep products whose brand contains this
+        #[arg(long)]
+        brand: Option<String>,
+
+        /// Only keep products whose category contains this
+        #[arg(long)]
+        category: Option<String>,
+    },
+
+    /// List saved searches
+    #[command(alias = "ls")]
+    List,
+
+    /// Delete a saved search
+    #[command(alias = "rm")]
+    Remove {
+        /// Search name
+        name: String,
+    },
+
+    /// Run a saved search (default: all of them) and show new products, removed
+    /// products and price changes since the previous run
+    Run {
+        /// Search name
+        name: Option<String>,
+
+        /// Don't record this run or notify; the next one compares against the same results
+        #[arg(long)]
+        no_save: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FeedCommand {
+    /// Feed of the current deals
+    Deals {
+        /// Maximum number of deals
+        #[arg(short, long, default_value = "50")]
+        max: u32,
+
+        /// Minimum discount percentage
+        #[arg(long)]
+        min_discount: Option<u8>,
+
+        /// Feed syntax
+        #[arg(long = "type", value_enum, default_value = "atom")]
+        kind: FeedFormat,
+
+        /// Output file, `-` for stdout (default: ~/.local/share/kk/www/deals.xml)
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+
+    /// Feed of the latest alerts fired by checks, the daemon and saved searches
+    Alerts {
+        /// Maximum number of alerts
+        #[arg(short, long, default_value = "50")]
+        max: usize,
+
+        /// Feed syntax
+        #[arg(long = "type", value_enum, default_value = "atom")]
+        kind: FeedFormat,
+
+        /// Output file, `-` for stdout (default: ~/.local/share/kk/www/alerts.xml)
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum NotifyCommand {
+    /// Send a test notification
+    Test {
+        /// Target name, e.g. `team` for [notify.team]
+        target: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WatchCommand {
+    /// Watch a product by ID or product page URL
+    Add {
+        /// Product ID or URL
+        product: String,
+
+        /// Label (default: the product name)
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// Tags (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+
+        /// Target price in euros
+        #[arg(long, value_name = "EUR")]
+        target: Option<f64>,
+
+        /// Price drop threshold in percent
+        #[arg(long, value_name = "PCT")]
+        max_drop: Option<f64>,
+    },
+
+    /// List watched products with their current prices
+    #[command(alias = "ls")]
+    List {
+        /// Only products with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+
+    /// Stop watching a product
+    #[command(alias = "rm")]
+    Remove {
+        /// Product ID or URL
+        product: String,
+    },
+
+    /// Change the label or thresholds of a watched product
+    Edit {
+        /// Product ID or URL
+        product: String,
+
+        /// New label
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// New target price in euros
+        #[arg(long, value_name = "EUR", conflicts_with = "clear_target")]
+        target: Option<f64>,
+
+        /// New price drop threshold in percent
+        #[arg(long, value_name = "PCT", conflicts_with = "clear_max_drop")]
+        max_drop: Option<f64>,
+
+        /// Remove the target price
+        #[arg(long)]
+        clear_target: bool,
+
+        /// Remove the price drop threshold
+        #[arg(long)]
+        clear_max_drop: bool,
+
+        /// Add an alert rule, e.g. "price <= 90d min" (repeatable)
+        #[arg(long = "rule", value_name = "CONDITION")]
+        rules: Vec<Condition>,
+
+        /// Remove the product's alert rules
+        #[arg(long)]
+        clear_rules: bool,
+    },
+
+    /// Add or remove tags
+    Tag {
+        /// Product ID or URL
+        product: String,
+
+        /// Tags
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Remove the tags instead of adding them
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Write the watchlist (or one tag of it) to a shareable file
+    Export {
+        /// Output file (default: stdout)
+        file: Option<PathBuf>,
+
+        /// Only products with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+    },
+
+    /// Merge a shared watchlist file into yours
+    Import {
+        /// Watchlist file
+        file: PathBuf,
+
+        /// Overwrite products you already watch
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Import daily prices (`product_id`, `date`, `min`, `avg`) from CSV or NDJSON
+    Import {
+        /// Dataset file
+        file: PathBuf,
+
+        /// Dataset format (default: from the file extension)
+        #[arg(long = "type", value_enum)]
+        kind: Option<DataFormat>,
+
+        /// Overwrite prices already stored for the same product and day
+        #[arg(long)]
+        replace: bool,
+
+        /// Import the valid rows even if some rows are invalid
+        #[arg(long)]
+        skip_invalid: bool,
+
+        /// Only validate the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Export daily prices to CSV, NDJSON or Parquet
+    Export {
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Dataset format (default: from the output extension, else CSV)
+        #[arg(long = "type", value_enum)]
+        kind: Option<DataFormat>,
+
+        /// Only these products (repeatable)
+        #[arg(long = "product", value_name = "ID")]
+        products: Vec<u64>,
+
+        /// Only products in this category (ID or name)
+        #[arg(long)]
+        category: Option<String>,
+
+        /// Only prices from this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<NaiveDate>,
+
+        /// Only prices up to this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        until: Option<NaiveDate>,
+    },
+
+    /// Row counts, size and date coverage
+    Stats,
+
+    /// Downsample or delete old rows (policy from the [retention] config section)
+    Prune {
+        /// Keep daily rows for this many days, then weekly (0 = forever)
+        #[arg(long, value_name = "DAYS")]
+        daily_days: Option<u32>,
+
+        /// Keep weekly rows for this many days, then monthly (0 = forever)
+        #[arg(long, value_name = "DAYS")]
+        weekly_days: Option<u32>,
+
+        /// Delete rows older than this many days (0 = never)
+        #[arg(long, value_name = "DAYS")]
+        max_days: Option<u32>,
+
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Rebuild the database file to reclaim space
+    Vacuum,
+
+    /// Write a consistent copy of the database (safe while kk is running)
+    Backup {
+        /// Destination file (must not exist)
+        dest: PathBuf,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging
-    let filter = if cli.verbose {
-        EnvFilter::new(Level::DEBUG.to_string())
-    } else {
-        EnvFilter::from_default_env().add_directive(Level::WARN.into())
-    };
-    tracing_subscriber::fmt().with_env_filter(filter).with_target(false).init();
+    let long_running = matches!(
+        cli.command,
+        Commands::Daemon { .. } | Commands::Serve { .. } | Commands::Exporter(_)
+    );
+    init_logging(cli.verbose, long_running);
 
-    let client = KuantoKustaClient::new()?;
+    let db = cli.db.clone().unwrap_or_else(Store::default_path);
+    let config_path = cli.config.clone().unwrap_or_else(Config::default_path);
+    let client = open_client(&cli, &db)?;
 
+    let mut alerts = 0;
+    let snapshot = cli.snapshot.as_deref();
+    let mut saved = None;
     let output = match cli.command {
         Commands::Search { query, max } => {
-            commands::search(&client, &query, max, cli.format).await?
+            listed(commands::search(&client, &query, max, snapshot, cli.format).await?, &mut saved)
         }
 
-        Commands::Browse { max } => commands::browse(&client, max, cli.format).await?,
+        Commands::Browse { max } => {
+            listed(commands::browse(&client, max, snapshot, cli.format).await?, &mut saved)
+        }
 
-        Commands::Deals { max, min_discount, min_price, max_price } => {
-            commands::deals(&client, max, min_discount, min_price, max_price, cli.format).await?
+        Commands::Deals(args) => {
+            let opts = args.into_options();
+            listed(commands::deals(&client, &opts, snapshot, cli.format).await?, &mut saved)
         }
 
-        Commands::History { product_id, days } => {
-            commands::history(&client, product_id, days, cli.format).await?
+        Commands::History(args) => {
+            commands::history(&client, &args.product_ids()?, &args.options(), cli.format).await?
+        }
+
+        Commands::Stats { product_id, days, buy_percentile } => {
+            commands::stats(&client, product_id, days, buy_percentile, cli.format).await?
+        }
+
+        Commands::Forecast { product_id, days, window } => {
+            commands::forecast(&client, product_id, days, window, cli.format).await?
         }
 
         Commands::Popular { category_id, max } => {
-            commands::popular(&client, category_id, max, cli.format).await?
+            let listing = commands::popular(&client, category_id, max, snapshot, cli.format);
+            listed(listing.await?, &mut saved)
         }
 
         Commands::Related { product_id, max } => {
-            commands::related(&client, product_id, max, cli.format).await?
+            let listing = commands::related(&client, product_id, max, snapshot, cli.format);
+            listed(listing.await?, &mut saved)
         }
 
+        Commands::Diff { from, to } => commands::diff(&from, &to, cli.format)?,
+
+        Commands::Query { sql, schema } => {
+            commands::query(&db, sql.as_deref(), schema, cli.format)?
+        }
+
+        Commands::Watch { list, action } => {
+            let path = list.unwrap_or_else(Watchlist::default_path);
+            run_watch(&client, action, &path, cli.format).await?
+        }
+
+        Commands::Saved { file, action } => {
+            run_saved(&client, action, file, &config_path, cli.format).await?
+        }
+
+        Commands::Check { list, tag, state, no_save } => {
+            let outcome =
+                run_check(&client, list, tag, state, no_save, &config_path, cli.format).await?;
+            alerts = outcome.alerts;
+            outcome.output
+        }
+
+        Commands::Dashboard { list, tag, out } => {
+            let watchlist = list.unwrap_or_else(Watchlist::default_path);
+            let out = out.unwrap_or_else(kuantokusta::paths::site_dir);
+            commands::dashboard(&db, &commands::DashboardOptions { watchlist, tag, out })?
+        }
+
+        Commands::Digest { list, tag, period, html, notify } => {
+            let watchlist = list.unwrap_or_else(Watchlist::default_path);
+            let opts = commands::DigestOptions { watchlist, tag, period, html, notify };
+            commands::digest(&db, &opts, &Config::load(&config_path)?.notify, cli.format).await?
+        }
+
+        Commands::Rules { action } => run_rules(&client, action, &config_path, cli.format).await?,
+
+        Commands::Daemon { action } => {
+            run_daemon(&client, action, &config_path, cli.format).await?
+        }
+
+        Commands::Exporter(args) => {
+            let request = Config::load(&config_path)?.request;
+            let client = client
+                .clone()
+                .with_rate_limit(Duration::from_millis(request.delay_ms))
+                .with_cache(Duration::from_secs(request.cache_secs));
+            commands::exporter(&client, &args.into_options()?).await?
+        }
+
+        Commands::Feed { action } => run_feed(&client, action, &db).await?,
+
+        Commands::Serve { listen, dir } => {
+            commands::serve(&dir.unwrap_or_else(kuantokusta::paths::site_dir), listen).await?
+        }
+
+        Commands::Notify { action: NotifyCommand::Test { target } } => {
+            commands::notify_test(&Config::load(&config_path)?.notify, &target).await?
+        }
+
+        Commands::Db { action } => run_db(action, &db, &config_path, cli.format)?,
+
         Commands::Categories { parent } => {
             commands::categories(&client, parent, cli.format).await?
         }
     };
 
     println!("{output}");
+    if let Some(path) = saved {
+        eprintln!("Snapshot saved to {}", path.display());
+    }
+    if alerts > 0 {
+        // 1 is taken by errors; 2 lets cron jobs tell alerts apart
+        std::process::exit(2);
+    }
     Ok(())
 }
+
+/// API client recording into the local store at `db`, unless disabled
+fn open_client(cli: &Cli, db: &Path) -> Result<KuantoKustaClient> {
+    let client = KuantoKustaClient::new()?;
+    // Local-only commands open the database themselves (query and export read-only)
+    let records = !matches!(cli.command, Commands::Query { .. } | Commands::Db { .. });
+    if !records || cli.no_store {
+        return Ok(client);
+    }
+    match Store::open(db) {
+        Ok(store) => Ok(client.with_store(store)),
+        Err(e) => {
+            tracing::warn!("Local price store disabled: {e:#}");
+            Ok(client)
+        }
+    }
+}
+
+/// Output of a listing command, keeping the snapshot it saved for a note on stderr
+fn listed(listing: commands::ListOutcome, saved: &mut Option<PathBuf>) -> String {
+    *saved = listing.snapshot;
+    listing.output
+}
+
+/// Log warnings, or debug output with `verbose`; long-running commands
+/// (daemon, servers) also report their progress at info level
+fn init_logging(verbose: bool, long_running: bool) {
+    let filter = if verbose {
+        EnvFilter::new(Level::DEBUG.to_string())
+    } else {
+        let level = if long_running { Level::INFO } else { Level::WARN };
+        EnvFilter::from_default_env().add_directive(level.into())
+    };
+    tracing_subscriber::fmt().with_env_filter(filter).with_target(false).init();
+}
+
+/// Run `kk check`, notifying the configured targets of alerts
+async fn run_check(
+    client: &KuantoKustaClient,
+    list: Option<PathBuf>,
+    tag: Option<String>,
+    state: Option<PathBuf>,
+    no_save: bool,
+    config_path: &Path,
+    format: OutputFormat,
+) -> Result<commands::CheckOutcome> {
+    let list = list.unwrap_or_else(Watchlist::default_path);
+    let state = state.unwrap_or_else(CheckState::default_path);
+    let config = Config::load(config_path)?;
+    let client = client.clone().with_rate_limit(Duration::from_millis(config.request.delay_ms));
+    commands::check(&client, &list, &state, tag.as_deref(), !no_save, &config, format).await
+}
+
+/// Run a `kk rules` subcommand
+async fn run_rules(
+    client: &KuantoKustaClient,
+    action: RulesCommand,
+    config_path: &Path,
+    format: OutputFormat,
+) -> Result<String> {
+    match action {
+        RulesCommand::Test { condition, product } => {
+            let product = product.as_deref().map(parse_product_ref).transpose()?;
+            commands::rules_test(client, &condition, product, &CheckState::default_path()).await
+        }
+        RulesCommand::List { list } => {
+            let list = list.unwrap_or_else(Watchlist::default_path);
+            commands::rules_list(&Config::load(config_path)?.alerts, &list, format)
+        }
+    }
+}
+
+/// Run a `kk daemon` subcommand
+async fn run_daemon(
+    client: &KuantoKustaClient,
+    action: Option<DaemonCommand>,
+    config_path: &Path,
+    format: OutputFormat,
+) -> Result<String> {
+    let run = DaemonCommand::Run { list: None, state: None, searches: None, once: false };
+    match action.unwrap_or(run) {
+        DaemonCommand::Run { list, state, searches, once } => {
+            let opts = commands::DaemonOptions {
+                config_path: config_path.to_path_buf(),
+                watchlist: list.unwrap_or_else(Watchlist::default_path),
+                state: state.unwrap_or_else(CheckState::default_path),
+                searches: searches.unwrap_or_else(SavedSearches::default_path),
+                once,
+            };
+            commands::daemon_run(client, &opts).await
+        }
+        DaemonCommand::Status => commands::daemon_status(config_path, format),
+    }
+}
+
+/// Run a `kk saved` subcommand against the saved searches in `file`
+async fn run_saved(
+    client: &KuantoKustaClient,
+    action: SavedCommand,
+    file: Option<PathBuf>,
+    config_path: &Path,
+    format: OutputFormat,
+) -> Result<String> {
+    let path = &file.unwrap_or_else(SavedSearches::default_path);
+    match action {
+        SavedCommand::Add { name, query, max, min_price, max_price, brand, category } => {
+            let search = SavedSearch {
+                name,
+                query,
+                max,
+                min_price,
+                max_price,
+                brand,
+                category,
+                added: store::today(),
+            };
+            commands::saved_add(path, search)
+        }
+        SavedCommand::List => commands::saved_list(path, format),
+        SavedCommand::Remove { name } => commands::saved_remove(path, &name),
+        SavedCommand::Run { name, no_save } => {
+            let config = Config::load(config_path)?;
+            commands::saved_run(client, path, name.as_deref(), !no_save, &config, format).await
+        }
+    }
+}
+
+/// Run a `kk feed` subcommand
+async fn run_feed(client: &KuantoKustaClient, action: FeedCommand, db: &Path) -> Result<String> {
+    let output = |format, out: Option<PathBuf>, name| commands::FeedOutput {
+        format,
+        path: out.unwrap_or_else(|| commands::FeedOutput::default_path(name)),
+    };
+    match action {
+        FeedCommand::Deals { max, min_discount, kind, out } => {
+            commands::feed_deals(client, max, min_discount, &output(kind, out, "deals")).await
+        }
+        FeedCommand::Alerts { max, kind, out } => {
+            commands::feed_alerts(db, max, &output(kind, out, "alerts"))
+        }
+    }
+}
+
+/// Run a `kk watch` subcommand against the watchlist at `path`
+async fn run_watch(
+    client: &KuantoKustaClient,
+    action: WatchCommand,
+    path: &Path,
+    format: OutputFormat,
+) -> Result<String> {
+    match action {
+        WatchCommand::Add { product, label, tags, target, max_drop } => {
+            commands::watch_add(client, path, &product, label, tags, target, max_drop).await
+        }
+        WatchCommand::List { tag } => {
+            commands::watch_list(client, path, tag.as_deref(), format).await
+        }
+        WatchCommand::Remove { product } => commands::watch_remove(path, &product),
+        WatchCommand::Edit {
+            product,
+            label,
+            target,
+            max_drop,
+            clear_target,
+            clear_max_drop,
+            rules,
+            clear_rules,
+        } => {
+            let edit = commands::WatchEdit {
+                label,
+                target_price: target,
+                max_drop,
+                clear_target,
+                clear_max_drop,
+                rules,
+                clear_rules,
+            };
+            commands::watch_edit(path, &product, edit)
+        }
+        WatchCommand::Tag { product, tags, remove } => {
+            commands::watch_tag(path, &product, tags, remove)
+        }
+        WatchCommand::Export { file, tag } => {
+            commands::watch_export(path, file.as_deref(), tag.as_deref())
+        }
+        WatchCommand::Import { file, replace } => commands::watch_import(path, &file, replace),
+    }
+}
+
+/// Run a `kk db` subcommand against the database at `db`
+fn run_db(
+    action: DbCommand,
+    db: &Path,
+    config_path: &Path,
+    format: OutputFormat,
+) -> Result<String> {
+    match action {
+        DbCommand::Import { file, kind, replace, skip_invalid, dry_run } => {
+            let opts = commands::ImportOptions {
+                path: file,
+                format: kind,
+                replace,
+                skip_invalid,
+                dry_run,
+            };
+            commands::db_import(db, &opts)
+        }
+        DbCommand::Export { output, kind, products, category, since, until } => {
+            let opts = commands::ExportOptions {
+                output,
+                format: kind,
+                filter: ExportFilter { product_ids: products, category, since, until },
+            };
+            commands::db_export(db, &opts)
+        }
+        DbCommand::Stats => commands::db_stats(db, format),
+        DbCommand::Prune { daily_days, weekly_days, max_days, dry_run } => {
+            let config = Config::load(config_path)?;
+            let policy = RetentionPolicy {
+                daily_days: daily_days.or(config.retention.daily_days),
+                weekly_days: weekly_days.or(config.retention.weekly_days),
+                max_days: max_days.or(config.retention.max_days),
+                ..config.retention
+            };
+            commands::db_prune(db, &policy, dry_run)
+        }
+        DbCommand::Vacuum => commands::db_vacuum(db),
+        DbCommand::Backup { dest } => commands::db_backup(db, &dest),
+    }
+}
@@ -0,0 +1,325 @@
+//! MQTT publishing of watched products, with Home Assistant discovery
+
+use crate::api::web_url;
+use crate::check::Observed;
+use anyhow::{Context, Result};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS, Transport};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long a round of publishing may take, connection included
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The `[mqtt]` config section
+#[derive(Debug, Clone, Deserialize)]
+pub struct MqttConfig {
+    pub host: String,
+    /// Default: 1883, or 8883 with TLS
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: bool,
+    /// CA certificate (PEM) to trust instead of the system roots
+    pub ca_file: Option<PathBuf>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Read the password from this environment variable instead
+    pub password_env: Option<String>,
+    /// Default: `kk-<pid>`, so `kk check` and `kk daemon` don't kick each
+    /// other off the broker
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    /// State topic of each product; `{id}` is replaced by the product ID
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    /// Publish retained messages, so new subscribers get the last prices
+    #[serde(default = "default_true")]
+    pub retain: bool,
+    /// Announce each product as Home Assistant sensors
+    #[serde(default = "default_true")]
+    pub discovery: bool,
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+fn default_client_id() -> String {
+    format!("kk-{}", std::process::id())
+}
+
+fn default_topic() -> String {
+    "kk/product/{id}".to_string()
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_string()
+}
+
+/// A message to publish
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publish {
+    pub topic: String,
+    pub payload: Value,
+}
+
+impl MqttConfig {
+    fn state_topic(&self, product_id: u64) -> String {
+        self.topic.replace("{id}", &product_id.to_string())
+    }
+
+    /// State messages for the fetched products, preceded by their discovery config
+    pub fn messages(&self, observed: &[Observed]) -> Vec<Publish> {
+        let mut messages = Vec::new();
+        for item in observed {
+            let Some(product) = &item.product else {
+                continue;
+            };
+            let state_topic = self.state_topic(product.id);
+            if self.discovery {
+                messages.extend(self.discovery(item, &state_topic));
+            }
+            messages.push(Publish {
+                topic: state_topic,
+                payload: json!({
+                    "price_min": product.price_min,
+                    "total_offers": product.total_offers,
+                    "discount": product.discount(),
+                }),
+            });
+        }
+        messages
+    }
+
+    /// One sensor per value, grouped into a device per product
+    fn discovery(&self, item: &Observed, state_topic: &str) -> Vec<Publish> {
+        let id = item.entry.id;
+        let url = item.product.as_ref().map(|p| web_url(&p.url));
+        let device = json!({
+            "identifiers": [format!("kk_{id}")],
+            "name": item.entry.label,
+            "manufacturer": "KuantoKusta",
+            "model": format!("Product {id}"),
+            "configuration_url": url,
+        });
+        let sensors = [
+            (
+                "price",
+                "Lowest price",
+                "price_min",
+                json!({ "unit_of_measurement": "€", "device_class": "monetary" }),
+            ),
+            (
+                "stores",
+                "Stores",
+                "total_offers",
+                json!({ "state_class": "measurement", "icon": "mdi:store" }),
+            ),
+            (
+                "discount",
+                "Discount",
+                "discount",
+                json!({ "unit_of_measurement": "%", "icon": "mdi:sale" }),
+            ),
+        ];
+        sensors
+            .into_iter()
+            .map(|(key, name, field, extra)| {
+                let mut payload = json!({
+                    "name": name,
+                    "unique_id": format!("kk_{id}_{key}"),
+                    "object_id": format!("kk_{id}_{key}"),
+                    "state_topic": state_topic,
+                    "value_template": format!("{{{{ value_json.{field} }}}}"),
+                    "device": device,
+                });
+                if let (Some(payload), Some(extra)) = (payload.as_object_mut(), extra.as_object()) {
+                    payload.extend(extra.clone());
+                }
+                Publish {
+                    topic: format!("{}/sensor/kk_{id}/{key}/config", self.discovery_prefix),
+                    payload,
+                }
+            })
+            .collect()
+    }
+
+    fn options(&self) -> Result<MqttOptions> {
+        let port = self.port.unwrap_or(if self.tls { 8883 } else { 1883 });
+        let mut options = MqttOptions::new(&self.client_id, &self.host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.username {
+            let password =
+                crate::config::password(self.password.as_ref(), self.password_env.as_ref())?;
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        if self.tls {
+            options.set_transport(match &self.ca_file {
+                Some(path) => {
+                    let ca = std::fs::read(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    Transport::tls(ca, None, None)
+                }
+                None => Transport::tls_with_default_config(),
+            });
+        }
+        Ok(options)
+    }
+}
+
+/// Publish the fetched products to the broker; returns the number of messages sent
+pub async fn publish(config: &MqttConfig, observed: &[Observed]) -> Result<usize> {
+    let messages = config.messages(observed);
+    if messages.is_empty() {
+        return Ok(0);
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(config.options()?, messages.len() + 1);
+    for message in &messages {
+        client
+            .publish(&message.topic, QoS::AtLeastOnce, config.retain, message.payload.to_string())
+            .await?;
+    }
+
+    // Drive the connection until every message is acknowledged, then disconnect
+    let mut pending = messages.len();
+    let drive = async {
+        loop {
+            match eventloop.poll().await? {
+                Event::Incoming(Packet::PubAck(_)) => {
+                    pending -= 1;
+                    if pending == 0 {
+                        client.disconnect().await?;
+                    }
+                }
+                Event::Outgoing(Outgoing::Disconnect) => break,
+                _ => {}
+            }
+        }
+        anyhow::Ok(())
+    };
+    tokio::time::timeout(PUBLISH_TIMEOUT, drive)
+        .await
+        .with_context(|| format!("Timed out publishing to {}", config.host))?
+        .with_context(|| format!("Failed to publish to MQTT broker {}", config.host))?;
+    Ok(messages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Product;
+    use crate::watchlist::WatchEntry;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn observed() -> Vec<Observed> {
+        let product: Product = serde_json::from_value(json!({
+            "id": 12345, "name": "PlayStation 5", "priceMin": 449.99, "totalOffers": 12,
+            "url": "/p/12345/ps5", "badges": { "discountPercentage": 10 }, "tags": {}
+        }))
+        .unwrap();
+        let entry: WatchEntry =
+            toml::from_str("id = 12345\nlabel = \"PS5\"\nadded = \"2024-06-01\"\n").unwrap();
+        vec![
+            Observed { entry: entry.clone(), product: Some(product), history: None },
+            Observed { entry: WatchEntry { id: 7, ..entry }, product: None, history: None },
+        ]
+    }
+
+    fn config(port: u16) -> MqttConfig {
+        toml::from_str(&format!("host = \"127.0.0.1\"\nport = {port}\n")).unwrap()
+    }
+
+    #[test]
+    fn state_and_discovery_messages() {
+        let messages = config(1883).messages(&observed());
+        assert_eq!(messages.len(), 4);
+        let state = &messages[3];
+        assert_eq!(state.topic, "kk/product/12345");
+        assert_eq!(
+            state.payload,
+            json!({ "price_min": 449.99, "total_offers": 12, "discount": 10 })
+        );
+
+        let price = &messages[0];
+        assert_eq!(price.topic, "homeassistant/sensor/kk_12345/price/config");
+        assert_eq!(price.payload["state_topic"], "kk/product/12345");
+        assert_eq!(price.payload["value_template"], "{{ value_json.price_min }}");
+        assert_eq!(price.payload["unit_of_measurement"], "€");
+        assert_eq!(price.payload["device"]["name"], "PS5");
+
+        let config = MqttConfig { discovery: false, topic: "prices/{id}".to_string(), ..config(1) };
+        let messages = config.messages(&observed());
+        assert_eq!(messages.iter().map(|m| m.topic.as_str()).collect::<Vec<_>>(), ["prices/12345"]);
+    }
+
+    #[test]
+    fn client_id_defaults_per_process() {
+        assert_eq!(config(1883).client_id, format!("kk-{}", std::process::id()));
+    }
+
+    /// Read one MQTT packet: its type nibble, flags and body
+    async fn read_packet(stream: &mut TcpStream) -> Option<(u8, u8, Vec<u8>)> {
+        let header = stream.read_u8().await.ok()?;
+        let (mut len, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.ok()?;
+            len |= usize::from(byte & 0x7f) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.ok()?;
+        Some((header >> 4, header & 0x0f, body))
+    }
+
+    /// A local broker that acknowledges one client and returns what it published
+    async fn broker() -> (u16, tokio::task::JoinHandle<Vec<(String, bool, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut published = Vec::new();
+            while let Some((kind, flags, body)) = read_packet(&mut stream).await {
+                match kind {
+                    // CONNECT → CONNACK
+                    1 => stream.write_all(&[0x20, 2, 0, 0]).await.unwrap(),
+                    // PUBLISH (QoS 1) → PUBACK
+                    3 => {
+                        let len = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                        let topic = String::from_utf8(body[2..2 + len].to_vec()).unwrap();
+                        let id = &body[2 + len..4 + len];
+                        let payload = String::from_utf8(body[4 + len..].to_vec()).unwrap();
+                        published.push((topic, flags & 1 == 1, payload));
+                        stream.write_all(&[0x40, 2, id[0], id[1]]).await.unwrap();
+                    }
+                    // DISCONNECT
+                    14 => break,
+                    _ => {}
+                }
+            }
+            published
+        });
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn publishes_to_local_broker() {
+        let (port, broker) = broker().await;
+        let sent = publish(&config(port), &observed()).await.unwrap();
+        assert_eq!(sent, 4);
+
+        let published = broker.await.unwrap();
+        assert_eq!(published.len(), 4);
+        let (topic, retained, payload) = &published[3];
+        assert_eq!(topic, "kk/product/12345");
+        assert!(retained);
+        assert_eq!(payload, r#"{"discount":10,"price_min":449.99,"total_offers":12}"#);
+    }
+}
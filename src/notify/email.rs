@@ -0,0 +1,251 @@
+//! SMTP target: plain-text and HTML emails
+
+use super::{Alert, Message, SendError};
+use crate::format::chart;
+use crate::html::escape;
+use anyhow::{Context, Result};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Height of the bars of the HTML price chart, in pixels
+const CHART_HEIGHT: f64 = 32.0;
+
+/// An SMTP server and the addresses to mail
+#[derive(Debug, Clone, Deserialize)]
+pub struct Email {
+    pub host: String,
+    /// Default: 587 for STARTTLS, 465 for TLS, 25 without encryption
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Read the password from this environment variable instead
+    pub password_env: Option<String>,
+    /// Sender, e.g. `kk <kk@example.com>`
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// How the SMTP connection is encrypted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// Plain connection upgraded with STARTTLS
+    #[default]
+    Starttls,
+    /// TLS from the start (SMTPS)
+    Tls,
+    /// No encryption, e.g. for a local SMTP sink
+    None,
+}
+
+impl Email {
+    pub(super) async fn send(&self, message: &Message) -> Result<(), SendError> {
+        let permanent = |error| SendError { error, retryable: false };
+        let email = self.build(message).map_err(permanent)?;
+        let transport = self.transport().map_err(permanent)?;
+        transport.send(email).await.map(drop).map_err(|e| SendError {
+            retryable: !e.is_permanent(),
+            error: anyhow::Error::new(e).context(format!("Failed to send email via {}", self.host)),
+        })
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        type Transport = AsyncSmtpTransport<Tokio1Executor>;
+        let (builder, port) = match self.security {
+            Security::Starttls => (Transport::starttls_relay(&self.host)?, 587),
+            Security::Tls => (Transport::relay(&self.host)?, 465),
+            Security::None => (Transport::builder_dangerous(&self.host), 25),
+        };
+        let mut builder =
+            builder.port(self.port.unwrap_or(port)).timeout(Some(Duration::from_secs(30)));
+        if let Some(username) = &self.username {
+            builder = builder.credentials(Credentials::new(username.clone(), self.password()?));
+        }
+        Ok(builder.build())
+    }
+
+    fn password(&self) -> Result<String> {
+        crate::config::password(self.password.as_ref(), self.password_env.as_ref())?
+            .context("SMTP `username` needs a `password` or `password_env`")
+    }
+
+    fn build(&self, message: &Message) -> Result<lettre::Message> {
+        let parse = |address: &str| {
+            address.parse::<Mailbox>().with_context(|| format!("Invalid email address `{address}`"))
+        };
+        let mut builder =
+            lettre::Message::builder().from(parse(&self.from)?).subject(&message.title);
+        if self.to.is_empty() {
+            anyhow::bail!("Email target has no `to` addresses");
+        }
+        for to in &self.to {
+            builder = builder.to(parse(to)?);
+        }
+        builder
+            .multipart(MultiPart::alternative_plain_html(message.text.clone(), html(message)))
+            .context("Failed to build email")
+    }
+}
+
+/// HTML body: a product table with a bar chart of each price history
+fn html(message: &Message) -> String {
+    if let Some(html) = &message.html {
+        return html.clone();
+    }
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html><body style=\"font-family: sans-serif; color: #222\">\n",
+    );
+    let _ = writeln!(out, "<h2>{}</h2>", escape(&message.title));
+    if message.alerts.is_empty() {
+        let _ = writeln!(out, "<pre>{}</pre>", escape(&message.text));
+    } else {
+        out.push_str("<table cellpadding=\"6\" style=\"border-collapse: collapse\">\n");
+        out.push_str(
+            "<tr style=\"text-align: left; border-bottom: 1px solid #ccc\">\
+             <th>Product</th><th>Alert</th><th>Was</th><th>Now</th><th>Discount</th>\
+             <th>Last 30 days</th></tr>\n",
+        );
+        for alert in &message.alerts {
+            out.push_str(&row(alert));
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
+
+fn row(alert: &Alert) -> String {
+    let price = |p: Option<f64>| p.map_or_else(String::new, |p| format!("{p:.2}€"));
+    format!(
+        "<tr style=\"border-bottom: 1px solid #eee\"><td><a href=\"{}\">{}</a></td><td>{}</td>\
+         <td><s>{}</s></td><td><b>{}</b></td><td>{}</td><td>{}</td></tr>\n",
+        escape(&alert.url),
+        escape(&alert.name),
+        escape(&alert.reason),
+        price(alert.old_price),
+        price(Some(alert.new_price)),
+        alert.discount.map_or_else(String::new, |d| format!("-{d}%")),
+        bars(&alert.history)
+    )
+}
+
+/// A price chart as a row of table cells, which renders in any mail client
+fn bars(values: &[f64]) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    let (lo, hi) = chart::bounds(values);
+    let mut out = String::from(
+        "<table cellpadding=\"0\" cellspacing=\"1\" style=\"border-collapse: separate\"><tr>",
+    );
+    for &v in values {
+        // The lowest price still gets a visible bar
+        let share = if hi > lo { (v - lo) / (hi - lo) } else { 0.5 };
+        let height = (share * 0.8 + 0.2) * CHART_HEIGHT;
+        let _ = write!(
+            out,
+            "<td style=\"vertical-align: bottom; height: {CHART_HEIGHT}px\" title=\"{v:.2}€\">\
+             <div style=\"width: 4px; height: {height:.0}px; background: #2e86de\"></div></td>"
+        );
+    }
+    out.push_str("</tr></table>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Target;
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// A local SMTP sink that accepts one message and returns its DATA
+    async fn smtp_sink() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            write.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+            let mut data = String::new();
+            let mut in_data = false;
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if in_data {
+                    if line == "." {
+                        in_data = false;
+                        write.write_all(b"250 queued\r\n").await.unwrap();
+                    } else {
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    continue;
+                }
+                let reply: &[u8] = match line.get(..4).unwrap_or_default() {
+                    "EHLO" => b"250 sink\r\n",
+                    "DATA" => {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    }
+                    "QUIT" => {
+                        write.write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+                write.write_all(reply).await.unwrap();
+            }
+            data
+        });
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn sends_to_local_sink() {
+        let (port, sink) = smtp_sink().await;
+        let target: Target = toml::from_str(&format!(
+            "type = \"email\"\nhost = \"127.0.0.1\"\nport = {port}\nsecurity = \"none\"\n\
+             from = \"kk <kk@example.com>\"\nto = [\"team@example.com\"]\nretries = 0\n"
+        ))
+        .unwrap();
+        let mut message = Message::test();
+        message.alerts[0].history = vec![549.99, 499.99, 449.99];
+        target.send(&reqwest::Client::new(), &message).await.unwrap();
+
+        let data = sink.await.unwrap();
+        assert!(data.contains("Subject: kk: test notification"));
+        assert!(data.contains("To: team@example.com"));
+        assert!(data.contains("Content-Type: multipart/alternative"));
+        assert!(data.contains("Content-Type: text/plain"));
+        assert!(data.contains("Content-Type: text/html"));
+    }
+
+    #[test]
+    fn html_body() {
+        let mut message = Message::test();
+        message.alerts[0].name = "Tom & Jerry <DVD>".to_string();
+        message.alerts[0].history = vec![500.0, 450.0];
+        let body = html(&message);
+        assert!(body.contains(">Tom &amp; Jerry &lt;DVD&gt;</a>"));
+        assert!(body.contains("<s>549.99€</s>"));
+        assert_eq!(body.matches("title=\"").count(), 2);
+        assert!(body.contains("height: 32px; background"));
+        assert!(body.contains("height: 6px; background"));
+
+        let email: Email =
+            toml::from_str("host = \"localhost\"\nfrom = \"kk\"\nto = [\"a@example.com\"]\n")
+                .unwrap();
+        assert_eq!(email.security, Security::Starttls);
+        assert!(email
+            .build(&message)
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid email address `kk`"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Exec hooks: user commands run when a rule with `exec` fires
+
+use super::Alert;
+use crate::rules::Interval;
+use anyhow::{Context, Result};
+use chrono::TimeDelta;
+use serde::Deserialize;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// The `[hooks]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Hooks still running after this are killed
+    pub timeout: Interval,
+    /// Maximum number of hooks running at once
+    pub concurrency: usize,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self { timeout: Interval(TimeDelta::seconds(30)), concurrency: 4 }
+    }
+}
+
+/// Run the exec hook of every alert that has one, logging how each ended
+///
+/// Hooks get the alert as JSON on stdin and as `KK_*` environment variables.
+/// Failures are logged; the results are only returned for inspection.
+pub async fn run_hooks(config: &HooksConfig, alerts: &[Alert]) -> Vec<Result<ExitStatus>> {
+    let semaphore = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    let timeout = config.timeout;
+    let handles: Vec<_> = alerts
+        .iter()
+        .filter(|alert| alert.exec.is_some())
+        .map(|alert| {
+            let (semaphore, alert) = (Arc::clone(&semaphore), alert.clone());
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let result = run_hook(&alert, timeout).await;
+                match &result {
+                    Ok(status) if status.success() => {
+                        tracing::info!("Hook for {} finished ({status})", alert.product_id);
+                    }
+                    Ok(status) => tracing::warn!("Hook for {} failed ({status})", alert.product_id),
+                    Err(e) => tracing::error!("{e:#}"),
+                }
+                result
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.unwrap_or_else(|e| {
+            tracing::error!("Hook task failed: {e}");
+            Err(anyhow::anyhow!("Hook task failed: {e}"))
+        }));
+    }
+    results
+}
+
+async fn run_hook(alert: &Alert, timeout: Interval) -> Result<ExitStatus> {
+    let command = alert.exec.as_deref().unwrap_or_default();
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command).envs(env(alert)).stdin(Stdio::piped()).stdout(Stdio::null());
+    // Its own process group, so a timeout also kills whatever the shell started
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let mut child = cmd
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run hook `{command}`"))?;
+
+    let input = serde_json::to_vec(alert)?;
+    let mut stdin = child.stdin.take();
+    let run = async {
+        if let Some(stdin) = &mut stdin {
+            // A hook that doesn't read its input may exit first; that's fine
+            let _ = stdin.write_all(&input).await;
+        }
+        drop(stdin);
+        child.wait().await
+    };
+    let Ok(status) = tokio::time::timeout(timeout.0.to_std().unwrap_or(Duration::ZERO), run).await
+    else {
+        #[cfg(unix)]
+        if let Some(group) = child.id().and_then(|id| rustix::process::Pid::from_raw(id as i32)) {
+            let _ = rustix::process::kill_process_group(group, rustix::process::Signal::KILL);
+        }
+        let _ = child.kill().await;
+        anyhow::bail!("Hook `{command}` timed out after {timeout}");
+    };
+    status.with_context(|| format!("Failed to wait for hook `{command}`"))
+}
+
+/// `KK_*` variables describing the alert; unknown values are left unset
+fn env(alert: &Alert) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("KK_PRODUCT_ID", alert.product_id.to_string()),
+        ("KK_PRODUCT_NAME", alert.name.clone()),
+        ("KK_REASON", alert.reason.clone()),
+        ("KK_NEW_PRICE", format!("{:.2}", alert.new_price)),
+        ("KK_URL", alert.url.clone()),
+    ];
+    if let Some(rule) = &alert.rule {
+        vars.push(("KK_RULE", rule.clone()));
+    }
+    if let Some(old) = alert.old_price {
+        vars.push(("KK_OLD_PRICE", format!("{old:.2}")));
+    }
+    if let Some(discount) = alert.discount {
+        vars.push(("KK_DISCOUNT", discount.to_string()));
+    }
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notify::Message;
+
+    fn alert(exec: &str) -> Alert {
+        let mut alert = Message::test().alerts.remove(0);
+        alert.rule = Some("cheap".to_string());
+        alert.exec = Some(exec.to_string());
+        alert
+    }
+
+    #[tokio::test]
+    async fn passes_alert_on_stdin_and_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let hook = format!(
+            "cat > {0}.json && echo \"$KK_PRODUCT_ID $KK_RULE $KK_OLD_PRICE $KK_NEW_PRICE\" > {0}.env",
+            out.display()
+        );
+        let results = run_hooks(&HooksConfig::default(), &[alert(&hook)]).await;
+        assert!(results[0].as_ref().unwrap().success());
+
+        let json: Alert =
+            serde_json::from_str(&std::fs::read_to_string(out.with_extension("json")).unwrap())
+                .unwrap();
+        assert_eq!(json.product_id, 11_406_755);
+        assert_eq!(json.rule.as_deref(), Some("cheap"));
+        let env = std::fs::read_to_string(out.with_extension("env")).unwrap();
+        assert_eq!(env, "11406755 cheap 549.99 449.99\n");
+    }
+
+    #[tokio::test]
+    async fn reports_failures_and_timeouts() {
+        let config = HooksConfig { timeout: "1s".parse().unwrap(), concurrency: 1 };
+        let mut no_hook = alert("true");
+        no_hook.exec = None;
+        let results =
+            run_hooks(&config, &[alert("exit 3"), no_hook, alert("sleep 10"), alert("true")]).await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().code(), Some(3));
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            "Hook `sleep 10` timed out after 1s"
+        );
+        assert!(results[2].as_ref().unwrap().success());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timeout_kills_background_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let config = HooksConfig { timeout: "1s".parse().unwrap(), concurrency: 1 };
+        let hook = format!("(sleep 2; touch {}) & wait", out.display());
+        assert!(run_hooks(&config, &[alert(&hook)]).await[0].is_err());
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!out.exists());
+    }
+}
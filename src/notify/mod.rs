@@ -0,0 +1,482 @@
+//! Alert notifications: named targets from the `[notify]` config section
+
+mod email;
+mod exec;
+mod webhook;
+
+pub use email::{Email, Security};
+pub use exec::{run_hooks, HooksConfig};
+pub use webhook::Webhook;
+
+use crate::api::{web_url, Deal, KuantoKustaClient, Product};
+use crate::check::{Change, ChangeKind, CheckReport};
+use crate::format::chart;
+use crate::rules::Rule;
+use crate::searches::{SearchDiff, SearchHit};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Days of history shown in an alert's sparkline
+const SPARKLINE_DAYS: u32 = 30;
+
+/// Delay before the first retry; doubled for each further one
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest wait between two retries, however many there are
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A notification target, e.g. `[notify.team]`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Target {
+    #[serde(flatten)]
+    pub channel: Channel,
+    /// Send the alerts of `kk check` and `kk daemon` here (default: true)
+    #[serde(default = "default_true")]
+    pub alerts: bool,
+    /// Retries after a failed delivery
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_retries() -> u32 {
+    3
+}
+
+/// Where and how a target delivers, chosen by its `type`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Channel {
+    /// JSON with the title, a text body and the structured alerts
+    Webhook(Webhook),
+    Slack(Webhook),
+    Discord(Webhook),
+    /// `url` is the topic URL, e.g. `https://ntfy.sh/my-alerts`
+    Ntfy(Webhook),
+    /// `url` is the message endpoint, e.g. `https://gotify.example.com/message?token=...`
+    Gotify(Webhook),
+    /// Plain-text and HTML email over SMTP
+    Email(Email),
+}
+
+/// A fired alert, with what a notification shows about the product
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub product_id: u64,
+    pub name: String,
+    /// What fired, e.g. "Target 650.00€ reached" or "Rule `90-day low`"
+    pub reason: String,
+    /// Label of the rule that fired, if a rule did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Price at the last check, when it changed since
+    pub old_price: Option<f64>,
+    pub new_price: f64,
+    pub discount: Option<u8>,
+    pub url: String,
+    /// Daily lowest prices over the last 30 days
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<f64>,
+    /// `history` as a sparkline
+    pub sparkline: Option<String>,
+    /// Command of the rule that fired, run by [`run_hooks`]
+    #[serde(skip)]
+    pub exec: Option<String>,
+}
+
+impl Alert {
+    /// Alerts of a watchlist check, completed with the products' current data
+    pub fn from_check(report: &CheckReport, products: &[&Product]) -> Vec<Self> {
+        alerting(report)
+            .filter_map(|change| {
+                let product = products.iter().find(|p| p.id == change.product_id)?;
+                Some(Self::new(change, report, product.price_min, product.discount(), &product.url))
+            })
+            .collect()
+    }
+
+    /// Alerts of a deal scan
+    pub fn from_deals(report: &CheckReport, deals: &[Deal]) -> Vec<Self> {
+        alerting(report)
+            .filter_map(|change| {
+                let deal = deals.iter().find(|d| d.id == change.product_id)?;
+                Some(Self::new(change, report, deal.price_min, deal.discount(), &deal.url))
+            })
+            .collect()
+    }
+
+    /// Alerts for the new products and price drops of a saved search
+    pub fn from_search(diff: &SearchDiff) -> Vec<Self> {
+        let alert = |hit: &SearchHit, reason: String, old_price| Self {
+            product_id: hit.product_id,
+            name: hit.name.clone(),
+            reason,
+            rule: None,
+            old_price,
+            new_price: hit.price_min,
+            discount: None,
+            url: hit.url.clone(),
+            history: Vec::new(),
+            sparkline: None,
+            exec: None,
+        };
+        let new = diff.new.iter().map(|hit| alert(hit, format!("New in `{}`", diff.search), None));
+        let drops = diff.changed.iter().filter(|c| c.change_pct < 0.0).map(|c| {
+            let reason = format!("Price drop ({:+.1}%) in `{}`", c.change_pct, diff.search);
+            alert(&c.hit, reason, Some(c.old_price))
+        });
+        new.chain(drops).collect()
+    }
+
+    fn new(
+        change: &Change,
+        report: &CheckReport,
+        price: f64,
+        discount: Option<u8>,
+        path: &str,
+    ) -> Self {
+        // The old price comes from this product's price change in the same check
+        let old_price = report.changes.iter().find_map(|c| match c.kind {
+            ChangeKind::PriceDrop { from, .. } | ChangeKind::PriceIncrease { from, .. }
+                if c.product_id == change.product_id =>
+            {
+                Some(from)
+            }
+            _ => None,
+        });
+        let reason = match &change.kind {
+            ChangeKind::PriceDrop { change_pct, .. } => format!("Price drop ({change_pct:+.1}%)"),
+            ChangeKind::PriceIncrease { change_pct, .. } => {
+                format!("Price increase ({change_pct:+.1}%)")
+            }
+            ChangeKind::TargetReached { target, .. } => format!("Target {target:.2}€ reached"),
+            ChangeKind::OffersChanged { from, to } => format!("Stores {from} → {to}"),
+            ChangeKind::RuleMatched { rule, .. } => format!("Rule `{rule}`"),
+        };
+        let rule = match &change.kind {
+            ChangeKind::RuleMatched { rule, .. } => Some(rule.clone()),
+            _ => None,
+        };
+        let url = web_url(path);
+        Self {
+            product_id: change.product_id,
+            name: change.label.clone(),
+            reason,
+            rule,
+            old_price,
+            new_price: price,
+            discount,
+            url,
+            history: Vec::new(),
+            sparkline: None,
+            exec: None,
+        }
+    }
+
+    /// Take the exec hook of the rule that fired from `rules`, the first one with its label
+    pub fn set_exec<'a>(&mut self, rules: impl IntoIterator<Item = &'a Rule>) {
+        let Some(label) = &self.rule else {
+            return;
+        };
+        self.exec = rules.into_iter().find(|r| r.label() == label).and_then(|r| r.exec.clone());
+    }
+
+    /// One line with the prices and discount, e.g. `699.99€ → 449.99€ · -25%`
+    pub fn prices(&self) -> String {
+        let mut out = self.old_price.map_or_else(
+            || format!("{:.2}€", self.new_price),
+            |old| format!("{old:.2}€ → {:.2}€", self.new_price),
+        );
+        if let Some(discount) = self.discount {
+            let _ = write!(out, " · -{discount}%");
+        }
+        out
+    }
+
+    /// Plain-text rendering, for targets that take a text body
+    pub fn text(&self) -> String {
+        let mut out = format!("{}: {}\n{}\n{}", self.name, self.reason, self.prices(), self.url);
+        if let Some(sparkline) = &self.sparkline {
+            let _ = write!(out, "\n{sparkline}");
+        }
+        out
+    }
+}
+
+fn alerting(report: &CheckReport) -> impl Iterator<Item = &Change> {
+    report.changes.iter().filter(|c| c.alert)
+}
+
+/// Something to send: a title, a text body and the alerts it is about
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub title: String,
+    pub text: String,
+    pub alerts: Vec<Alert>,
+    /// HTML body for email, instead of one rendered from the alerts
+    #[serde(skip)]
+    pub html: Option<String>,
+}
+
+impl Message {
+    /// A message about fired alerts
+    pub fn alerts(alerts: Vec<Alert>) -> Self {
+        let title = match alerts.as_slice() {
+            [alert] => format!("kk: {}", alert.name),
+            alerts => format!("kk: {} price alerts", alerts.len()),
+        };
+        let text = alerts.iter().map(Alert::text).collect::<Vec<_>>().join("\n\n");
+        Self { title, text, alerts, html: None }
+    }
+
+    /// A sample alert sent by `kk notify test`
+    pub fn test() -> Self {
+        let history = vec![549.99, 539.99, 539.99, 499.99, 479.99, 449.99];
+        let mut message = Self::alerts(vec![Alert {
+            product_id: 11_406_755,
+            name: "Sony PlayStation 5 Slim".to_string(),
+            reason: "Test notification".to_string(),
+            rule: None,
+            old_price: Some(549.99),
+            new_price: 449.99,
+            discount: Some(18),
+            url: web_url("/p/11406755/sony-playstation-5-slim"),
+            sparkline: Some(chart::sparkline(&history)),
+            history,
+            exec: None,
+        }]);
+        message.title = "kk: test notification".to_string();
+        message
+    }
+}
+
+impl Target {
+    /// Deliver `message`, retrying network errors, 429s and server errors
+    pub async fn send(&self, http: &reqwest::Client, message: &Message) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.channel.deliver(http, message).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.retries && e.retryable => {
+                    let delay = retry_delay(attempt);
+                    tracing::debug!("Delivery failed, retrying in {delay:?}: {:#}", e.error);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.error),
+            }
+        }
+    }
+}
+
+/// Backoff before retry `attempt + 1`, capped at [`MAX_RETRY_DELAY`]
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_DELAY.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RETRY_DELAY)
+}
+
+impl Channel {
+    async fn deliver(&self, http: &reqwest::Client, message: &Message) -> Result<(), SendError> {
+        let (hook, url, body) = match self {
+            Self::Email(email) => return email.send(message).await,
+            Self::Webhook(hook) => (hook, hook.url.clone(), serde_json::json!(message)),
+            Self::Slack(hook) => (hook, hook.url.clone(), webhook::slack(message)),
+            Self::Discord(hook) => (hook, hook.url.clone(), webhook::discord(message)),
+            Self::Ntfy(hook) => {
+                let (server, body) = webhook::ntfy(&hook.url, message)
+                    .map_err(|error| SendError { error, retryable: false })?;
+                (hook, server, body)
+            }
+            Self::Gotify(hook) => (hook, hook.url.clone(), webhook::gotify(message)),
+        };
+        hook.post(http, &url, &body).await
+    }
+}
+
+/// A failed delivery attempt
+struct SendError {
+    error: anyhow::Error,
+    /// Whether trying again may help
+    retryable: bool,
+}
+
+/// HTTP client for notification targets
+pub fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("kuantokusta-cli/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(15))
+        .build()
+        .context("Failed to create HTTP client")
+}
+
+/// The target called `name`
+pub fn target<'a>(targets: &'a BTreeMap<String, Target>, name: &str) -> Result<&'a Target> {
+    if let Some(target) = targets.get(name) {
+        return Ok(target);
+    }
+    if targets.is_empty() {
+        anyhow::bail!("No notification targets configured; add one under [notify.{name}]");
+    }
+    let names: Vec<&str> = targets.keys().map(String::as_str).collect();
+    anyhow::bail!("Unknown notification target `{name}` (configured: {})", names.join(", "))
+}
+
+/// Send `message` to the targets called `names`; every target is tried, and
+/// the error lists the ones that failed
+pub async fn send_to(
+    targets: &BTreeMap<String, Target>,
+    names: &[String],
+    message: &Message,
+) -> Result<()> {
+    let resolved = names
+        .iter()
+        .map(|name| target(targets, name).map(|t| (name, t)))
+        .collect::<Result<Vec<_>>>()?;
+    let http = http_client()?;
+    let mut failed = Vec::new();
+    for (name, target) in resolved {
+        if let Err(e) = target.send(&http, message).await {
+            tracing::error!("Failed to notify `{name}`: {e:#}");
+            failed.push(format!("`{name}`"));
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("Failed to notify {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// Send `alerts` to every target that takes alerts, adding a history sparkline
+/// to each; failures are logged and don't stop the other targets
+pub async fn notify_alerts(
+    client: &KuantoKustaClient,
+    targets: &BTreeMap<String, Target>,
+    mut alerts: Vec<Alert>,
+) {
+    if alerts.is_empty() || !targets.values().any(|t| t.alerts) {
+        return;
+    }
+    for alert in &mut alerts {
+        match client.price_history(alert.product_id, SPARKLINE_DAYS).await {
+            Ok(history) if !history.data.is_empty() => {
+                alert.history = history.data.iter().map(|p| p.min).collect();
+                alert.sparkline = Some(chart::sparkline(&alert.history));
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("No sparkline for {}: {e:#}", alert.product_id),
+        }
+    }
+
+    let http = match http_client() {
+        Ok(http) => http,
+        Err(e) => {
+            tracing::error!("{e:#}");
+            return;
+        }
+    };
+    let message = Message::alerts(alerts);
+    for (name, target) in targets.iter().filter(|(_, t)| t.alerts) {
+        if let Err(e) = target.send(&http, &message).await {
+            tracing::error!("Failed to notify `{name}`: {e:#}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use chrono::Utc;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(retry_delay(0), Duration::from_millis(500));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(7), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    fn report() -> CheckReport {
+        let change = |kind, alert| Change { product_id: 1, label: "PS5".to_string(), kind, alert };
+        CheckReport {
+            checked_at: Utc::now(),
+            checked: 1,
+            failed: vec![],
+            changes: vec![
+                change(ChangeKind::PriceDrop { from: 500.0, to: 449.99, change_pct: -10.0 }, false),
+                change(ChangeKind::TargetReached { target: 450.0, price: 449.99 }, true),
+            ],
+        }
+    }
+
+    #[test]
+    fn builds_alerts_from_checks() {
+        let product: Product = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "PlayStation 5", "priceMin": 449.99, "totalOffers": 12,
+            "url": "/p/1/ps5", "badges": { "discountPercentage": 10 }, "tags": {}
+        }))
+        .unwrap();
+
+        let alerts = Alert::from_check(&report(), &[&product]);
+        assert_eq!(alerts.len(), 1);
+        let alert = &alerts[0];
+        assert_eq!(alert.reason, "Target 450.00€ reached");
+        assert_eq!(alert.url, "https://www.kuantokusta.pt/p/1/ps5");
+        assert_eq!(alert.prices(), "500.00€ → 449.99€ · -10%");
+        assert!(Alert::from_check(&report(), &[]).is_empty());
+
+        let message = Message::alerts(alerts);
+        assert_eq!(message.title, "kk: PS5");
+        assert_eq!(
+            message.text,
+            "PS5: Target 450.00€ reached\n500.00€ → 449.99€ · -10%\nhttps://www.kuantokusta.pt/p/1/ps5"
+        );
+    }
+
+    #[test]
+    fn takes_exec_from_fired_rule() {
+        let deal: Deal = serde_json::from_value(serde_json::json!({
+            "id": 1, "name": "PS5", "priceMin": 449.99, "url": "/p/1/ps5"
+        }))
+        .unwrap();
+        let mut report = report();
+        report.changes[1].kind =
+            ChangeKind::RuleMatched { rule: "cheap".to_string(), price: 449.99 };
+        let rules: Vec<Rule> = toml::from_str::<crate::rules::AlertsConfig>(
+            "[[rules]]\nwhen = \"price < 500\"\nexec = \"a\"\n\
+             [[rules]]\nname = \"cheap\"\nwhen = \"price < 450\"\nexec = \"notify-send kk\"\n",
+        )
+        .unwrap()
+        .rules;
+
+        let mut alert = Alert::from_deals(&report, &[deal]).remove(0);
+        assert_eq!(alert.rule.as_deref(), Some("cheap"));
+        alert.set_exec(&rules);
+        assert_eq!(alert.exec.as_deref(), Some("notify-send kk"));
+        alert.set_exec(&rules[..1]);
+        assert_eq!(alert.exec, None);
+    }
+
+    #[test]
+    fn parses_targets() {
+        let config: Config = toml::from_str(
+            "[notify.team]\ntype = \"slack\"\nurl = \"https://hooks.slack.com/services/x\"\n\
+             [notify.phone]\ntype = \"ntfy\"\nurl = \"https://ntfy.sh/kk\"\nalerts = false\nretries = 0\n",
+        )
+        .unwrap();
+        let team = &config.notify["team"];
+        assert!(matches!(team.channel, Channel::Slack(_)));
+        assert!(team.alerts);
+        assert_eq!(team.retries, 3);
+        assert!(!config.notify["phone"].alerts);
+
+        assert!(toml::from_str::<Config>("[notify.x]\ntype = \"pager\"\nurl = \"u\"\n").is_err());
+    }
+}
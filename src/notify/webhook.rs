@@ -0,0 +1,279 @@
+//! HTTP targets: generic JSON webhooks, Slack, Discord, ntfy and Gotify
+
+use super::{Alert, Message, SendError};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Discord rejects messages with more embeds than this
+const DISCORD_MAX_EMBEDS: usize = 10;
+
+/// Slack rejects messages with more blocks than this
+const SLACK_MAX_BLOCKS: usize = 50;
+
+/// An HTTP endpoint taking a JSON POST
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// Extra request headers, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Webhook {
+    pub(super) async fn post(
+        &self,
+        http: &reqwest::Client,
+        url: &str,
+        body: &Value,
+    ) -> Result<(), SendError> {
+        let mut request = http.post(url).json(body);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(|e| SendError {
+            error: anyhow::Error::new(e).context(format!("Failed to reach {}", redact(url))),
+            retryable: true,
+        })?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let detail = response.text().await.unwrap_or_default();
+        Err(SendError {
+            error: anyhow::anyhow!(
+                "{} answered {status}: {}",
+                redact(url),
+                detail.trim().chars().take(200).collect::<String>()
+            ),
+            retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        })
+    }
+}
+
+/// Hide the path and query of a URL, which often hold a secret token
+fn redact(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|host| format!("{}://{host}/…", u.scheme())))
+        .unwrap_or_else(|| "the webhook".to_string())
+}
+
+pub(super) fn slack(message: &Message) -> Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": message.title },
+    })];
+    if message.alerts.is_empty() {
+        blocks
+            .push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": message.text } }));
+    }
+    // Keep one block free for the "+N more" line when they don't all fit
+    let shown = if message.alerts.len() < SLACK_MAX_BLOCKS {
+        message.alerts.len()
+    } else {
+        SLACK_MAX_BLOCKS - 2
+    };
+    for alert in &message.alerts[..shown] {
+        let mut text = format!(
+            "*<{}|{}>*\n{}\n{}",
+            slack_escape(&alert.url),
+            slack_escape(&alert.name),
+            slack_escape(&alert.reason),
+            slack_prices(alert)
+        );
+        if let Some(sparkline) = &alert.sparkline {
+            text.push_str(&format!("\n`{sparkline}`"));
+        }
+        blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } }));
+    }
+    if let Some(more) = more(message.alerts.len(), shown) {
+        blocks.push(json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": more }] }));
+    }
+    json!({ "text": message.title, "blocks": blocks })
+}
+
+/// Escape the characters Slack treats as control sequences in mrkdwn
+fn slack_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// The line standing in for the alerts left out of a capped message
+fn more(total: usize, shown: usize) -> Option<String> {
+    (total > shown).then(|| format!("+{} more", total - shown))
+}
+
+/// Prices with the old one struck through, e.g. `~699.99€~ → *449.99€*`
+fn slack_prices(alert: &Alert) -> String {
+    let mut out = alert.old_price.map_or_else(
+        || format!("*{:.2}€*", alert.new_price),
+        |old| format!("~{old:.2}€~ → *{:.2}€*", alert.new_price),
+    );
+    if let Some(discount) = alert.discount {
+        out.push_str(&format!(" · -{discount}%"));
+    }
+    out
+}
+
+pub(super) fn discord(message: &Message) -> Value {
+    let embeds: Vec<Value> = message
+        .alerts
+        .iter()
+        .take(DISCORD_MAX_EMBEDS)
+        .map(|alert| {
+            let mut description = format!("{}\n**{}**", alert.reason, alert.prices());
+            if let Some(sparkline) = &alert.sparkline {
+                description.push_str(&format!("\n`{sparkline}`"));
+            }
+            json!({
+                "title": alert.name,
+                "url": alert.url,
+                "description": description,
+                "color": 0x002e_cc71,
+            })
+        })
+        .collect();
+    if embeds.is_empty() {
+        json!({ "content": format!("**{}**\n{}", message.title, message.text) })
+    } else if let Some(more) = more(message.alerts.len(), embeds.len()) {
+        json!({ "content": format!("{}\n{more}", message.title), "embeds": embeds })
+    } else {
+        json!({ "content": message.title, "embeds": embeds })
+    }
+}
+
+/// ntfy takes JSON on the server root, with the topic in the body
+pub(super) fn ntfy(topic_url: &str, message: &Message) -> Result<(String, Value)> {
+    let mut url = reqwest::Url::parse(topic_url)
+        .with_context(|| format!("Invalid ntfy topic URL `{topic_url}`"))?;
+    let topic = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|topic| !topic.is_empty())
+        .map(str::to_string)
+        .with_context(|| format!("No topic in ntfy URL `{topic_url}`"))?;
+    url.path_segments_mut()
+        .map_err(|()| anyhow::anyhow!("Invalid ntfy topic URL `{topic_url}`"))?
+        .pop();
+
+    let mut body = json!({
+        "topic": topic,
+        "title": message.title,
+        "message": message.text,
+        "tags": ["moneybag"],
+    });
+    if let Some(alert) = message.alerts.first() {
+        body["click"] = json!(alert.url);
+    }
+    Ok((url.to_string(), body))
+}
+
+pub(super) fn gotify(message: &Message) -> Value {
+    let mut body = json!({ "title": message.title, "message": message.text, "priority": 5 });
+    if let Some(alert) = message.alerts.first() {
+        body["extras"] = json!({ "client::notification": { "click": { "url": alert.url } } });
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Target;
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn target(kind: &str, url: &str, retries: u32) -> Target {
+        toml::from_str(&format!(
+            "type = \"{kind}\"\nurl = \"{url}\"\nretries = {retries}\nheaders = {{ X-Key = \"s3cret\" }}\n"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn payloads() {
+        let message = Message::test();
+        let alert = &message.alerts[0];
+
+        let body = slack(&message);
+        assert_eq!(body["text"], "kk: test notification");
+        let section = body["blocks"][1]["text"]["text"].as_str().unwrap();
+        assert!(section.starts_with(&format!("*<{}|Sony PlayStation 5 Slim>*", alert.url)));
+        assert!(section.contains("~549.99€~ → *449.99€* · -18%"));
+
+        let body = discord(&message);
+        assert_eq!(body["embeds"][0]["url"], alert.url.as_str());
+        assert_eq!(body["content"], "kk: test notification");
+
+        let (url, body) = ntfy("https://ntfy.sh/kk-alerts", &message).unwrap();
+        assert_eq!(url, "https://ntfy.sh/");
+        assert_eq!(body["topic"], "kk-alerts");
+        assert_eq!(body["click"], alert.url.as_str());
+        assert!(ntfy("https://ntfy.sh/", &message).is_err());
+
+        let body = gotify(&Message { alerts: vec![], ..message });
+        assert_eq!(body["message"], alert.text().as_str());
+        assert!(body.get("extras").is_none());
+    }
+
+    #[test]
+    fn caps_chat_payloads() {
+        let mut message = Message::test();
+        message.alerts[0].name = "Fish & Chips <XL>".to_string();
+        let alerts = vec![message.alerts[0].clone(); 60];
+        let message = Message { alerts, ..message };
+
+        let body = slack(&message);
+        let blocks = body["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), SLACK_MAX_BLOCKS);
+        assert!(blocks[1]["text"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("|Fish &amp; Chips &lt;XL&gt;>"));
+        assert_eq!(blocks[49]["elements"][0]["text"], "+12 more");
+
+        let body = discord(&message);
+        assert_eq!(body["embeds"].as_array().unwrap().len(), DISCORD_MAX_EMBEDS);
+        assert_eq!(body["content"], "kk: test notification\n+50 more");
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header("X-Key", "s3cret"))
+            .and(body_partial_json(json!({ "title": "kk: test notification" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = reqwest::Client::new();
+        let hook = target("webhook", &format!("{}/hook", server.uri()), 1);
+        hook.send(&http, &Message::test()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("no such hook"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = reqwest::Client::new();
+        let hook = target("slack", &format!("{}/services/secret", server.uri()), 3);
+        let err = hook.send(&http, &Message::test()).await.unwrap_err().to_string();
+        assert!(err.ends_with("answered 404 Not Found: no such hook"));
+        assert!(!err.contains("secret"));
+    }
+}
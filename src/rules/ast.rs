@@ -0,0 +1,204 @@
+//! Syntax tree of rule conditions
+
+use std::fmt;
+
+/// A boolean condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+    Compare { left: Operand, op: CmpOp, right: Operand },
+}
+
+/// One side of a comparison
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Number(f64),
+    Metric(Metric),
+    /// Aggregate of the lowest daily price over the last `days` days, e.g. `90d min`
+    Window {
+        days: u32,
+        agg: Agg,
+    },
+}
+
+/// A value read from the product, deal or previous check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Current lowest price
+    Price,
+    /// Lowest price at the last check
+    Previous,
+    /// Percentage drop since the last check (negative when the price went up)
+    Drop,
+    /// Number of stores selling the product
+    Stores,
+    /// Discount percentage
+    Discount,
+    /// Average rating
+    Rating,
+    /// Number of reviews
+    Reviews,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Agg {
+    Min,
+    Max,
+    Avg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Metric {
+    pub const NAMES: &'static [&'static str] =
+        &["price", "previous", "drop", "stores", "discount", "rating", "reviews"];
+
+    /// Metric named `name`, accepting the API field names as aliases
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "price" | "price_min" => Self::Price,
+            "previous" | "last" => Self::Previous,
+            "drop" => Self::Drop,
+            "stores" | "offers" | "total_offers" => Self::Stores,
+            "discount" => Self::Discount,
+            "rating" => Self::Rating,
+            "reviews" => Self::Reviews,
+            _ => return None,
+        })
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Price => "price",
+            Self::Previous => "previous",
+            Self::Drop => "drop",
+            Self::Stores => "stores",
+            Self::Discount => "discount",
+            Self::Rating => "rating",
+            Self::Reviews => "reviews",
+        }
+    }
+}
+
+impl Agg {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "min" | "low" => Self::Min,
+            "max" | "high" => Self::Max,
+            "avg" | "mean" => Self::Avg,
+            _ => return None,
+        })
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Avg => "avg",
+        }
+    }
+}
+
+impl CmpOp {
+    pub fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+            Self::Eq => (left - right).abs() < 1e-9,
+            Self::Ne => (left - right).abs() >= 1e-9,
+        }
+    }
+
+    const fn symbol(self) -> &'static str {
+        match self {
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+        }
+    }
+}
+
+impl Expr {
+    /// Longest price history window the condition reads, if any
+    pub fn history_days(&self) -> Option<u32> {
+        match self {
+            Self::And(a, b) | Self::Or(a, b) => a.history_days().max(b.history_days()),
+            Self::Not(e) => e.history_days(),
+            Self::Compare { left, right, .. } => left.history_days().max(right.history_days()),
+        }
+    }
+
+    /// Whether the condition reads `metric`
+    pub fn uses(&self, metric: Metric) -> bool {
+        match self {
+            Self::And(a, b) | Self::Or(a, b) => a.uses(metric) || b.uses(metric),
+            Self::Not(e) => e.uses(metric),
+            Self::Compare { left, right, .. } => {
+                [left, right].iter().any(|o| **o == Operand::Metric(metric))
+            }
+        }
+    }
+}
+
+impl Operand {
+    const fn history_days(&self) -> Option<u32> {
+        match self {
+            Self::Window { days, .. } => Some(*days),
+            Self::Number(_) | Self::Metric(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Parenthesise `or` inside `and`, and anything compound under `not`
+        let grouped = |e: &Self, f: &mut fmt::Formatter<'_>, when: bool| {
+            if when {
+                write!(f, "({e})")
+            } else {
+                write!(f, "{e}")
+            }
+        };
+        match self {
+            Self::And(a, b) => {
+                grouped(a, f, matches!(**a, Self::Or(..)))?;
+                f.write_str(" and ")?;
+                grouped(b, f, matches!(**b, Self::Or(..)))
+            }
+            Self::Or(a, b) => {
+                write!(f, "{a} or ")?;
+                grouped(b, f, matches!(**b, Self::Or(..)))
+            }
+            Self::Not(e) => {
+                f.write_str("not ")?;
+                grouped(e, f, !matches!(**e, Self::Compare { .. }))
+            }
+            Self::Compare { left, op, right } => write!(f, "{left} {} {right}", op.symbol()),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Metric(m) => f.write_str(m.name()),
+            Self::Window { days, agg } => write!(f, "{days}d {}", agg.name()),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Evaluation of rule conditions over product, deal and history data
+
+use super::ast::{Agg, Expr, Metric, Operand};
+use crate::api::{Deal, PriceHistory, Product};
+use chrono::{Days, NaiveDate};
+
+/// The values a condition can read
+///
+/// Anything unknown (no previous check, no rating, no history) makes the
+/// comparisons reading it unknown; a condition that ends up unknown doesn't match.
+#[derive(Debug, Clone, Default)]
+pub struct Facts<'a> {
+    pub price: Option<f64>,
+    pub previous: Option<f64>,
+    pub stores: Option<u32>,
+    pub discount: Option<f64>,
+    pub rating: Option<f64>,
+    pub reviews: Option<u32>,
+    pub history: Option<&'a PriceHistory>,
+    /// Day the history windows end on
+    pub today: Option<NaiveDate>,
+}
+
+impl<'a> Facts<'a> {
+    pub fn from_product(product: &Product) -> Self {
+        Self {
+            price: Some(product.price_min),
+            stores: Some(product.total_offers),
+            discount: product.discount().map(f64::from),
+            rating: product.rating.as_ref().map(|r| f64::from(r.rating_count)),
+            reviews: product.rating.as_ref().map(|r| r.reviews_count),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_deal(deal: &Deal) -> Self {
+        Self {
+            price: Some(deal.price_min),
+            stores: Some(deal.total_offers),
+            discount: deal.discount().map(f64::from),
+            rating: deal.rating.as_ref().map(|r| f64::from(r.rating_count)),
+            reviews: deal.rating.as_ref().map(|r| r.reviews_count),
+            ..Self::default()
+        }
+    }
+
+    /// Use `history` for windows ending on `today`
+    #[must_use]
+    pub const fn with_history(mut self, history: &'a PriceHistory, today: NaiveDate) -> Self {
+        self.history = Some(history);
+        self.today = Some(today);
+        self
+    }
+
+    /// Price at the last check
+    #[must_use]
+    pub const fn with_previous(mut self, previous: Option<f64>) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    fn metric(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Price => self.price,
+            Metric::Previous => self.previous,
+            Metric::Drop => match (self.previous, self.price) {
+                (Some(prev), Some(price)) if prev > 0.0 => Some((prev - price) / prev * 100.0),
+                _ => None,
+            },
+            Metric::Stores => self.stores.map(f64::from),
+            Metric::Discount => self.discount,
+            Metric::Rating => self.rating,
+            Metric::Reviews => self.reviews.map(f64::from),
+        }
+    }
+
+    /// Aggregate of the daily lowest price over the `days` days ending today
+    fn window(&self, days: u32, agg: Agg) -> Option<f64> {
+        let (history, today) = (self.history?, self.today?);
+        let start = today.checked_sub_days(Days::new(u64::from(days)))?;
+        let prices: Vec<f64> = history
+            .data
+            .iter()
+            .filter(|p| p.date > start && p.date <= today)
+            .map(|p| p.min)
+            .collect();
+        if prices.is_empty() {
+            return None;
+        }
+        Some(match agg {
+            Agg::Min => prices.iter().copied().fold(f64::INFINITY, f64::min),
+            Agg::Max => prices.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Agg::Avg => prices.iter().sum::<f64>() / prices.len() as f64,
+        })
+    }
+
+    fn operand(&self, operand: &Operand) -> Option<f64> {
+        match *operand {
+            Operand::Number(n) => Some(n),
+            Operand::Metric(m) => self.metric(m),
+            Operand::Window { days, agg } => self.window(days, agg),
+        }
+    }
+}
+
+impl Expr {
+    /// Whether the condition holds; unknown values never match
+    pub fn matches(&self, facts: &Facts) -> bool {
+        self.eval(facts).unwrap_or(false)
+    }
+
+    /// Three-valued evaluation: `None` when the outcome depends on an unknown value
+    fn eval(&self, facts: &Facts) -> Option<bool> {
+        match self {
+            Self::And(a, b) => match (a.eval(facts), b.eval(facts)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Self::Or(a, b) => match (a.eval(facts), b.eval(facts)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Self::Not(e) => e.eval(facts).map(|v| !v),
+            Self::Compare { left, op, right } => {
+                Some(op.apply(facts.operand(left)?, facts.operand(right)?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Badges, PricePoint, Rating, Tags};
+    use crate::rules::parse;
+
+    fn product() -> Product {
+        Product {
+            badges: Badges { discount_percentage: Some(25), ..Badges::default() },
+            rating: Some(Rating { rating_count: 4.6, reviews_count: 30 }),
+            ..Product::fixture(1, 600.0)
+                .with_name("PS5")
+                .with_brand("Sony")
+                .with_category("Consoles")
+                .with_offers(12)
+                .with_url("/p/1/ps5")
+        }
+    }
+
+    fn matches(source: &str, facts: &Facts) -> bool {
+        parse(source).unwrap().matches(facts)
+    }
+
+    #[test]
+    fn product_metrics() {
+        let facts = Facts::from_product(&product());
+        assert!(matches("price < 650", &facts));
+        assert!(matches("stores >= 10 and discount >= 20", &facts));
+        assert!(matches("rating >= 4.5 and reviews > 10", &facts));
+        assert!(!matches("price < 650 and stores > 20", &facts));
+
+        // No previous check: `drop` is unknown, and so is its negation
+        assert!(!matches("drop >= 10%", &facts));
+        assert!(!matches("not drop >= 10%", &facts));
+        assert!(matches("drop >= 10% or price < 650", &facts));
+
+        let facts = facts.with_previous(Some(700.0));
+        assert!(matches("drop >= 10% since last check", &facts));
+        assert!(!matches("drop >= 15%", &facts));
+    }
+
+    #[test]
+    fn deal_metrics() {
+        let p = product();
+        let deal = Deal {
+            id: p.id,
+            name: p.name,
+            images: p.images,
+            price_min: p.price_min,
+            total_offers: p.total_offers,
+            url: p.url,
+            brand: p.brand,
+            badges: Badges::default(),
+            rating: None,
+            tags: Tags { discount_percentage: Some(30), ..Tags::default() },
+        };
+        let facts = Facts::from_deal(&deal);
+        assert!(matches("discount >= 30 and stores >= 10", &facts));
+        assert!(!matches("rating >= 1", &facts));
+    }
+
+    #[test]
+    fn history_windows() {
+        let today: NaiveDate = "2024-06-30".parse().unwrap();
+        let point =
+            |date: &str, min: f64| PricePoint { date: date.parse().unwrap(), avg: min + 50.0, min };
+        let history = PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: vec![
+                point("2024-03-01", 550.0),
+                point("2024-06-01", 610.0),
+                point("2024-06-29", 620.0),
+            ],
+        };
+
+        let facts = Facts::from_product(&product());
+        assert!(!matches("price <= 90d min", &facts));
+
+        let facts = facts.with_history(&history, today);
+        assert!(matches("price <= 90d min", &facts));
+        assert_eq!(facts.window(30, Agg::Min), Some(610.0));
+        assert!(matches("price < 30d avg and 7d max == 620", &facts));
+        assert!(!matches("price < 1d min", &facts));
+    }
+}
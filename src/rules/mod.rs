@@ -0,0 +1,303 @@
+//! Alert rules: conditions like `price <= 90d min` with cooldowns and quiet hours
+//!
+//! Rules are defined globally in the `[alerts]` section of the config file, or
+//! per watchlist entry. A rule fires whenever its condition holds, at most once
+//! per cooldown; matches during its quiet hours fire once they are over.
+
+pub mod ast;
+pub mod eval;
+mod parser;
+
+pub use ast::{Agg, CmpOp, Expr, Metric, Operand};
+pub use eval::Facts;
+pub use parser::{parse, ParseError};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Cooldown of rules that don't set one (and no `[alerts]` default)
+pub const DEFAULT_COOLDOWN: TimeDelta = TimeDelta::hours(24);
+
+/// A parsed condition, kept with its source text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub const fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ParseError;
+
+    fn from_str(source: &str) -> Result<Self, ParseError> {
+        Ok(Self { source: source.trim().to_string(), expr: parse(source)? })
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = ParseError;
+
+    fn try_from(source: String) -> Result<Self, ParseError> {
+        source.parse()
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+/// A duration written as `<number><unit>`, with units `s`, `m`, `h`, `d` or `w`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Interval(pub TimeDelta);
+
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let n: i64 = number
+            .parse()
+            .with_context(|| format!("Invalid duration `{s}`; expected e.g. 30m, 6h or 2d"))?;
+        let delta = match unit.trim() {
+            "s" => TimeDelta::seconds(n),
+            "m" | "min" => TimeDelta::minutes(n),
+            "h" => TimeDelta::hours(n),
+            "d" => TimeDelta::days(n),
+            "w" => TimeDelta::weeks(n),
+            "" if n == 0 => TimeDelta::zero(),
+            _ => anyhow::bail!("Invalid duration unit in `{s}`; expected s, m, h, d or w"),
+        };
+        Ok(Self(delta))
+    }
+}
+
+impl TryFrom<String> for Interval {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.to_string()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.num_seconds();
+        let units = [(604_800, "w"), (86_400, "d"), (3_600, "h"), (60, "m")];
+        match units.iter().find(|(size, _)| secs != 0 && secs % size == 0) {
+            Some((size, unit)) => write!(f, "{}{unit}", secs / size),
+            None => write!(f, "{secs}s"),
+        }
+    }
+}
+
+/// A daily time range in local time, e.g. `22:00-07:30`; may wrap past midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let time = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                .with_context(|| format!("Invalid quiet hours `{s}`; expected HH:MM-HH:MM"))
+        };
+        let (start, end) = s
+            .split_once('-')
+            .with_context(|| format!("Invalid quiet hours `{s}`; expected HH:MM-HH:MM"))?;
+        Ok(Self { start: time(start)?, end: time(end)? })
+    }
+}
+
+impl TryFrom<String> for QuietHours {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<QuietHours> for String {
+    fn from(hours: QuietHours) -> Self {
+        format!("{}-{}", hours.start.format("%H:%M"), hours.end.format("%H:%M"))
+    }
+}
+
+/// An alert rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub when: Condition,
+    /// Minimum time between two alerts of this rule for the same product
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<Interval>,
+    /// Overrides the global quiet hours
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// Shell command run when the rule fires (see `[hooks]`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<String>,
+}
+
+impl Rule {
+    pub const fn new(when: Condition) -> Self {
+        Self { name: None, when, cooldown: None, quiet_hours: None, exec: None }
+    }
+
+    /// The rule's name, or its condition if unnamed
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or_else(|| self.when.source())
+    }
+}
+
+/// A rule with where it's defined: `global`, or the ID of the watched product
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopedRule {
+    pub scope: String,
+    #[serde(flatten)]
+    pub rule: Rule,
+}
+
+/// The `[alerts]` config section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// Rules applied to every watched product
+    pub rules: Vec<Rule>,
+    /// Default cooldown (24h when unset)
+    pub cooldown: Option<Interval>,
+    /// No rule alerts during these hours
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Whether a matching rule may fire now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    Fire,
+    Cooldown,
+    Quiet,
+}
+
+impl AlertsConfig {
+    /// Gate a matching `rule` that last fired at `last_fired`
+    pub fn gate(
+        &self,
+        rule: &Rule,
+        last_fired: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        local_time: NaiveTime,
+    ) -> Gate {
+        if rule.quiet_hours.or(self.quiet_hours).is_some_and(|q| q.contains(local_time)) {
+            return Gate::Quiet;
+        }
+        let cooldown = rule.cooldown.or(self.cooldown).map_or(DEFAULT_COOLDOWN, |c| c.0);
+        if last_fired.is_some_and(|last| now - last < cooldown) {
+            return Gate::Cooldown;
+        }
+        Gate::Fire
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_and_quiet_hours() {
+        assert_eq!("30m".parse::<Interval>().unwrap().0, TimeDelta::minutes(30));
+        assert_eq!("2d".parse::<Interval>().unwrap().to_string(), "2d");
+        assert_eq!(Interval(TimeDelta::minutes(90)).to_string(), "90m");
+        assert!("6 hours".parse::<Interval>().is_err());
+        assert!("h".parse::<Interval>().is_err());
+
+        let night: QuietHours = "22:00-07:30".parse().unwrap();
+        let at = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+        assert!(night.contains(at("23:15")));
+        assert!(night.contains(at("07:00")));
+        assert!(!night.contains(at("07:30")));
+        assert!(!night.contains(at("12:00")));
+        let lunch: QuietHours = "12:00-13:00".parse().unwrap();
+        assert!(lunch.contains(at("12:30")) && !lunch.contains(at("22:00")));
+        assert!("22:00".parse::<QuietHours>().is_err());
+    }
+
+    #[test]
+    fn gating() {
+        let now: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+        let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+        let mut rule = Rule::new("price < 10".parse().unwrap());
+        let alerts =
+            AlertsConfig { cooldown: Some("6h".parse().unwrap()), ..AlertsConfig::default() };
+
+        assert_eq!(alerts.gate(&rule, None, now, noon), Gate::Fire);
+        assert_eq!(alerts.gate(&rule, Some(now - TimeDelta::hours(5)), now, noon), Gate::Cooldown);
+        assert_eq!(alerts.gate(&rule, Some(now - TimeDelta::hours(6)), now, noon), Gate::Fire);
+
+        rule.cooldown = Some("1h".parse().unwrap());
+        assert_eq!(alerts.gate(&rule, Some(now - TimeDelta::hours(5)), now, noon), Gate::Fire);
+        assert_eq!(
+            AlertsConfig::default().gate(&rule, Some(now - TimeDelta::hours(5)), now, noon),
+            Gate::Fire
+        );
+
+        rule.quiet_hours = Some("11:00-13:00".parse().unwrap());
+        assert_eq!(alerts.gate(&rule, None, now, noon), Gate::Quiet);
+    }
+
+    #[test]
+    fn rules_from_toml() {
+        let alerts: AlertsConfig = toml::from_str(
+            "quiet_hours = \"22:00-08:00\"\n\
+             [[rules]]\nname = \"big drop\"\nwhen = \"drop >= 10% since last check\"\ncooldown = \"6h\"\n\
+             [[rules]]\nwhen = \"price <= 90d min\"\n",
+        )
+        .unwrap();
+        assert_eq!(alerts.rules.len(), 2);
+        assert_eq!(alerts.rules[0].label(), "big drop");
+        assert_eq!(alerts.rules[1].label(), "price <= 90d min");
+        assert_eq!(alerts.rules[1].when.expr().history_days(), Some(90));
+        assert_eq!(toml::to_string(&alerts.rules[0]).unwrap().lines().count(), 3);
+
+        let err = toml::from_str::<AlertsConfig>("[[rules]]\nwhen = \"price =< 5\"\n").unwrap_err();
+        assert!(err.to_string().contains("expected a value or metric"));
+    }
+}
@@ -0,0 +1,380 @@
+//! Lexer and recursive-descent parser for rule conditions
+//!
+//! ```text
+//! expr    := and ("or" and)*
+//! and     := unary ("and" unary)*
+//! unary   := "not" unary | "(" expr ")" | compare
+//! compare := operand op operand ["since" "last" "check"]
+//! operand := number ["%" | "€"] | <N>"d" ("min" | "max" | "avg") | metric
+//! ```
+
+use super::ast::{Agg, CmpOp, Expr, Metric, Operand};
+use crate::api::HISTORY_WINDOWS;
+use std::fmt;
+
+/// A syntax error, pointing at the offending position of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub source: String,
+    /// Byte offset of the error
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.source[..self.position].chars().count();
+        write!(
+            f,
+            "{} (column {})\n  {}\n  {}^",
+            self.message,
+            column + 1,
+            self.source,
+            " ".repeat(column)
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    /// A number of days, e.g. `90d`
+    Days(u32),
+    Percent,
+    Ident(String),
+    Op(CmpOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "`{n}`"),
+            Self::Days(d) => write!(f, "`{d}d`"),
+            Self::Percent => f.write_str("`%`"),
+            Self::Ident(s) => write!(f, "`{s}`"),
+            Self::Op(_) => f.write_str("comparison operator"),
+            Self::And => f.write_str("`and`"),
+            Self::Or => f.write_str("`or`"),
+            Self::Not => f.write_str("`not`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+        }
+    }
+}
+
+/// Parse a rule condition
+pub fn parse(source: &str) -> Result<Expr, ParseError> {
+    let tokens = lex(source)?;
+    let mut parser = Parser { source, tokens, pos: 0 };
+    if parser.tokens.is_empty() {
+        return Err(parser.error_at(0, "empty condition"));
+    }
+    let expr = parser.expr()?;
+    if let Some((token, at)) = parser.peek() {
+        return Err(parser.error_at(at, format!("unexpected {token}; expected `and` or `or`")));
+    }
+    Ok(expr)
+}
+
+fn lex(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let error =
+        |position, message: String| ParseError { source: source.to_string(), position, message };
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            // The currency symbol is decoration: `650€` reads as `650`
+            '€' => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let text = &source[start..end];
+                let days_suffix = chars.peek().is_some_and(|&(_, c)| c == 'd')
+                    && !source[end + 1..].starts_with(|c: char| c.is_alphanumeric());
+                if days_suffix {
+                    chars.next();
+                    let days =
+                        text.parse::<u32>().ok().filter(|d| *d > 0).ok_or_else(|| {
+                            error(start, format!("invalid number of days `{text}d`"))
+                        })?;
+                    Token::Days(days)
+                } else {
+                    Token::Number(
+                        text.parse()
+                            .map_err(|_| error(start, format!("invalid number `{text}`")))?,
+                    )
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                match source[start..end].to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    word => Token::Ident(word.to_string()),
+                }
+            }
+            _ => {
+                chars.next();
+                let next = chars.peek().map(|&(_, c)| c);
+                let mut two = |token| {
+                    chars.next();
+                    token
+                };
+                match (c, next) {
+                    ('<', Some('=')) => two(Token::Op(CmpOp::Le)),
+                    ('>', Some('=')) => two(Token::Op(CmpOp::Ge)),
+                    ('=', Some('=')) => two(Token::Op(CmpOp::Eq)),
+                    ('!', Some('=')) => two(Token::Op(CmpOp::Ne)),
+                    ('&', Some('&')) => two(Token::And),
+                    ('|', Some('|')) => two(Token::Or),
+                    ('<', _) => Token::Op(CmpOp::Lt),
+                    ('>', _) => Token::Op(CmpOp::Gt),
+                    ('=', _) => Token::Op(CmpOp::Eq),
+                    ('!', _) => Token::Not,
+                    ('%', _) => Token::Percent,
+                    ('(', _) => Token::LParen,
+                    (')', _) => Token::RParen,
+                    _ => return Err(error(start, format!("unexpected character `{c}`"))),
+                }
+            }
+        };
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<(Token, usize)> {
+        self.tokens.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.pos).is_some_and(|(t, _)| t == token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error_at(&self, position: usize, message: impl Into<String>) -> ParseError {
+        ParseError { source: self.source.to_string(), position, message: message.into() }
+    }
+
+    /// Error about the next token, or the end of input
+    fn unexpected(&self, expected: &str) -> ParseError {
+        match self.peek() {
+            Some((token, at)) => self.error_at(at, format!("expected {expected}, found {token}")),
+            None => self.error_at(self.source.len(), format!("expected {expected}")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.and()?;
+        while self.eat(&Token::Or) {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.unary()?;
+        while self.eat(&Token::And) {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let expr = self.expr()?;
+            if !self.eat(&Token::RParen) {
+                return Err(self.unexpected("`)`"));
+            }
+            return Ok(expr);
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Expr, ParseError> {
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Some((Token::Op(op), _)) => {
+                self.pos += 1;
+                op
+            }
+            _ => return Err(self.unexpected("a comparison operator (<, <=, >, >=, ==, !=)")),
+        };
+        let right = self.operand()?;
+        let expr = Expr::Compare { left, op, right };
+
+        if let Some((Token::Ident(word), at)) = self.peek() {
+            if word == "since" {
+                self.pos += 1;
+                for expected in ["last", "check"] {
+                    match self.next() {
+                        Some((Token::Ident(w), _)) if w == expected => {}
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.unexpected("`since last check`"));
+                        }
+                    }
+                }
+                if !expr.uses(Metric::Drop) {
+                    return Err(self.error_at(at, "`since last check` only applies to `drop`"));
+                }
+            }
+        }
+        Ok(expr)
+    }
+
+    fn operand(&mut self) -> Result<Operand, ParseError> {
+        let Some((token, at)) = self.next() else {
+            return Err(self.error_at(self.source.len(), "expected a value or metric"));
+        };
+        match token {
+            Token::Number(n) => {
+                self.eat(&Token::Percent);
+                Ok(Operand::Number(n))
+            }
+            Token::Days(days) => {
+                let agg = match self.peek() {
+                    Some((Token::Ident(word), _)) => Agg::from_name(&word),
+                    _ => None,
+                };
+                let Some(agg) = agg else {
+                    return Err(self.unexpected(&format!("`min`, `max` or `avg` after `{days}d`")));
+                };
+                self.pos += 1;
+                let longest = HISTORY_WINDOWS[HISTORY_WINDOWS.len() - 1];
+                if days > longest {
+                    return Err(self.error_at(
+                        at,
+                        format!("`{days}d` is longer than the {longest} days of price history"),
+                    ));
+                }
+                Ok(Operand::Window { days, agg })
+            }
+            Token::Ident(word) => Metric::from_name(&word).map(Operand::Metric).ok_or_else(|| {
+                self.error_at(
+                    at,
+                    format!(
+                        "unknown metric `{word}`; expected one of {}, or a window like `90d min`",
+                        Metric::NAMES.join(", ")
+                    ),
+                )
+            }),
+            token => {
+                self.pos -= 1;
+                Err(self.error_at(at, format!("expected a value or metric, found {token}")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(source: &str) -> String {
+        parse(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_request_examples() {
+        assert_eq!(roundtrip("price < 650"), "price < 650");
+        assert_eq!(roundtrip("drop >= 10% since last check"), "drop >= 10");
+        assert_eq!(roundtrip("price <= 90d min"), "price <= 90d min");
+        assert_eq!(roundtrip("stores >= 10 and discount >= 20"), "stores >= 10 and discount >= 20");
+        assert_eq!(roundtrip("rating >= 4.5"), "rating >= 4.5");
+        assert_eq!(roundtrip("PRICE_MIN<650€"), "price < 650");
+    }
+
+    #[test]
+    fn precedence_and_grouping() {
+        let expr = parse("price < 1 or stores > 2 and rating >= 4").unwrap();
+        assert!(matches!(expr, Expr::Or(..)));
+        assert_eq!(
+            roundtrip("(price < 1 or stores > 2) and not rating >= 4"),
+            "(price < 1 or stores > 2) and not rating >= 4"
+        );
+        assert_eq!(
+            roundtrip("!(price < 1 && drop > 5) || offers != 3"),
+            "not (price < 1 and drop > 5) or stores != 3"
+        );
+        assert_eq!(parse("price <= 30d avg or price < 7d low").unwrap().history_days(), Some(30));
+    }
+
+    #[test]
+    fn reports_errors_with_position() {
+        let err = parse("price <> 650").unwrap_err();
+        assert_eq!(err.position, 7);
+        assert_eq!(err.message, "expected a value or metric, found comparison operator");
+        assert_eq!(
+            err.to_string(),
+            "expected a value or metric, found comparison operator (column 8)\n  price <> 650\n         ^"
+        );
+
+        let err = parse("cost < 650").unwrap_err();
+        assert_eq!(err.position, 0);
+        assert!(err.message.starts_with("unknown metric `cost`"));
+
+        assert_eq!(parse("price 650").unwrap_err().position, 6);
+        assert_eq!(parse("(price < 650").unwrap_err().message, "expected `)`");
+        assert_eq!(parse("price <= 90d").unwrap_err().position, 12);
+        let err = parse("price <= 180d min").unwrap_err();
+        assert_eq!(
+            (err.position, err.message.as_str()),
+            (9, "`180d` is longer than the 90 days of price history")
+        );
+        assert_eq!(parse("price < 650 since last check").unwrap_err().position, 12);
+        assert_eq!(parse("drop > 5 since tuesday").unwrap_err().position, 15);
+        assert_eq!(parse("price < 5 stores > 2").unwrap_err().position, 10);
+        assert_eq!(parse("price < 5 $").unwrap_err().message, "unexpected character `$`");
+        assert_eq!(parse("  ").unwrap_err().message, "empty condition");
+    }
+}
@@ -0,0 +1,285 @@
+//! Saved searches (`~/.config/kk/searches.toml`) and what changed between runs
+
+use crate::api::{web_url, Product};
+use crate::diff::{change_pct, KeyedDiff};
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A named search with its filters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    /// Results fetched per run, before the filters
+    #[serde(default = "default_max")]
+    pub max: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<f64>,
+    /// Only keep products whose brand contains this (case-insensitive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brand: Option<String>,
+    /// Only keep products whose category contains this (case-insensitive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    pub added: NaiveDate,
+}
+
+const fn default_max() -> usize {
+    20
+}
+
+impl SavedSearch {
+    /// Whether `product` passes the filters
+    pub fn matches(&self, product: &Product) -> bool {
+        let contains = |value: &str, filter: Option<&String>| {
+            filter.map_or(true, |f| value.to_lowercase().contains(&f.to_lowercase()))
+        };
+        self.min_price.map_or(true, |min| product.price_min >= min)
+            && self.max_price.map_or(true, |max| product.price_min <= max)
+            && contains(&product.brand, self.brand.as_ref())
+            && contains(&product.category, self.category.as_ref())
+    }
+
+    /// The filters in one line, e.g. `100.00-500.00€, brand msi`
+    pub fn filters(&self) -> String {
+        let mut parts = Vec::new();
+        match (self.min_price, self.max_price) {
+            (Some(min), Some(max)) => parts.push(format!("{min:.2}-{max:.2}€")),
+            (Some(min), None) => parts.push(format!("≥ {min:.2}€")),
+            (None, Some(max)) => parts.push(format!("≤ {max:.2}€")),
+            (None, None) => {}
+        }
+        if let Some(brand) = &self.brand {
+            parts.push(format!("brand {brand}"));
+        }
+        if let Some(category) = &self.category {
+            parts.push(format!("category {category}"));
+        }
+        parts.join(", ")
+    }
+}
+
+/// The saved searches file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearches {
+    #[serde(default, rename = "searches")]
+    pub entries: Vec<SavedSearch>,
+}
+
+impl SavedSearches {
+    /// Default saved searches location
+    pub fn default_path() -> PathBuf {
+        crate::paths::config_dir().join("searches.toml")
+    }
+
+    /// Load the saved searches, or none if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .with_context(|| format!("Invalid saved searches in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Save the searches
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let raw = toml::to_string_pretty(self).context("Failed to serialize saved searches")?;
+        crate::paths::write_atomic(path, raw)
+    }
+
+    /// The search called `name`, or an error naming it
+    pub fn get(&self, name: &str) -> Result<&SavedSearch> {
+        self.entries
+            .iter()
+            .find(|s| s.name == name)
+            .with_context(|| format!("No saved search named `{name}`"))
+    }
+
+    /// Add a search; fails if the name is taken
+    pub fn add(&mut self, search: SavedSearch) -> Result<()> {
+        if self.entries.iter().any(|s| s.name == search.name) {
+            anyhow::bail!("There already is a saved search named `{}`", search.name);
+        }
+        self.entries.push(search);
+        Ok(())
+    }
+
+    /// Remove a search
+    pub fn remove(&mut self, name: &str) -> Result<SavedSearch> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|s| s.name == name)
+            .with_context(|| format!("No saved search named `{name}`"))?;
+        Ok(self.entries.remove(idx))
+    }
+}
+
+/// A product in a search's results
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub product_id: u64,
+    pub name: String,
+    pub url: String,
+    pub price_min: f64,
+    pub total_offers: u32,
+}
+
+impl From<&Product> for SearchHit {
+    fn from(product: &Product) -> Self {
+        Self {
+            product_id: product.id,
+            name: product.name.clone(),
+            url: web_url(&product.url),
+            price_min: product.price_min,
+            total_offers: product.total_offers,
+        }
+    }
+}
+
+/// A product whose price changed between two runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitChange {
+    #[serde(flatten)]
+    pub hit: SearchHit,
+    pub old_price: f64,
+    pub change_pct: f64,
+}
+
+/// What changed in a saved search's results since its previous run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchDiff {
+    pub search: String,
+    pub results: usize,
+    /// Day of the run compared against; `None` on the first run
+    pub previous: Option<NaiveDate>,
+    pub new: Vec<SearchHit>,
+    pub removed: Vec<SearchHit>,
+    pub changed: Vec<HitChange>,
+}
+
+impl SearchDiff {
+    /// Compare `current` with the `previous` run's results
+    pub fn new(
+        search: &str,
+        previous: Option<(NaiveDate, Vec<Product>)>,
+        current: &[Product],
+    ) -> Self {
+        let mut diff = Self {
+            search: search.to_string(),
+            results: current.len(),
+            previous: previous.as_ref().map(|(date, _)| *date),
+            new: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        // The first run only records the results
+        let Some((_, previous)) = previous else {
+            return diff;
+        };
+        let keyed = KeyedDiff::new(&previous, current, |p| p.id, |p| p.price_min);
+        diff.new = keyed.added.into_iter().map(SearchHit::from).collect();
+        diff.removed = keyed.removed.into_iter().map(SearchHit::from).collect();
+        diff.changed = keyed
+            .repriced
+            .into_iter()
+            .map(|(product, old_price)| HitChange {
+                hit: product.into(),
+                old_price,
+                change_pct: change_pct(old_price, product.price_min),
+            })
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// e.g. "20 result(s), 2 new, 1 removed, 3 price change(s)"
+    pub fn summary(&self) -> String {
+        if self.previous.is_none() {
+            return format!("{} result(s), first run", self.results);
+        }
+        format!(
+            "{} result(s), {} new, {} removed, {} price change(s)",
+            self.results,
+            self.new.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(id: u64, brand: &str, price: f64) -> Product {
+        Product::fixture(id, price).with_brand(brand).with_category("Placas Gráficas")
+    }
+
+    fn search(extra: &str) -> SavedSearch {
+        toml::from_str(&format!(
+            "name = \"rtx\"\nquery = \"rtx 4070\"\nadded = \"2024-06-01\"\n{extra}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn filters_products() {
+        let rtx =
+            search("min_price = 500\nmax_price = 700\nbrand = \"MSI\"\ncategory = \"gráficas\"\n");
+        assert_eq!(rtx.max, 20);
+        assert_eq!(rtx.filters(), "500.00-700.00€, brand MSI, category gráficas");
+        assert!(rtx.matches(&product(1, "msi", 599.0)));
+        assert!(!rtx.matches(&product(1, "Asus", 599.0)));
+        assert!(!rtx.matches(&product(1, "MSI", 799.0)));
+        assert!(search("").matches(&product(1, "", 1.0)));
+        assert_eq!(search("").filters(), "");
+    }
+
+    #[test]
+    fn saved_searches_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("searches.toml");
+        let mut searches = SavedSearches::load(&path).unwrap();
+        searches.add(search("max_price = 700\n")).unwrap();
+        assert!(searches.add(search("")).is_err());
+        searches.save(&path).unwrap();
+
+        let mut loaded = SavedSearches::load(&path).unwrap();
+        assert_eq!(loaded, searches);
+        assert_eq!(loaded.get("rtx").unwrap().max_price, Some(700.0));
+        assert_eq!(loaded.get("gpu").unwrap_err().to_string(), "No saved search named `gpu`");
+        loaded.remove("rtx").unwrap();
+        assert!(loaded.entries.is_empty());
+    }
+
+    #[test]
+    fn diffs_runs() {
+        let date = "2024-06-01".parse().unwrap();
+        let previous = vec![product(1, "", 600.0), product(2, "", 500.0), product(3, "", 400.0)];
+        let current = [product(2, "", 450.0), product(1, "", 600.0), product(4, "", 700.0)];
+
+        let diff = SearchDiff::new("rtx", Some((date, previous)), &current);
+        assert_eq!(diff.new.iter().map(|h| h.product_id).collect::<Vec<_>>(), [4]);
+        assert_eq!(diff.removed.iter().map(|h| h.product_id).collect::<Vec<_>>(), [3]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].hit.url, "https://www.kuantokusta.pt/p/2");
+        assert!((diff.changed[0].change_pct + 10.0).abs() < 1e-9);
+        assert_eq!(diff.summary(), "3 result(s), 1 new, 1 removed, 1 price change(s)");
+
+        let first = SearchDiff::new("rtx", None, &current);
+        assert!(first.is_empty());
+        assert_eq!(first.summary(), "3 result(s), first run");
+    }
+}
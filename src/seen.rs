@@ -0,0 +1,171 @@
+//! Deals already listed by `kk deals --new-only`
+
+use crate::api::Deal;
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The best price and discount a deal was listed with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeenDeal {
+    pub price_min: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount: Option<u8>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl SeenDeal {
+    /// Whether `deal` is cheaper or more discounted than this
+    fn is_beaten_by(&self, deal: &Deal) -> bool {
+        deal.price_min < self.price_min - 0.005
+            || deal.discount().unwrap_or(0) > self.discount.unwrap_or(0)
+    }
+}
+
+/// Deals listed before, keyed by deal ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeenDeals {
+    pub deals: BTreeMap<u64, SeenDeal>,
+}
+
+impl SeenDeals {
+    /// Default seen-set location
+    pub fn default_path() -> PathBuf {
+        crate::paths::data_dir().join("seen-deals.json")
+    }
+
+    /// Load the seen-set, or an empty one if there is none yet
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid seen deals in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Save the seen-set
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Keep the deals that are new, or cheaper or more discounted than when
+    /// last listed, and remember all of them
+    ///
+    /// Deals not listed for longer than `expire` are forgotten first, so they
+    /// count as new when they come back; a zero `expire` keeps them forever.
+    pub fn retain_new(
+        &mut self,
+        deals: Vec<Deal>,
+        expire: TimeDelta,
+        now: DateTime<Utc>,
+    ) -> Vec<Deal> {
+        if expire > TimeDelta::zero() {
+            self.deals.retain(|_, seen| now - seen.last_seen <= expire);
+        }
+        deals
+            .into_iter()
+            .filter(|deal| {
+                let discount = deal.discount();
+                match self.deals.get_mut(&deal.id) {
+                    Some(seen) if !seen.is_beaten_by(deal) => {
+                        seen.last_seen = now;
+                        false
+                    }
+                    Some(seen) => {
+                        seen.price_min = seen.price_min.min(deal.price_min);
+                        seen.discount = seen.discount.max(discount);
+                        seen.last_seen = now;
+                        true
+                    }
+                    None => {
+                        let seen = SeenDeal { price_min: deal.price_min, discount, last_seen: now };
+                        self.deals.insert(deal.id, seen);
+                        true
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Badges, Tags};
+
+    fn deal(id: u64, price: f64, discount: u8) -> Deal {
+        Deal {
+            id,
+            name: format!("Deal {id}"),
+            images: vec![],
+            price_min: price,
+            total_offers: 4,
+            url: format!("/p/{id}"),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(discount), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        }
+    }
+
+    fn ids(deals: &[Deal]) -> Vec<u64> {
+        deals.iter().map(|d| d.id).collect()
+    }
+
+    #[test]
+    fn lists_each_deal_once_until_it_improves() {
+        let mut seen = SeenDeals::default();
+        let week = TimeDelta::weeks(1);
+        let t0: DateTime<Utc> = "2024-06-01T08:00:00Z".parse().unwrap();
+
+        let new = seen.retain_new(vec![deal(1, 100.0, 20), deal(2, 50.0, 30)], week, t0);
+        assert_eq!(ids(&new), [1, 2]);
+
+        // Same deals, a worse price for 2, a better price for 1 and a better discount for 3
+        let t1 = t0 + TimeDelta::days(1);
+        let deals = vec![deal(1, 90.0, 20), deal(2, 55.0, 25), deal(3, 10.0, 40)];
+        assert_eq!(ids(&seen.retain_new(deals, week, t1)), [1, 3]);
+        let deals = vec![deal(2, 50.0, 30), deal(3, 10.0, 45)];
+        assert_eq!(ids(&seen.retain_new(deals, week, t1)), [3]);
+        assert!((seen.deals[&1].price_min - 90.0).abs() < 1e-9);
+
+        // Deal 1 hasn't been listed for over a week
+        let t2 = t1 + TimeDelta::days(8);
+        assert_eq!(ids(&seen.retain_new(vec![deal(1, 90.0, 20)], week, t2)), [1]);
+        assert_eq!(seen.deals.len(), 1);
+        assert!(seen
+            .retain_new(vec![deal(1, 90.0, 20)], TimeDelta::zero(), t2 + week * 4)
+            .is_empty());
+    }
+
+    #[test]
+    fn resurfaces_deals_with_a_better_tag_discount() {
+        let mut seen = SeenDeals::default();
+        let week = TimeDelta::weeks(1);
+        let now = Utc::now();
+        let tagged = |discount| Deal {
+            badges: Badges::default(),
+            tags: Tags { discount_percentage: Some(discount), ..Tags::default() },
+            ..deal(1, 100.0, 0)
+        };
+
+        assert_eq!(ids(&seen.retain_new(vec![tagged(20)], week, now)), [1]);
+        assert_eq!(seen.deals[&1].discount, Some(20));
+        assert!(seen.retain_new(vec![tagged(20)], week, now).is_empty());
+        assert_eq!(ids(&seen.retain_new(vec![tagged(25)], week, now)), [1]);
+    }
+
+    #[test]
+    fn seen_deals_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("seen-deals.json");
+        let mut seen = SeenDeals::load(&path).unwrap();
+        seen.retain_new(vec![deal(1, 100.0, 20)], TimeDelta::weeks(1), Utc::now());
+        seen.save(&path).unwrap();
+        assert_eq!(SeenDeals::load(&path).unwrap(), seen);
+    }
+}
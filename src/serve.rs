@@ -0,0 +1,315 @@
+//! Minimal HTTP/1.1 server for `kk serve`
+//!
+//! Only `GET` and `HEAD` are supported, one request per connection; enough
+//! for feed readers and browsers on a local network.
+
+use crate::html::escape;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Longest request head accepted
+const MAX_REQUEST: usize = 8192;
+/// Time a client gets to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A response to send back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self { status: 200, content_type, body: body.into() }
+    }
+
+    pub fn not_found() -> Self {
+        Self::ok("text/plain; charset=utf-8", "Not found\n").with_status(404)
+    }
+
+    #[must_use]
+    pub const fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+/// Answers the request for a path (without the query string)
+pub type Handler = Arc<dyn Fn(String) -> ResponseFuture + Send + Sync>;
+
+/// The response a [`Handler`] eventually gives
+pub type ResponseFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+/// Accept connections on `listener` until Ctrl-C
+pub async fn serve(listener: TcpListener, handler: Handler) -> Result<()> {
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted.context("Failed to accept a connection")?;
+                let handler = Arc::clone(&handler);
+                tokio::spawn(async move {
+                    if let Err(e) = respond(stream, &handler).await {
+                        tracing::debug!("Request from {peer} failed: {e:#}");
+                    }
+                });
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Bind `addr`, with the address in the error
+pub async fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    TcpListener::bind(addr).await.with_context(|| format!("Failed to listen on {addr}"))
+}
+
+async fn respond(mut stream: TcpStream, handler: &Handler) -> Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let head = tokio::time::timeout(READ_TIMEOUT, async {
+        let mut chunk = [0; 1024];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        Ok::<_, std::io::Error>(String::from_utf8_lossy(&buf).into_owned())
+    })
+    .await
+    .context("Request timed out")??;
+
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = (parts.next().unwrap_or_default(), parts.next().unwrap_or("/"));
+    let response = match method {
+        "GET" | "HEAD" => {
+            let path = target.split(['?', '#']).next().unwrap_or("/");
+            handler(urlencoding::decode(path).map_or_else(|_| path.to_string(), Into::into)).await
+        }
+        _ => Response::ok("text/plain; charset=utf-8", "Method not allowed\n").with_status(405),
+    };
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    )
+    .into_bytes();
+    if method != "HEAD" {
+        out.extend_from_slice(&response.body);
+    }
+    stream.write_all(&out).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+const fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Serve the files under `root`; `/` lists them unless there's an `index.html`
+pub fn static_files(root: PathBuf) -> Handler {
+    let root = Arc::new(root);
+    Arc::new(move |path| {
+        let root = Arc::clone(&root);
+        Box::pin(async move { static_file(&root, &path).await })
+    })
+}
+
+async fn static_file(root: &Path, path: &str) -> Response {
+    let Ok(root) = tokio::fs::canonicalize(root).await else {
+        return Response::not_found();
+    };
+    let Some(file) = resolve(&root, path).await else {
+        return Response::not_found();
+    };
+    if is_dir(&file).await {
+        let index = file.join("index.html");
+        if tokio::fs::metadata(&index).await.is_ok_and(|m| m.is_file()) {
+            return read_file(&index).await;
+        }
+        return listing(&root, &file).await;
+    }
+    read_file(&file).await
+}
+
+/// The file for a request path, refusing anything outside `root` (which must
+/// be canonical), including through symlinks
+async fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut file = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => file.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let file = tokio::fs::canonicalize(&file).await.ok()?;
+    file.starts_with(root).then_some(file)
+}
+
+async fn is_dir(path: &Path) -> bool {
+    tokio::fs::metadata(path).await.is_ok_and(|m| m.is_dir())
+}
+
+async fn read_file(path: &Path) -> Response {
+    match tokio::fs::read(path).await {
+        Ok(body) => Response::ok(content_type(path, &body), body),
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {e}", path.display());
+            Response::not_found()
+        }
+    }
+}
+
+async fn listing(root: &Path, dir: &Path) -> Response {
+    let mut names = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !name.starts_with('.') {
+                let is_dir = entry.file_type().await.is_ok_and(|t| t.is_dir());
+                names.push((name, is_dir));
+            }
+        }
+    }
+    names.sort();
+
+    let prefix = dir.strip_prefix(root).unwrap_or(dir);
+    let (mut base, mut heading) = ("/".to_string(), "/".to_string());
+    for part in prefix {
+        let part = part.to_string_lossy();
+        let _ = write!(base, "{}/", urlencoding::encode(&part));
+        let _ = write!(heading, "{part}/");
+    }
+    let mut body = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>kk</title></head><body>\n\
+         <h1>{}</h1>\n<ul>\n",
+        escape(&heading)
+    );
+    for (name, is_dir) in names {
+        let slash = if is_dir { "/" } else { "" };
+        let _ = writeln!(
+            body,
+            "<li><a href=\"{}{}{slash}\">{}{slash}</a></li>",
+            escape(&base),
+            urlencoding::encode(&name),
+            escape(&name)
+        );
+    }
+    body.push_str("</ul>\n</body></html>\n");
+    Response::ok("text/html; charset=utf-8", body)
+}
+
+fn content_type(path: &Path, body: &[u8]) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "xml" => feed_type(body),
+        "atom" => "application/atom+xml; charset=utf-8",
+        "rss" => "application/rss+xml; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Atom or RSS for feeds written by `kk feed`, plain XML otherwise
+fn feed_type(body: &[u8]) -> &'static str {
+    let head = String::from_utf8_lossy(&body[..body.len().min(256)]);
+    if head.contains("<feed") {
+        "application/atom+xml; charset=utf-8"
+    } else if head.contains("<rss") {
+        "application/rss+xml; charset=utf-8"
+    } else {
+        "application/xml; charset=utf-8"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_inside_root_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("deals.xml"), "<feed/>").unwrap();
+        assert_eq!(resolve(&root, "/deals.xml").await, Some(root.join("deals.xml")));
+        assert_eq!(resolve(&root, "/").await, Some(root.clone()));
+        assert_eq!(resolve(&root, "/../deals.xml").await, None);
+        assert_eq!(resolve(&root, "/missing.xml").await, None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn refuses_symlinks_out_of_root() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("out")).unwrap();
+        std::os::unix::fs::symlink(root.join("deals.xml"), root.join("latest.xml")).unwrap();
+        std::fs::write(root.join("deals.xml"), "<feed/>").unwrap();
+
+        assert_eq!(resolve(&root, "/out/secret.txt").await, None);
+        assert_eq!(resolve(&root, "/latest.xml").await, Some(root.join("deals.xml")));
+    }
+
+    #[tokio::test]
+    async fn lists_directories_with_escaped_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("feeds/by tag")).unwrap();
+        std::fs::write(root.join("feeds/<b>.xml"), "<rss/>").unwrap();
+
+        let body = |r: Response| String::from_utf8(r.body).unwrap();
+        let index = body(static_file(&root, "/").await);
+        assert!(index.contains("<a href=\"/feeds/\">feeds/</a>"));
+        let feeds = body(static_file(&root, "/feeds").await);
+        assert!(feeds.contains("<h1>/feeds/</h1>"));
+        assert!(feeds.contains("<a href=\"/feeds/%3Cb%3E.xml\">&lt;b&gt;.xml</a>"));
+        assert!(feeds.contains("<a href=\"/feeds/by%20tag/\">by tag/</a>"));
+    }
+
+    #[tokio::test]
+    async fn serves_files_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("deals.xml"), "<?xml version=\"1.0\"?>\n<rss/>").unwrap();
+        let listener = bind("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, static_files(dir.path().to_path_buf())));
+
+        let response = reqwest::get(format!("{base}/deals.xml")).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "application/rss+xml; charset=utf-8");
+        assert_eq!(response.text().await.unwrap(), "<?xml version=\"1.0\"?>\n<rss/>");
+
+        let index = reqwest::get(&base).await.unwrap().text().await.unwrap();
+        assert!(index.contains("<a href=\"/deals.xml\">deals.xml</a>"));
+        let missing = reqwest::get(format!("{base}/alerts.xml")).await.unwrap();
+        assert_eq!(missing.status(), 404);
+    }
+}
@@ -0,0 +1,238 @@
+//! JSON snapshots of listed products (`--snapshot`) and diffs between them (`kk diff`)
+
+use crate::api::{web_url, Deal, Product};
+use crate::diff::{change_pct, KeyedDiff};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The products listed by one run of a command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// Command that listed the items, e.g. `search`
+    pub command: String,
+    /// Its arguments in a few words, e.g. the search query
+    #[serde(default)]
+    pub args: String,
+    pub taken_at: DateTime<Utc>,
+    pub items: Vec<SnapshotItem>,
+}
+
+/// A listed product, with the fields compared by `kk diff`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotItem {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub brand: String,
+    pub price_min: f64,
+    #[serde(default)]
+    pub total_offers: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount: Option<u8>,
+    #[serde(default)]
+    pub url: String,
+}
+
+impl From<&Product> for SnapshotItem {
+    fn from(product: &Product) -> Self {
+        Self {
+            id: product.id,
+            name: product.name.clone(),
+            brand: product.brand.clone(),
+            price_min: product.price_min,
+            total_offers: product.total_offers,
+            discount: product.discount(),
+            url: web_url(&product.url),
+        }
+    }
+}
+
+impl From<&Deal> for SnapshotItem {
+    fn from(deal: &Deal) -> Self {
+        Self {
+            id: deal.id,
+            name: deal.name.clone(),
+            brand: deal.brand.clone(),
+            price_min: deal.price_min,
+            total_offers: deal.total_offers,
+            discount: deal.discount(),
+            url: web_url(&deal.url),
+        }
+    }
+}
+
+impl Snapshot {
+    /// A snapshot of `items` taken now
+    pub fn new(command: &str, args: impl Into<String>, items: Vec<SnapshotItem>) -> Self {
+        Self { command: command.to_string(), args: args.into(), taken_at: Utc::now(), items }
+    }
+
+    /// e.g. `search "rtx 4070"`
+    pub fn source(&self) -> String {
+        if self.args.is_empty() {
+            self.command.clone()
+        } else {
+            format!("{} {}", self.command, self.args)
+        }
+    }
+
+    /// File name used when saving into a directory, e.g. `search-20240601T120000Z.json`
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.json", self.command, self.taken_at.format("%Y%m%dT%H%M%SZ"))
+    }
+
+    /// Load a snapshot file
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid snapshot in {}", path.display()))
+    }
+
+    /// Save to `path`, or to a timestamped file in it when it is a directory
+    /// (an existing one, or a path ending with `/`); returns the file written
+    pub fn save(&self, path: &Path) -> Result<PathBuf> {
+        let is_dir = path.is_dir() || path.as_os_str().to_string_lossy().ends_with('/');
+        let file = if is_dir { path.join(self.file_name()) } else { path.to_path_buf() };
+        let raw = serde_json::to_string_pretty(self).context("Failed to serialize snapshot")?;
+        crate::paths::write_atomic(&file, raw)?;
+        Ok(file)
+    }
+}
+
+/// Where a side of a diff came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub source: String,
+    pub taken_at: DateTime<Utc>,
+    pub items: usize,
+}
+
+impl From<&Snapshot> for SnapshotInfo {
+    fn from(snapshot: &Snapshot) -> Self {
+        Self { source: snapshot.source(), taken_at: snapshot.taken_at, items: snapshot.items.len() }
+    }
+}
+
+/// An item listed in both snapshots at different prices
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repriced {
+    #[serde(flatten)]
+    pub item: SnapshotItem,
+    pub old_price: f64,
+    pub delta: f64,
+    pub change_pct: f64,
+}
+
+/// Items added, removed and re-priced between two snapshots, keyed by product ID
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub from: SnapshotInfo,
+    pub to: SnapshotInfo,
+    pub added: Vec<SnapshotItem>,
+    pub removed: Vec<SnapshotItem>,
+    /// Biggest drops first
+    pub repriced: Vec<Repriced>,
+    pub unchanged: usize,
+}
+
+impl SnapshotDiff {
+    pub fn new(from: &Snapshot, to: &Snapshot) -> Self {
+        let diff = KeyedDiff::new(&from.items, &to.items, |i| i.id, |i| i.price_min);
+        let mut repriced: Vec<Repriced> = diff
+            .repriced
+            .into_iter()
+            .map(|(item, old_price)| Repriced {
+                item: item.clone(),
+                old_price,
+                delta: item.price_min - old_price,
+                change_pct: change_pct(old_price, item.price_min),
+            })
+            .collect();
+        repriced.sort_by(|a, b| a.change_pct.total_cmp(&b.change_pct));
+        Self {
+            from: from.into(),
+            to: to.into(),
+            added: diff.added.into_iter().cloned().collect(),
+            removed: diff.removed.into_iter().cloned().collect(),
+            repriced,
+            unchanged: diff.unchanged,
+        }
+    }
+
+    /// e.g. "2 added, 1 removed, 3 re-priced, 14 unchanged"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} re-priced, {} unchanged",
+            self.added.len(),
+            self.removed.len(),
+            self.repriced.len(),
+            self.unchanged
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::WEB_BASE;
+
+    fn item(id: u64, price: f64) -> SnapshotItem {
+        SnapshotItem {
+            id,
+            name: format!("Product {id}"),
+            brand: String::new(),
+            price_min: price,
+            total_offers: 3,
+            discount: None,
+            url: format!("{WEB_BASE}/p/{id}"),
+        }
+    }
+
+    #[test]
+    fn saves_into_directories_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut snapshot = Snapshot::new("search", "\"rtx 4070\"", vec![item(1, 10.0)]);
+        snapshot.taken_at = "2024-06-01T12:00:00Z".parse().unwrap();
+        assert_eq!(snapshot.source(), "search \"rtx 4070\"");
+
+        let saved = snapshot.save(dir.path()).unwrap();
+        assert_eq!(saved, dir.path().join("search-20240601T120000Z.json"));
+        assert_eq!(Snapshot::load(&saved).unwrap(), snapshot);
+
+        let nested = format!("{}/snapshots/", dir.path().display());
+        let saved = snapshot.save(Path::new(&nested)).unwrap();
+        assert_eq!(saved, dir.path().join("snapshots/search-20240601T120000Z.json"));
+        let file = dir.path().join("rtx.json");
+        assert_eq!(snapshot.save(&file).unwrap(), file);
+    }
+
+    #[test]
+    fn diffs_by_product_id() {
+        let from = Snapshot::new("deals", "", vec![item(1, 100.0), item(2, 50.0), item(3, 20.0)]);
+        let to = Snapshot::new(
+            "deals",
+            "",
+            vec![item(3, 25.0), item(4, 5.0), item(1, 80.0), item(2, 50.0)],
+        );
+        let diff = SnapshotDiff::new(&from, &to);
+
+        assert_eq!(diff.added.iter().map(|i| i.id).collect::<Vec<_>>(), [4]);
+        assert!(diff.removed.is_empty());
+        let repriced: Vec<_> = diff.repriced.iter().map(|r| (r.item.id, r.delta)).collect();
+        assert_eq!(repriced, [(1, -20.0), (3, 5.0)]);
+        assert!((diff.repriced[0].change_pct + 20.0).abs() < 1e-9);
+        assert_eq!(diff.summary(), "1 added, 0 removed, 2 re-priced, 1 unchanged");
+
+        let back = SnapshotDiff::new(&to, &from);
+        assert_eq!(back.removed.iter().map(|i| i.id).collect::<Vec<_>>(), [4]);
+        assert_eq!((back.from.items, back.to.items), (4, 3));
+    }
+}
@@ -0,0 +1,472 @@
+//! Import and export of daily price datasets (CSV, NDJSON, Parquet)
+
+use super::Store;
+use crate::api::models::deserialize_date;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rusqlite::types::ToSql;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Parquet schema of exported rows
+const PARQUET_SCHEMA: &str = "
+message price {
+    REQUIRED INT64 product_id;
+    REQUIRED INT32 date (DATE);
+    REQUIRED DOUBLE min;
+    REQUIRED DOUBLE avg;
+    OPTIONAL BYTE_ARRAY name (UTF8);
+    REQUIRED BYTE_ARRAY source (UTF8);
+}
+";
+
+/// File format of a dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DataFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl DataFormat {
+    /// Guess the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "csv" => Some(Self::Csv),
+            "ndjson" | "jsonl" | "json" => Some(Self::Ndjson),
+            "parquet" | "pq" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// One imported daily price
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ImportRow {
+    #[serde(alias = "productId", alias = "product", alias = "id")]
+    pub product_id: u64,
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
+    pub min: f64,
+    /// Defaults to `min` when missing
+    #[serde(default)]
+    pub avg: Option<f64>,
+}
+
+impl ImportRow {
+    fn validate(&self, today: NaiveDate) -> Result<(), String> {
+        if self.product_id == 0 {
+            return Err("product id must be positive".to_string());
+        }
+        if !self.min.is_finite() || self.min <= 0.0 {
+            return Err(format!("min price must be positive, got {}", self.min));
+        }
+        if let Some(avg) = self.avg {
+            if !avg.is_finite() || avg < self.min {
+                return Err(format!("avg price {avg} is below min price {}", self.min));
+            }
+        }
+        if self.date > today {
+            return Err(format!("date {} is in the future", self.date));
+        }
+        Ok(())
+    }
+}
+
+/// Rows read from a dataset, with the problems found along the way
+#[derive(Debug, Default)]
+pub struct ParsedRows {
+    pub rows: Vec<ImportRow>,
+    /// `line N: reason` for each rejected row
+    pub invalid: Vec<String>,
+}
+
+/// Read and validate a CSV or NDJSON dataset
+pub fn read_rows(path: &Path, format: DataFormat, today: NaiveDate) -> Result<ParsedRows> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut parsed = ParsedRows::default();
+    let mut accept = |line: u64, row: Result<ImportRow, String>| match row
+        .and_then(|r| r.validate(today).map(|()| r))
+    {
+        Ok(r) => parsed.rows.push(r),
+        Err(e) => parsed.invalid.push(format!("line {line}: {e}")),
+    };
+
+    match format {
+        DataFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(file);
+            for (idx, record) in reader.deserialize::<ImportRow>().enumerate() {
+                let line = record
+                    .as_ref()
+                    .err()
+                    .and_then(|e| e.position())
+                    .map_or(idx as u64 + 2, csv::Position::line);
+                accept(line, record.map_err(|e| csv_reason(&e)));
+            }
+        }
+        DataFormat::Ndjson => {
+            for (idx, line) in std::io::BufReader::new(file).lines().enumerate() {
+                let line = line.context("Failed to read dataset")?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                accept(idx as u64 + 1, serde_json::from_str(&line).map_err(|e| e.to_string()));
+            }
+        }
+        DataFormat::Parquet => {
+            anyhow::bail!("Importing Parquet is not supported; use CSV or NDJSON")
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn csv_reason(err: &csv::Error) -> String {
+    match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+        _ => err.to_string(),
+    }
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub replaced: usize,
+    /// Rows for days the store already had (kept unchanged)
+    pub skipped: usize,
+}
+
+/// Filter for exported rows
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub product_ids: Vec<u64>,
+    /// Category ID, or category name as listed on products
+    pub category: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+/// One exported daily price (same shape as the `product_prices` view)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub product_id: u64,
+    pub date: NaiveDate,
+    pub min: f64,
+    pub avg: f64,
+    pub name: Option<String>,
+    pub source: String,
+}
+
+impl Store {
+    /// Import daily prices; existing days are kept unless `replace` is set
+    pub fn import_prices(&mut self, rows: &[ImportRow], replace: bool) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let tx = self.conn.transaction()?;
+        for row in rows {
+            let avg = row.avg.unwrap_or(row.min);
+            let existing: Option<(f64, f64)> = tx
+                .query_row(
+                    "SELECT min, avg FROM price_points WHERE product_id = ?1 AND date = ?2",
+                    params![row.product_id, row.date],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .optional()?;
+
+            match existing {
+                None => {
+                    tx.execute(
+                        "INSERT INTO price_points (product_id, date, min, avg) VALUES (?1, ?2, ?3, ?4)",
+                        params![row.product_id, row.date, row.min, avg],
+                    )?;
+                    report.inserted += 1;
+                }
+                Some(_) if replace => {
+                    tx.execute(
+                        "UPDATE price_points SET min = ?3, avg = ?4, days = 1
+                         WHERE product_id = ?1 AND date = ?2",
+                        params![row.product_id, row.date, row.min, avg],
+                    )?;
+                    report.replaced += 1;
+                }
+                Some(_) => report.skipped += 1,
+            }
+        }
+        tx.commit().context("Failed to import prices")?;
+        Ok(report)
+    }
+
+    /// Daily prices matching `filter`, ordered by product and date
+    pub fn export_prices(&self, filter: &ExportFilter) -> Result<Vec<ExportRow>> {
+        let mut sql =
+            "SELECT product_id, date, min, avg, name, source FROM product_prices WHERE 1 = 1"
+                .to_string();
+        let mut args: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if !filter.product_ids.is_empty() {
+            let marks = vec!["?"; filter.product_ids.len()].join(", ");
+            sql.push_str(&format!(" AND product_id IN ({marks})"));
+            args.extend(filter.product_ids.iter().map(|&id| Box::new(id) as Box<dyn ToSql>));
+        }
+        if let Some(category) = &filter.category {
+            if let Ok(id) = category.parse::<u64>() {
+                sql.push_str(
+                    " AND product_id IN (SELECT product_id FROM category_products WHERE category_id = ?)",
+                );
+                args.push(Box::new(id));
+            } else {
+                sql.push_str(
+                    " AND product_id IN (SELECT id FROM products WHERE category = ? COLLATE NOCASE)",
+                );
+                args.push(Box::new(category.clone()));
+            }
+        }
+        if let Some(since) = filter.since {
+            sql.push_str(" AND date >= ?");
+            args.push(Box::new(since));
+        }
+        if let Some(until) = filter.until {
+            sql.push_str(" AND date <= ?");
+            args.push(Box::new(until));
+        }
+        sql.push_str(" ORDER BY product_id, date");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |r| {
+            Ok(ExportRow {
+                product_id: r.get(0)?,
+                date: r.get(1)?,
+                min: r.get(2)?,
+                avg: r.get(3)?,
+                name: r.get(4)?,
+                source: r.get(5)?,
+            })
+        })?;
+
+        rows.collect::<rusqlite::Result<_>>().context("Failed to export prices")
+    }
+}
+
+/// Write exported rows in the given format
+pub fn write_rows<W: Write + Send>(rows: &[ExportRow], format: DataFormat, out: W) -> Result<()> {
+    match format {
+        DataFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        DataFormat::Ndjson => {
+            let mut out = std::io::BufWriter::new(out);
+            for row in rows {
+                serde_json::to_writer(&mut out, row)?;
+                out.write_all(b"\n")?;
+            }
+            out.flush()?;
+        }
+        DataFormat::Parquet => write_parquet(rows, out)?,
+    }
+    Ok(())
+}
+
+fn write_parquet<W: Write + Send>(rows: &[ExportRow], out: W) -> Result<()> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(out, schema, props)?;
+
+    let epoch = NaiveDate::default();
+    let ids: Vec<i64> =
+        rows.iter().map(|r| i64::try_from(r.product_id).unwrap_or(i64::MAX)).collect();
+    let dates: Vec<i32> = rows
+        .iter()
+        .map(|r| i32::try_from((r.date - epoch).num_days()).unwrap_or(i32::MAX))
+        .collect();
+    let mins: Vec<f64> = rows.iter().map(|r| r.min).collect();
+    let avgs: Vec<f64> = rows.iter().map(|r| r.avg).collect();
+    let names: Vec<ByteArray> =
+        rows.iter().filter_map(|r| r.name.as_deref()).map(ByteArray::from).collect();
+    let name_levels: Vec<i16> = rows.iter().map(|r| i16::from(r.name.is_some())).collect();
+    let sources: Vec<ByteArray> = rows.iter().map(|r| ByteArray::from(r.source.as_str())).collect();
+
+    let mut group = writer.next_row_group()?;
+    let mut idx = 0;
+    while let Some(mut column) = group.next_column()? {
+        match idx {
+            0 => column.typed::<Int64Type>().write_batch(&ids, None, None)?,
+            1 => column.typed::<Int32Type>().write_batch(&dates, None, None)?,
+            2 => column.typed::<DoubleType>().write_batch(&mins, None, None)?,
+            3 => column.typed::<DoubleType>().write_batch(&avgs, None, None)?,
+            4 => column.typed::<ByteArrayType>().write_batch(&names, Some(&name_levels), None)?,
+            _ => column.typed::<ByteArrayType>().write_batch(&sources, None, None)?,
+        };
+        column.close()?;
+        idx += 1;
+    }
+    group.close()?;
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        "2024-06-01".parse().unwrap()
+    }
+
+    fn write(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(DataFormat::from_path(Path::new("a.CSV")), Some(DataFormat::Csv));
+        assert_eq!(DataFormat::from_path(Path::new("a.jsonl")), Some(DataFormat::Ndjson));
+        assert_eq!(DataFormat::from_path(Path::new("a.parquet")), Some(DataFormat::Parquet));
+        assert_eq!(DataFormat::from_path(Path::new("a.txt")), None);
+    }
+
+    #[test]
+    fn read_csv_validates_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            &dir,
+            "prices.csv",
+            "product_id,date,min,avg\n\
+             1,2024-01-01,10.5,12\n\
+             1,2024-01-02,9.99,\n\
+             1,01/03/2024,9,9\n\
+             2,2024-01-01,-1,\n\
+             2,2030-01-01,5,5\n\
+             3,2024-01-01,10,8\n",
+        );
+
+        let parsed = read_rows(&path, DataFormat::Csv, today()).unwrap();
+        assert_eq!(parsed.rows.len(), 2);
+        assert_eq!(parsed.rows[1].avg, None);
+        assert_eq!(parsed.invalid.len(), 4);
+        assert!(parsed.invalid[0].starts_with("line 4:"), "{}", parsed.invalid[0]);
+        assert!(parsed.invalid[2].contains("future"));
+        assert!(parsed.invalid[3].contains("below min"));
+    }
+
+    #[test]
+    fn read_ndjson_accepts_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            &dir,
+            "prices.ndjson",
+            "{\"productId\": 1, \"date\": \"2024-01-01T00:00:00\", \"min\": 10.0}\n\n{\"id\": 2}\n",
+        );
+
+        let parsed = read_rows(&path, DataFormat::Ndjson, today()).unwrap();
+        assert_eq!(parsed.rows.len(), 1);
+        assert_eq!(parsed.rows[0].date.to_string(), "2024-01-01");
+        assert_eq!(parsed.invalid.len(), 1);
+        assert!(parsed.invalid[0].starts_with("line 3:"));
+    }
+
+    #[test]
+    fn import_handles_duplicates() {
+        let mut store = Store::open_in_memory().unwrap();
+        let row = |min: f64| ImportRow {
+            product_id: 1,
+            date: "2024-01-01".parse().unwrap(),
+            min,
+            avg: None,
+        };
+
+        let report = store.import_prices(&[row(10.0), row(11.0)], false).unwrap();
+        assert_eq!(report, ImportReport { inserted: 1, replaced: 0, skipped: 1 });
+
+        let report = store.import_prices(&[row(12.0)], true).unwrap();
+        assert_eq!(report, ImportReport { inserted: 0, replaced: 1, skipped: 0 });
+        assert!((store.history(1).unwrap()[0].min - 12.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn export_filters_and_writes() {
+        let mut store = Store::open_in_memory().unwrap();
+        let rows: Vec<ImportRow> = [(1, "2024-01-01"), (1, "2024-02-01"), (2, "2024-01-01")]
+            .iter()
+            .map(|&(product_id, date)| ImportRow {
+                product_id,
+                date: date.parse().unwrap(),
+                min: 10.0,
+                avg: Some(11.0),
+            })
+            .collect();
+        store.import_prices(&rows, false).unwrap();
+
+        let filter = ExportFilter {
+            product_ids: vec![1],
+            since: Some("2024-01-15".parse().unwrap()),
+            ..ExportFilter::default()
+        };
+        let exported = store.export_prices(&filter).unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].date.to_string(), "2024-02-01");
+
+        let all = store.export_prices(&ExportFilter::default()).unwrap();
+        let mut csv = Vec::new();
+        write_rows(&all, DataFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("product_id,date,min,avg,name,source\n"));
+        assert!(csv.contains("2,2024-01-01,10.0,11.0,,history"));
+
+        let mut ndjson = Vec::new();
+        write_rows(&all, DataFormat::Ndjson, &mut ndjson).unwrap();
+        assert_eq!(String::from_utf8(ndjson).unwrap().lines().count(), 3);
+
+        let mut parquet = Vec::new();
+        write_rows(&all, DataFormat::Parquet, &mut parquet).unwrap();
+        assert_eq!(&parquet[..4], b"PAR1");
+    }
+
+    #[test]
+    fn export_filters_by_category() {
+        let mut store = Store::open_in_memory().unwrap();
+        let product = crate::api::Product {
+            id: 5,
+            name: "Phone".to_string(),
+            brand: String::new(),
+            category: "Smartphones".to_string(),
+            price_min: 100.0,
+            total_offers: 1,
+            url: String::new(),
+            images: vec![],
+            badges: crate::api::Badges::default(),
+            rating: None,
+            tags: crate::api::Tags::default(),
+        };
+        store.record_category_products(155, &[product], today()).unwrap();
+        store
+            .import_prices(
+                &[ImportRow { product_id: 6, date: today(), min: 1.0, avg: None }],
+                false,
+            )
+            .unwrap();
+
+        for category in ["155", "smartphones"] {
+            let filter =
+                ExportFilter { category: Some(category.to_string()), ..ExportFilter::default() };
+            let rows = store.export_prices(&filter).unwrap();
+            assert_eq!(rows.len(), 1, "{category}");
+            assert_eq!(rows[0].name.as_deref(), Some("Phone"));
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Retention, compaction and backups for the local store
+
+use super::Store;
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Tables with a `date` column, and the columns identifying one series in each
+///
+/// All but `alerts` are downsampled; alerts are events, so only `max_days` removes them.
+const DATED_TABLES: &[(&str, &str)] = &[
+    ("price_points", "product_id"),
+    ("product_snapshots", "product_id"),
+    ("deal_snapshots", "product_id"),
+    ("category_products", "category_id, product_id"),
+    ("search_results", "search, product_id"),
+    ("alerts", "product_id"),
+];
+
+/// How long to keep data at each resolution
+///
+/// Daily rows older than `daily_days` are reduced to one per week, and weekly
+/// rows older than `weekly_days` to one per month. Rows older than `max_days`
+/// are deleted; `0` disables a limit. Price points are aggregated (lowest min,
+/// mean avg over the days they stand for, dated at the first day with data);
+/// snapshots keep the first row of each period. Fired alerts are only deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub daily_days: Option<u32>,
+    pub weekly_days: Option<u32>,
+    pub max_days: Option<u32>,
+    /// Apply the policy automatically after each daemon cycle
+    pub auto: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { daily_days: Some(365), weekly_days: Some(3 * 365), max_days: None, auto: false }
+    }
+}
+
+/// Rows removed from each table by a prune
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PruneReport {
+    pub removed: Vec<(String, usize)>,
+}
+
+impl PruneReport {
+    pub fn total(&self) -> usize {
+        self.removed.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Row count and date coverage of one table
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStats {
+    pub name: String,
+    pub rows: u64,
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+}
+
+/// Overview of the store's contents
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbStats {
+    pub path: Option<String>,
+    pub schema_version: usize,
+    pub size_bytes: u64,
+    pub products: u64,
+    pub tables: Vec<TableStats>,
+}
+
+#[derive(Clone, Copy)]
+enum Bucket {
+    Week,
+    Month,
+}
+
+impl Bucket {
+    /// SQL expression mapping `column` to the first day of its period
+    fn start_of(self, column: &str) -> String {
+        match self {
+            Self::Week => format!("date({column}, 'weekday 0', '-6 days')"),
+            Self::Month => format!("date({column}, 'start of month')"),
+        }
+    }
+}
+
+impl Store {
+    /// Row counts, size and date coverage
+    pub fn stats(&self) -> Result<DbStats> {
+        let page_count: u64 = self.conn.pragma_query_value(None, "page_count", |r| r.get(0))?;
+        let page_size: u64 = self.conn.pragma_query_value(None, "page_size", |r| r.get(0))?;
+        let products: u64 =
+            self.conn.query_row("SELECT COUNT(*) FROM products", [], |r| r.get(0))?;
+
+        let tables = DATED_TABLES
+            .iter()
+            .map(|(table, _)| {
+                self.conn
+                    .query_row(
+                        &format!("SELECT COUNT(*), MIN(date), MAX(date) FROM {table}"),
+                        [],
+                        |r| {
+                            Ok(TableStats {
+                                name: (*table).to_string(),
+                                rows: r.get(0)?,
+                                first_date: r.get(1)?,
+                                last_date: r.get(2)?,
+                            })
+                        },
+                    )
+                    .with_context(|| format!("Failed to read stats for {table}"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DbStats {
+            path: self.path.as_ref().map(|p| p.display().to_string()),
+            schema_version: self.schema_version()?,
+            size_bytes: page_count * page_size,
+            products,
+            tables,
+        })
+    }
+
+    /// Apply a retention policy relative to `today`; with `dry_run` nothing is changed
+    pub fn prune(
+        &mut self,
+        policy: &RetentionPolicy,
+        today: NaiveDate,
+        dry_run: bool,
+    ) -> Result<PruneReport> {
+        let cutoff =
+            |days: Option<u32>| days.filter(|&d| d > 0).map(|d| today - Days::new(u64::from(d)));
+        let before = row_counts(&self.conn)?;
+
+        let tx = self.conn.transaction()?;
+        if let Some(cutoff) = cutoff(policy.max_days) {
+            for (table, _) in DATED_TABLES {
+                tx.execute(&format!("DELETE FROM {table} WHERE date < ?1"), [cutoff])?;
+            }
+        }
+        for (days, bucket) in
+            [(policy.daily_days, Bucket::Week), (policy.weekly_days, Bucket::Month)]
+        {
+            if let Some(cutoff) = cutoff(days) {
+                downsample(&tx, cutoff, bucket)?;
+            }
+        }
+
+        let after = row_counts(&tx)?;
+        if dry_run {
+            tx.rollback()?;
+        } else {
+            tx.commit().context("Failed to prune price store")?;
+        }
+
+        let removed = DATED_TABLES
+            .iter()
+            .zip(before.iter().zip(after))
+            .map(|((table, _), (b, a))| ((*table).to_string(), b.saturating_sub(a)))
+            .collect();
+        Ok(PruneReport { removed })
+    }
+
+    /// Apply the policy if it is marked automatic, logging the outcome
+    pub fn auto_prune(&mut self, policy: &RetentionPolicy, today: NaiveDate) -> Result<()> {
+        if policy.auto {
+            let report = self.prune(policy, today, false)?;
+            tracing::debug!("Retention removed {} row(s)", report.total());
+        }
+        Ok(())
+    }
+
+    /// Rebuild the database file to reclaim free space; returns `(before, after)` sizes
+    pub fn vacuum(&self) -> Result<(u64, u64)> {
+        let before = self.stats()?.size_bytes;
+        self.conn.execute_batch("VACUUM").context("Failed to vacuum price store")?;
+        Ok((before, self.stats()?.size_bytes))
+    }
+
+    /// Write a consistent, compacted copy of the database to `dest`
+    pub fn backup(&self, dest: &Path) -> Result<()> {
+        if dest.exists() {
+            anyhow::bail!("{} already exists", dest.display());
+        }
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let dest = dest.to_str().context("Backup path is not valid UTF-8")?;
+        self.conn
+            .execute("VACUUM INTO ?1", [dest])
+            .with_context(|| format!("Failed to back up price store to {dest}"))?;
+        Ok(())
+    }
+}
+
+fn row_counts(conn: &rusqlite::Connection) -> Result<Vec<usize>> {
+    DATED_TABLES
+        .iter()
+        .map(|(table, _)| {
+            let n: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0))?;
+            Ok(usize::try_from(n).unwrap_or(0))
+        })
+        .collect()
+}
+
+/// Reduce rows older than `cutoff` to one per period
+fn downsample(conn: &rusqlite::Connection, cutoff: NaiveDate, bucket: Bucket) -> Result<()> {
+    let period = bucket.start_of("date");
+    conn.execute_batch("DROP TABLE IF EXISTS temp.pruned_points")?;
+    conn.execute(
+        &format!(
+            "CREATE TEMP TABLE pruned_points AS
+             SELECT product_id, MIN(date) AS date, MIN(min) AS min,
+                    SUM(avg * days) / SUM(days) AS avg, SUM(days) AS days
+             FROM price_points WHERE date < ?1 GROUP BY product_id, {period}"
+        ),
+        [cutoff],
+    )?;
+    conn.execute("DELETE FROM price_points WHERE date < ?1", [cutoff])?;
+    conn.execute_batch(
+        "INSERT INTO price_points (product_id, date, min, avg, days)
+         SELECT product_id, date, min, avg, days FROM temp.pruned_points;
+         DROP TABLE temp.pruned_points;",
+    )?;
+
+    for (table, keys) in DATED_TABLES[1..].iter().filter(|(table, _)| *table != "alerts") {
+        let same_series: Vec<String> =
+            keys.split(", ").map(|k| format!("o.{k} = {table}.{k}")).collect();
+        conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE date < ?1 AND EXISTS (
+                     SELECT 1 FROM {table} o
+                     WHERE {} AND o.date < {table}.date AND {} = {}
+                 )",
+                same_series.join(" AND "),
+                bucket.start_of("o.date"),
+                bucket.start_of(&format!("{table}.date")),
+            ),
+            [cutoff],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{PriceHistory, PricePoint};
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn store_with_days(start: &str, days: u64) -> Store {
+        let mut store = Store::open_in_memory().unwrap();
+        let start = date(start);
+        let data = (0..days)
+            .map(|i| {
+                let min = 100.0 - i as f64;
+                PricePoint { date: start + Days::new(i), avg: min + 10.0, min }
+            })
+            .collect();
+        store.record_history(1, &PriceHistory { min_axis: 0.0, max_axis: 0.0, data }).unwrap();
+        store
+    }
+
+    #[test]
+    fn prune_downsamples_old_points() {
+        // 2024-01-01 is a Monday; 28 days = 4 full weeks
+        let mut store = store_with_days("2024-01-01", 28);
+        let policy =
+            RetentionPolicy { daily_days: Some(10), weekly_days: None, ..Default::default() };
+        let today = date("2024-02-05");
+
+        let report = store.prune(&policy, today, false).unwrap();
+        let history = store.history(1).unwrap();
+
+        // Before the cutoff (2024-01-26): 3 full weeks + Mon-Thu of week 4
+        assert_eq!(history[0].date, date("2024-01-01"));
+        assert_eq!(history[1].date, date("2024-01-08"));
+        assert!((history[0].min - 94.0).abs() < 1e-9);
+        assert!((history[0].avg - 107.0).abs() < 1e-9);
+        assert_eq!(history.len(), 4 + 3);
+        assert_eq!(report.total(), 28 - 7);
+
+        // Idempotent
+        assert_eq!(store.prune(&policy, today, false).unwrap().total(), 0);
+    }
+
+    #[test]
+    fn repeated_prunes_weight_aggregated_points() {
+        // Daily pruning that catches up on week 1 (Mon 2024-01-01) one day at a time
+        let mut store = store_with_days("2024-01-01", 7);
+        let policy =
+            RetentionPolicy { daily_days: Some(1), weekly_days: None, ..Default::default() };
+        for day in 3..=8 {
+            store.prune(&policy, date("2024-01-01") + Days::new(day), false).unwrap();
+        }
+
+        let history = store.history(1).unwrap();
+        assert_eq!(history.len(), 1);
+        // Mean of the daily avgs 110, 109, ..., 104
+        assert!((history[0].avg - 107.0).abs() < 1e-9);
+        assert!((history[0].min - 94.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn prune_monthly_and_max_age() {
+        let mut store = store_with_days("2024-01-01", 60);
+        let policy = RetentionPolicy {
+            daily_days: Some(5),
+            weekly_days: Some(20),
+            max_days: Some(50),
+            auto: false,
+        };
+        store.prune(&policy, date("2024-03-01"), false).unwrap();
+
+        let dates: Vec<String> =
+            store.history(1).unwrap().iter().map(|p| p.date.to_string()).collect();
+        assert_eq!(
+            dates,
+            [
+                "2024-01-11", // January, monthly
+                "2024-02-05", // weeks before the monthly cutoff (2024-02-10)
+                "2024-02-12",
+                "2024-02-19",
+                "2024-02-25", // daily
+                "2024-02-26",
+                "2024-02-27",
+                "2024-02-28",
+                "2024-02-29",
+            ]
+        );
+    }
+
+    #[test]
+    fn prune_dry_run_changes_nothing() {
+        let mut store = store_with_days("2024-01-01", 28);
+        let policy = RetentionPolicy { max_days: Some(1), ..Default::default() };
+        let report = store.prune(&policy, date("2024-06-01"), true).unwrap();
+
+        assert_eq!(report.total(), 28);
+        assert_eq!(store.history(1).unwrap().len(), 28);
+    }
+
+    #[test]
+    fn prune_thins_snapshots() {
+        let mut store = Store::open_in_memory().unwrap();
+        let product = crate::api::Product::fixture(1, 10.0);
+        let alerts = crate::notify::Message::test().alerts;
+        for day in ["2024-01-01", "2024-01-03", "2024-01-05", "2024-01-08"] {
+            store.record_category_products(7, std::slice::from_ref(&product), date(day)).unwrap();
+            let fired_at = format!("{day}T12:00:00Z").parse().unwrap();
+            store.record_alerts(&alerts, fired_at).unwrap();
+        }
+
+        let mut policy =
+            RetentionPolicy { daily_days: Some(1), weekly_days: None, ..Default::default() };
+        let report = store.prune(&policy, date("2024-02-01"), false).unwrap();
+
+        // Alerts are kept whole...
+        let removed: Vec<usize> = report.removed.iter().map(|(_, n)| *n).collect();
+        assert_eq!(removed, vec![0, 2, 0, 2, 0, 0]);
+
+        // ...until they pass the maximum age
+        policy.max_days = Some(1);
+        let report = store.prune(&policy, date("2024-02-01"), false).unwrap();
+        assert_eq!(report.removed[5], ("alerts".to_string(), 4));
+    }
+
+    #[test]
+    fn stats_vacuum_and_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = Store::open(&dir.path().join("prices.db")).unwrap();
+        store
+            .record_history(
+                1,
+                &PriceHistory {
+                    min_axis: 0.0,
+                    max_axis: 0.0,
+                    data: vec![PricePoint { date: date("2024-01-01"), avg: 2.0, min: 1.0 }],
+                },
+            )
+            .unwrap();
+
+        let stats = store.stats().unwrap();
+        assert_eq!(stats.tables[0].name, "price_points");
+        assert_eq!(stats.tables[0].rows, 1);
+        assert_eq!(stats.tables[0].first_date, Some(date("2024-01-01")));
+        assert_eq!(stats.tables[1].first_date, None);
+        assert!(stats.size_bytes > 0);
+
+        store.vacuum().unwrap();
+
+        let dest = dir.path().join("backup/prices.db");
+        store.backup(&dest).unwrap();
+        assert!(store.backup(&dest).is_err());
+        let copy = Store::open(&dest).unwrap();
+        assert_eq!(copy.history(1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! Schema migrations for the local price store
+//!
+//! Each entry upgrades the schema by one version; the current version is kept
+//! in `SQLite`'s `user_version` pragma. Never edit a released migration - append
+//! a new one instead.
+
+pub const MIGRATIONS: &[&str] = &[
+    // 1: products, daily prices, deals and categories
+    "
+    CREATE TABLE products (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT NOT NULL,
+        brand       TEXT NOT NULL DEFAULT '',
+        category    TEXT NOT NULL DEFAULT '',
+        url         TEXT NOT NULL DEFAULT '',
+        image       TEXT,
+        first_seen  TEXT NOT NULL,
+        last_seen   TEXT NOT NULL
+    );
+
+    CREATE TABLE price_points (
+        product_id  INTEGER NOT NULL,
+        date        TEXT NOT NULL,
+        min         REAL NOT NULL,
+        avg         REAL NOT NULL,
+        PRIMARY KEY (product_id, date)
+    );
+
+    CREATE TABLE product_snapshots (
+        product_id    INTEGER NOT NULL,
+        date          TEXT NOT NULL,
+        price_min     REAL NOT NULL,
+        total_offers  INTEGER NOT NULL,
+        discount      INTEGER,
+        rating        REAL,
+        PRIMARY KEY (product_id, date)
+    );
+
+    CREATE TABLE deal_snapshots (
+        product_id    INTEGER NOT NULL,
+        date          TEXT NOT NULL,
+        price_min     REAL NOT NULL,
+        total_offers  INTEGER NOT NULL,
+        discount      INTEGER,
+        PRIMARY KEY (product_id, date)
+    );
+
+    CREATE TABLE categories (
+        id          INTEGER PRIMARY KEY,
+        parent_id   INTEGER,
+        label       TEXT NOT NULL,
+        slug        TEXT NOT NULL,
+        url         TEXT NOT NULL DEFAULT '',
+        last_seen   TEXT NOT NULL
+    );
+
+    CREATE TABLE category_products (
+        category_id  INTEGER NOT NULL,
+        product_id   INTEGER NOT NULL,
+        date         TEXT NOT NULL,
+        rank         INTEGER NOT NULL,
+        PRIMARY KEY (category_id, product_id, date)
+    );
+    ",
+    // 2: documented views for `kk query` (see `store::query::VIEWS`)
+    "
+    CREATE VIEW product_prices AS
+    SELECT pp.product_id, p.name, p.brand, pp.date, pp.min, pp.avg, 'history' AS source
+    FROM price_points pp
+    LEFT JOIN products p ON p.id = pp.product_id
+    UNION ALL
+    SELECT s.product_id, p.name, p.brand, s.date, s.price_min, s.price_min, 'listing'
+    FROM product_snapshots s
+    LEFT JOIN products p ON p.id = s.product_id
+    WHERE NOT EXISTS (
+        SELECT 1 FROM price_points pp
+        WHERE pp.product_id = s.product_id AND pp.date = s.date
+    );
+
+    CREATE VIEW deals_seen AS
+    SELECT d.product_id, p.name, p.brand, p.url, d.date, d.price_min, d.discount,
+           d.total_offers,
+           MIN(d.date) OVER (PARTITION BY d.product_id) AS first_seen
+    FROM deal_snapshots d
+    LEFT JOIN products p ON p.id = d.product_id;
+
+    CREATE VIEW category_snapshots AS
+    SELECT cp.category_id, c.label AS category, cp.date, cp.rank, cp.product_id, p.name,
+           s.price_min
+    FROM category_products cp
+    LEFT JOIN categories c ON c.id = cp.category_id
+    LEFT JOIN products p ON p.id = cp.product_id
+    LEFT JOIN product_snapshots s ON s.product_id = cp.product_id AND s.date = cp.date;
+    ",
+    // 3: daily results of saved searches (`kk saved run`)
+    "
+    CREATE TABLE search_results (
+        search        TEXT NOT NULL,
+        date          TEXT NOT NULL,
+        product_id    INTEGER NOT NULL,
+        rank          INTEGER NOT NULL,
+        price_min     REAL NOT NULL,
+        total_offers  INTEGER NOT NULL,
+        PRIMARY KEY (search, date, product_id)
+    );
+
+    CREATE VIEW saved_search_results AS
+    SELECT r.search, r.date, r.rank, r.product_id, p.name, p.url, r.price_min, r.total_offers
+    FROM search_results r
+    LEFT JOIN products p ON p.id = r.product_id;
+    ",
+    // 4: fired alerts (`kk feed alerts`)
+    "
+    CREATE TABLE alerts (
+        id          INTEGER PRIMARY KEY,
+        date        TEXT NOT NULL,
+        fired_at    TEXT NOT NULL,
+        product_id  INTEGER NOT NULL,
+        name        TEXT NOT NULL,
+        reason      TEXT NOT NULL,
+        rule        TEXT,
+        old_price   REAL,
+        new_price   REAL NOT NULL,
+        discount    INTEGER,
+        url         TEXT NOT NULL DEFAULT ''
+    );
+
+    CREATE VIEW fired_alerts AS
+    SELECT fired_at, date, product_id, name, reason, rule, old_price, new_price, discount, url
+    FROM alerts;
+    ",
+    // 5: number of days each price point stands for, so retention can
+    // aggregate already aggregated points with the right weights
+    "
+    ALTER TABLE price_points ADD COLUMN days INTEGER NOT NULL DEFAULT 1;
+    ",
+];
@@ -0,0 +1,806 @@
+//! Local `SQLite` store of every product, deal and price point the CLI fetches
+//!
+//! Rows are deduplicated per product and day, so running the same command
+//! repeatedly keeps the latest values for that day.
+
+pub mod dataset;
+pub mod maintenance;
+mod migrations;
+pub mod query;
+
+use crate::api::{Badges, Category, Deal, PriceHistory, PricePoint, Product, Tags};
+use crate::notify::Alert;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use migrations::MIGRATIONS;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub use maintenance::{DbStats, PruneReport, RetentionPolicy};
+pub use query::{QueryResult, View, VIEWS};
+
+/// Store shared between clients and tasks
+pub type SharedStore = Arc<Mutex<Store>>;
+
+/// Local price database
+pub struct Store {
+    conn: Connection,
+    path: Option<PathBuf>,
+}
+
+/// What the store knows about a product
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredProduct {
+    pub id: u64,
+    pub name: String,
+    /// Empty when the product was only seen in deal lists
+    pub category: String,
+    /// Page path, e.g. `/p/12345/name`
+    pub url: String,
+}
+
+/// A deal with the day it was first listed and its latest price
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeenDeal {
+    pub product: StoredProduct,
+    pub price_min: f64,
+    pub discount: Option<u8>,
+    pub first_seen: NaiveDate,
+}
+
+/// A product that showed up in a saved search, with its latest price there
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchListing {
+    pub search: String,
+    pub product: StoredProduct,
+    pub price_min: f64,
+    pub first_seen: NaiveDate,
+}
+
+/// The lowest price, number of stores and discount a product was listed with
+/// on one day
+#[derive(Debug, Clone, PartialEq)]
+pub struct OfferSnapshot {
+    pub date: NaiveDate,
+    pub price_min: f64,
+    pub total_offers: u32,
+    pub discount: Option<u8>,
+}
+
+/// A fired alert as kept in the store, with the product's image
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedAlert {
+    pub fired_at: DateTime<Utc>,
+    pub alert: Alert,
+    pub image: Option<String>,
+}
+
+impl std::fmt::Debug for Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Store").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+/// Today's date in local time, used to key snapshots
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Lock a shared store, recovering from a poisoned mutex
+pub fn lock(store: &SharedStore) -> MutexGuard<'_, Store> {
+    store.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+impl Store {
+    /// Default database location (`~/.local/share/kk/prices.db`)
+    pub fn default_path() -> PathBuf {
+        crate::paths::data_dir().join("prices.db")
+    }
+
+    /// Open (or create) a store at `path` and apply pending migrations
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open price store at {}", path.display()))?;
+        conn.pragma_update(None, "journal_mode", "WAL").context("Failed to enable WAL mode")?;
+
+        Self::init(conn, Some(path.to_path_buf()))
+    }
+
+    /// Open a store that must already exist (for commands that only read or maintain it)
+    pub fn open_existing(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            anyhow::bail!(
+                "No local price database at {}; run a few kk commands first",
+                path.display()
+            );
+        }
+        Self::open(path)
+    }
+
+    /// Open a throwaway in-memory store (for testing)
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory().context("Failed to open in-memory store")?;
+        Self::init(conn, None)
+    }
+
+    fn init(conn: Connection, path: Option<PathBuf>) -> Result<Self> {
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .context("Failed to set busy timeout")?;
+
+        let mut store = Self { conn, path };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// Path of the database file (`None` for in-memory stores)
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Underlying `SQLite` connection
+    pub const fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Current schema version
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: i64 = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .context("Failed to read schema version")?;
+        Ok(usize::try_from(version).unwrap_or(0))
+    }
+
+    fn migrate(&mut self) -> Result<()> {
+        let current = self.schema_version()?;
+        if current > MIGRATIONS.len() {
+            anyhow::bail!(
+                "Price store schema v{current} is newer than this kk supports (v{}); please upgrade",
+                MIGRATIONS.len()
+            );
+        }
+
+        for (idx, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = idx + 1;
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(sql)
+                .with_context(|| format!("Failed to apply price store migration {version}"))?;
+            tx.pragma_update(None, "user_version", version)?;
+            tx.commit()?;
+            tracing::debug!("Applied price store migration {version}");
+        }
+
+        Ok(())
+    }
+
+    /// Record products returned by search/browse/popular/related
+    pub fn record_products(&mut self, products: &[Product], date: NaiveDate) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for p in products {
+            upsert_product(&tx, p.id, &p.name, &p.brand, &p.category, &p.url, &p.images, date)?;
+            tx.execute(
+                "INSERT INTO product_snapshots (product_id, date, price_min, total_offers, discount, rating)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (product_id, date) DO UPDATE SET
+                     price_min = excluded.price_min,
+                     total_offers = excluded.total_offers,
+                     discount = excluded.discount,
+                     rating = COALESCE(excluded.rating, rating)",
+                params![
+                    p.id,
+                    date,
+                    p.price_min,
+                    p.total_offers,
+                    p.discount(),
+                    p.rating.as_ref().map(|r| f64::from(r.rating_count)),
+                ],
+            )?;
+        }
+        tx.commit().context("Failed to record products")
+    }
+
+    /// Record deals
+    pub fn record_deals(&mut self, deals: &[Deal], date: NaiveDate) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for d in deals {
+            upsert_product(&tx, d.id, &d.name, &d.brand, "", &d.url, &d.images, date)?;
+            tx.execute(
+                "INSERT INTO deal_snapshots (product_id, date, price_min, total_offers, discount)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (product_id, date) DO UPDATE SET
+                     price_min = excluded.price_min,
+                     total_offers = excluded.total_offers,
+                     discount = excluded.discount",
+                params![d.id, date, d.price_min, d.total_offers, d.discount(),],
+            )?;
+        }
+        tx.commit().context("Failed to record deals")
+    }
+
+    /// Record the daily points of a price history
+    pub fn record_history(&mut self, product_id: u64, history: &PriceHistory) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for point in &history.data {
+            insert_price_point(&tx, product_id, point)?;
+        }
+        tx.commit().context("Failed to record price history")
+    }
+
+    /// Record the category tree
+    pub fn record_categories(&mut self, categories: &[Category], date: NaiveDate) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for c in categories {
+            tx.execute(
+                "INSERT INTO categories (id, parent_id, label, slug, url, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO UPDATE SET
+                     parent_id = excluded.parent_id,
+                     label = excluded.label,
+                     slug = excluded.slug,
+                     url = excluded.url,
+                     last_seen = excluded.last_seen",
+                params![c.id, c.parent_id, c.label, c.slug, c.url, date],
+            )?;
+        }
+        tx.commit().context("Failed to record categories")
+    }
+
+    /// Record the ranking of popular products in a category
+    pub fn record_category_products(
+        &mut self,
+        category_id: u64,
+        products: &[Product],
+        date: NaiveDate,
+    ) -> Result<()> {
+        self.record_products(products, date)?;
+
+        let tx = self.conn.transaction()?;
+        for (rank, p) in products.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO category_products (category_id, product_id, date, rank)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (category_id, product_id, date) DO UPDATE SET rank = excluded.rank",
+                params![category_id, p.id, date, rank + 1],
+            )?;
+        }
+        tx.commit().context("Failed to record category products")
+    }
+
+    /// Record the results of a saved search, replacing earlier results of the same day
+    pub fn record_search_results(
+        &mut self,
+        search: &str,
+        products: &[Product],
+        date: NaiveDate,
+    ) -> Result<()> {
+        self.record_products(products, date)?;
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM search_results WHERE search = ?1 AND date = ?2",
+            params![search, date],
+        )?;
+        for (rank, p) in products.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO search_results (search, date, product_id, rank, price_min, total_offers)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (search, date, product_id) DO NOTHING",
+                params![search, date, p.id, rank + 1, p.price_min, p.total_offers],
+            )?;
+        }
+        tx.commit().context("Failed to record search results")
+    }
+
+    /// The latest recorded results of a saved search and their day, best ranked first
+    pub fn search_results(&self, search: &str) -> Result<Option<(NaiveDate, Vec<Product>)>> {
+        let latest: Option<NaiveDate> = self.conn.query_row(
+            "SELECT MAX(date) FROM search_results WHERE search = ?1",
+            [search],
+            |row| row.get(0),
+        )?;
+        let Some(date) = latest else {
+            return Ok(None);
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT r.product_id, COALESCE(p.name, ''), COALESCE(p.brand, ''),
+                    COALESCE(p.category, ''), COALESCE(p.url, ''), r.price_min, r.total_offers
+             FROM search_results r
+             LEFT JOIN products p ON p.id = r.product_id
+             WHERE r.search = ?1 AND r.date = ?2
+             ORDER BY r.rank",
+        )?;
+        let products = stmt.query_map(params![search, date], |row| {
+            Ok(Product {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                brand: row.get(2)?,
+                category: row.get(3)?,
+                url: row.get(4)?,
+                price_min: row.get(5)?,
+                total_offers: row.get(6)?,
+                images: Vec::new(),
+                badges: Badges::default(),
+                rating: None,
+                tags: Tags::default(),
+            })
+        })?;
+        let products = products
+            .collect::<rusqlite::Result<_>>()
+            .with_context(|| format!("Failed to read results of `{search}`"))?;
+        Ok(Some((date, products)))
+    }
+
+    /// All locally known daily prices for a product, oldest first
+    ///
+    /// Price-history points win over list snapshots for the same day; days only
+    /// seen in product or deal lists use the listed price as both min and avg.
+    pub fn history(&self, product_id: u64) -> Result<Vec<PricePoint>> {
+        let mut by_date: BTreeMap<NaiveDate, PricePoint> = BTreeMap::new();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT date, MIN(price_min) FROM (
+                 SELECT date, price_min FROM product_snapshots WHERE product_id = ?1
+                 UNION ALL
+                 SELECT date, price_min FROM deal_snapshots WHERE product_id = ?1
+             ) GROUP BY date",
+        )?;
+        let snapshots = stmt.query_map([product_id], |row| {
+            let min: f64 = row.get(1)?;
+            Ok(PricePoint { date: row.get(0)?, avg: min, min })
+        })?;
+        for point in snapshots {
+            let point = point?;
+            by_date.insert(point.date, point);
+        }
+
+        let mut stmt =
+            self.conn.prepare("SELECT date, min, avg FROM price_points WHERE product_id = ?1")?;
+        let points = stmt.query_map([product_id], |row| {
+            Ok(PricePoint { date: row.get(0)?, min: row.get(1)?, avg: row.get(2)? })
+        })?;
+        for point in points {
+            let point = point?;
+            by_date.insert(point.date, point);
+        }
+
+        Ok(by_date.into_values().collect())
+    }
+
+    /// The daily listings of a product, newest first; product listings win
+    /// over deal listings of the same day
+    pub fn offers(&self, product_id: u64) -> Result<Vec<OfferSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT date, price_min, total_offers, discount FROM product_snapshots
+             WHERE product_id = ?1
+             UNION ALL
+             SELECT date, price_min, total_offers, discount FROM deal_snapshots d
+             WHERE product_id = ?1 AND NOT EXISTS (
+                 SELECT 1 FROM product_snapshots s
+                 WHERE s.product_id = d.product_id AND s.date = d.date
+             )
+             ORDER BY date DESC",
+        )?;
+        let offers = stmt.query_map([product_id], |row| {
+            Ok(OfferSnapshot {
+                date: row.get(0)?,
+                price_min: row.get(1)?,
+                total_offers: row.get(2)?,
+                discount: row.get(3)?,
+            })
+        })?;
+        offers
+            .collect::<rusqlite::Result<_>>()
+            .with_context(|| format!("Failed to read offers of product {product_id}"))
+    }
+
+    /// A product's name, category and page, if it was ever recorded
+    pub fn product(&self, id: u64) -> Result<Option<StoredProduct>> {
+        self.conn
+            .query_row("SELECT id, name, category, url FROM products WHERE id = ?1", [id], |row| {
+                Ok(StoredProduct {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    category: row.get(2)?,
+                    url: row.get(3)?,
+                })
+            })
+            .optional()
+            .with_context(|| format!("Failed to read product {id}"))
+    }
+
+    /// Deals first listed after `since` and up to `until`, biggest discount first
+    pub fn new_deals(&self, since: NaiveDate, until: NaiveDate) -> Result<Vec<SeenDeal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT d.product_id, COALESCE(p.name, ''), COALESCE(p.category, ''),
+                    COALESCE(p.url, ''), d.price_min, d.discount, f.first_seen
+             FROM (
+                 SELECT product_id, MIN(date) AS first_seen, MAX(date) AS last_seen
+                 FROM deal_snapshots WHERE date <= ?2 GROUP BY product_id
+             ) f
+             JOIN deal_snapshots d ON d.product_id = f.product_id AND d.date = f.last_seen
+             LEFT JOIN products p ON p.id = f.product_id
+             WHERE f.first_seen > ?1
+             ORDER BY COALESCE(d.discount, 0) DESC, d.price_min",
+        )?;
+        let deals = stmt.query_map(params![since, until], |row| {
+            Ok(SeenDeal {
+                product: StoredProduct {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    category: row.get(2)?,
+                    url: row.get(3)?,
+                },
+                price_min: row.get(4)?,
+                discount: row.get(5)?,
+                first_seen: row.get(6)?,
+            })
+        })?;
+        deals.collect::<rusqlite::Result<_>>().context("Failed to read deals")
+    }
+
+    /// Products that first showed up in a saved search after `since` and up to
+    /// `until`, leaving out each search's first run, at their latest price
+    pub fn new_search_results(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<SearchListing>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.search, f.product_id, COALESCE(p.name, ''), COALESCE(p.category, ''),
+                    COALESCE(p.url, ''), r.price_min, f.first_seen
+             FROM (
+                 SELECT search, product_id, MIN(date) AS first_seen, MAX(date) AS last_seen
+                 FROM search_results WHERE date <= ?2 GROUP BY search, product_id
+             ) f
+             JOIN (SELECT search, MIN(date) AS started FROM search_results GROUP BY search) s
+                 ON s.search = f.search
+             JOIN search_results r
+                 ON r.search = f.search AND r.product_id = f.product_id AND r.date = f.last_seen
+             LEFT JOIN products p ON p.id = f.product_id
+             WHERE f.first_seen > ?1 AND f.first_seen > s.started
+             ORDER BY f.search, r.rank",
+        )?;
+        let listings = stmt.query_map(params![since, until], |row| {
+            Ok(SearchListing {
+                search: row.get(0)?,
+                product: StoredProduct {
+                    id: row.get(1)?,
+                    name: row.get(2)?,
+                    category: row.get(3)?,
+                    url: row.get(4)?,
+                },
+                price_min: row.get(5)?,
+                first_seen: row.get(6)?,
+            })
+        })?;
+        listings.collect::<rusqlite::Result<_>>().context("Failed to read search results")
+    }
+
+    /// Keep fired alerts, for `kk feed alerts`
+    pub fn record_alerts(&mut self, alerts: &[Alert], fired_at: DateTime<Utc>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for a in alerts {
+            tx.execute(
+                "INSERT INTO alerts (date, fired_at, product_id, name, reason, rule, old_price,
+                                     new_price, discount, url)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    fired_at.with_timezone(&Local).date_naive(),
+                    fired_at,
+                    a.product_id,
+                    a.name,
+                    a.reason,
+                    a.rule,
+                    a.old_price,
+                    a.new_price,
+                    a.discount,
+                    a.url,
+                ],
+            )?;
+        }
+        tx.commit().context("Failed to record alerts")
+    }
+
+    /// The latest `limit` alerts, newest first
+    pub fn recent_alerts(&self, limit: usize) -> Result<Vec<LoggedAlert>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.fired_at, a.product_id, a.name, a.reason, a.rule, a.old_price, a.new_price,
+                    a.discount, a.url, p.image
+             FROM alerts a
+             LEFT JOIN products p ON p.id = a.product_id
+             ORDER BY a.fired_at DESC, a.id DESC
+             LIMIT ?1",
+        )?;
+        let alerts = stmt.query_map([limit], |row| {
+            Ok(LoggedAlert {
+                fired_at: row.get(0)?,
+                alert: Alert {
+                    product_id: row.get(1)?,
+                    name: row.get(2)?,
+                    reason: row.get(3)?,
+                    rule: row.get(4)?,
+                    old_price: row.get(5)?,
+                    new_price: row.get(6)?,
+                    discount: row.get(7)?,
+                    url: row.get(8)?,
+                    history: Vec::new(),
+                    sparkline: None,
+                    exec: None,
+                },
+                image: row.get(9)?,
+            })
+        })?;
+        alerts.collect::<rusqlite::Result<_>>().context("Failed to read alerts")
+    }
+}
+
+/// Merge locally stored points into an API history (API values win on the same day)
+pub fn merge_history(local: Vec<PricePoint>, api: &PriceHistory) -> PriceHistory {
+    let mut by_date: BTreeMap<NaiveDate, PricePoint> =
+        local.into_iter().map(|p| (p.date, p)).collect();
+    for point in &api.data {
+        by_date.insert(point.date, point.clone());
+    }
+
+    let data: Vec<PricePoint> = by_date.into_values().collect();
+    let min_axis = data.iter().map(|p| p.min).fold(api.min_axis, f64::min);
+    let max_axis = data.iter().map(|p| p.avg.max(p.min)).fold(api.max_axis, f64::max);
+
+    PriceHistory { min_axis, max_axis, data }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn upsert_product(
+    conn: &Connection,
+    id: u64,
+    name: &str,
+    brand: &str,
+    category: &str,
+    url: &str,
+    images: &[String],
+    date: NaiveDate,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO products (id, name, brand, category, url, image, first_seen, last_seen)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+         ON CONFLICT (id) DO UPDATE SET
+             name = excluded.name,
+             brand = CASE WHEN excluded.brand = '' THEN brand ELSE excluded.brand END,
+             category = CASE WHEN excluded.category = '' THEN category ELSE excluded.category END,
+             url = CASE WHEN excluded.url = '' THEN url ELSE excluded.url END,
+             image = COALESCE(excluded.image, image),
+             first_seen = MIN(first_seen, excluded.first_seen),
+             last_seen = MAX(last_seen, excluded.last_seen)",
+        params![id, name, brand, category, url, images.first(), date],
+    )?;
+    Ok(())
+}
+
+fn insert_price_point(conn: &Connection, product_id: u64, point: &PricePoint) -> Result<()> {
+    conn.execute(
+        "INSERT INTO price_points (product_id, date, min, avg) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (product_id, date) DO UPDATE SET min = excluded.min, avg = excluded.avg,
+             days = 1",
+        params![product_id, point.date, point.min, point.avg],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn product(id: u64, price: f64) -> Product {
+        Product::fixture(id, price).with_brand("Brand").with_category("Phones")
+    }
+
+    fn count(store: &Store, table: &str) -> i64 {
+        store.conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrations_are_applied_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/prices.db");
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+        assert_eq!(store.path(), Some(path.as_path()));
+        drop(store);
+
+        let store = Store::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", 999).unwrap();
+        drop(conn);
+
+        let err = Store::open(&path).unwrap_err();
+        assert!(err.to_string().contains("newer"));
+    }
+
+    #[test]
+    fn products_deduplicated_per_day() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.record_products(&[product(1, 100.0)], date("2024-01-01")).unwrap();
+        store.record_products(&[product(1, 90.0)], date("2024-01-01")).unwrap();
+        store.record_products(&[product(1, 80.0)], date("2024-01-02")).unwrap();
+
+        assert_eq!(count(&store, "products"), 1);
+        assert_eq!(count(&store, "product_snapshots"), 2);
+
+        let history = store.history(1).unwrap();
+        assert_eq!(history.len(), 2);
+        assert!((history[0].min - 90.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn product_metadata_keeps_known_values() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.record_products(&[product(1, 100.0)], date("2024-01-02")).unwrap();
+
+        let mut sparse = product(1, 100.0);
+        sparse.brand = String::new();
+        sparse.category = String::new();
+        store.record_products(&[sparse], date("2024-01-01")).unwrap();
+
+        let (brand, first_seen, last_seen): (String, NaiveDate, NaiveDate) = store
+            .conn
+            .query_row("SELECT brand, first_seen, last_seen FROM products WHERE id = 1", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(brand, "Brand");
+        assert_eq!(first_seen, date("2024-01-01"));
+        assert_eq!(last_seen, date("2024-01-02"));
+    }
+
+    #[test]
+    fn history_points_override_snapshots() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.record_products(&[product(1, 100.0)], date("2024-01-01")).unwrap();
+        store
+            .record_history(
+                1,
+                &PriceHistory {
+                    min_axis: 0.0,
+                    max_axis: 0.0,
+                    data: vec![PricePoint { date: date("2024-01-01"), min: 95.0, avg: 110.0 }],
+                },
+            )
+            .unwrap();
+
+        let history = store.history(1).unwrap();
+        assert_eq!(history.len(), 1);
+        assert!((history[0].avg - 110.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn deals_and_categories_recorded() {
+        let mut store = Store::open_in_memory().unwrap();
+        let deal = Deal {
+            id: 7,
+            name: "Deal".to_string(),
+            images: vec!["img.jpg".to_string()],
+            price_min: 49.0,
+            total_offers: 2,
+            url: "/p/7".to_string(),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(20), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        };
+        store.record_deals(&[deal], date("2024-01-01")).unwrap();
+        store
+            .record_category_products(
+                155,
+                &[product(1, 10.0), product(2, 20.0)],
+                date("2024-01-01"),
+            )
+            .unwrap();
+
+        assert_eq!(count(&store, "deal_snapshots"), 1);
+        assert_eq!(count(&store, "category_products"), 2);
+        assert_eq!(count(&store, "products"), 3);
+
+        assert_eq!(store.product(1).unwrap().unwrap().category, "Phones");
+        assert!(store.product(99).unwrap().is_none());
+        let deals = store.new_deals(date("2023-12-31"), date("2024-01-01")).unwrap();
+        assert_eq!(deals.len(), 1);
+        assert_eq!(deals[0].product.url, "/p/7");
+        assert_eq!(deals[0].discount, Some(20));
+        assert!(store.new_deals(date("2024-01-01"), date("2024-01-02")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn offers_by_day() {
+        let mut store = Store::open_in_memory().unwrap();
+        let deal = |price, discount| Deal {
+            id: 1,
+            name: "Product 1".to_string(),
+            images: vec![],
+            price_min: price,
+            total_offers: 2,
+            url: "/p/1".to_string(),
+            brand: String::new(),
+            badges: Badges { discount_percentage: Some(discount), ..Badges::default() },
+            rating: None,
+            tags: Tags::default(),
+        };
+        store.record_products(&[product(1, 100.0)], date("2024-01-01")).unwrap();
+        store.record_deals(&[deal(80.0, 20)], date("2024-01-01")).unwrap();
+        store.record_deals(&[deal(75.0, 25)], date("2024-01-02")).unwrap();
+
+        let offers = store.offers(1).unwrap();
+        let days: Vec<_> = offers.iter().map(|o| (o.date, o.discount)).collect();
+        assert_eq!(days, [(date("2024-01-02"), Some(25)), (date("2024-01-01"), None)]);
+        assert!(store.offers(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn alerts_recorded() {
+        let mut store = Store::open_in_memory().unwrap();
+        let mut with_image = product(1, 90.0);
+        with_image.images = vec!["https://img/1.jpg".to_string()];
+        store.record_products(&[with_image], date("2024-01-01")).unwrap();
+        let alert = |id, reason: &str| Alert {
+            product_id: id,
+            name: format!("Product {id}"),
+            reason: reason.to_string(),
+            rule: None,
+            old_price: Some(100.0),
+            new_price: 90.0,
+            discount: None,
+            url: format!("https://www.kuantokusta.pt/p/{id}"),
+            history: vec![100.0, 90.0],
+            sparkline: None,
+            exec: None,
+        };
+        let t0: DateTime<Utc> = "2024-01-01T08:00:00Z".parse().unwrap();
+        store.record_alerts(&[alert(1, "Target reached")], t0).unwrap();
+        store.record_alerts(&[alert(2, "Rule `low`")], t0 + chrono::TimeDelta::hours(1)).unwrap();
+
+        let alerts = store.recent_alerts(10).unwrap();
+        let reasons: Vec<_> = alerts.iter().map(|a| a.alert.reason.as_str()).collect();
+        assert_eq!(reasons, ["Rule `low`", "Target reached"]);
+        assert_eq!(alerts[1].fired_at, t0);
+        assert_eq!(alerts[1].image.as_deref(), Some("https://img/1.jpg"));
+        assert!(alerts[1].alert.history.is_empty());
+        assert_eq!(store.recent_alerts(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn merge_history_prefers_api() {
+        let local = vec![
+            PricePoint { date: date("2023-10-01"), min: 50.0, avg: 60.0 },
+            PricePoint { date: date("2024-01-01"), min: 1.0, avg: 1.0 },
+        ];
+        let api = PriceHistory {
+            min_axis: 80.0,
+            max_axis: 120.0,
+            data: vec![PricePoint { date: date("2024-01-01"), min: 90.0, avg: 100.0 }],
+        };
+
+        let merged = merge_history(local, &api);
+        assert_eq!(merged.data.len(), 2);
+        assert!((merged.data[1].min - 90.0).abs() < f64::EPSILON);
+        assert!((merged.min_axis - 50.0).abs() < f64::EPSILON);
+        assert!((merged.max_axis - 120.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,273 @@
+//! Read-only ad-hoc SQL over the local store
+
+use super::migrations::MIGRATIONS;
+use super::Store;
+use anyhow::{Context, Result};
+use rusqlite::limits::Limit;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// A documented, stable view for ad-hoc queries
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct View {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// `(column, description)` pairs, in column order
+    pub columns: &'static [(&'static str, &'static str)],
+}
+
+/// Views kept stable across releases; tables may change, these may only grow
+pub const VIEWS: &[View] = &[
+    View {
+        name: "product_prices",
+        description: "Daily prices per product, from price histories or (failing that) listings",
+        columns: &[
+            ("product_id", "KuantoKusta product ID"),
+            ("name", "Product name (NULL if never listed)"),
+            ("brand", "Brand"),
+            ("date", "Day, YYYY-MM-DD"),
+            ("min", "Lowest offer that day, in euros"),
+            ("avg", "Average offer that day (equals min for listings)"),
+            ("source", "'history' or 'listing'"),
+        ],
+    },
+    View {
+        name: "deals_seen",
+        description: "Every day a product showed up in the deals list",
+        columns: &[
+            ("product_id", "KuantoKusta product ID"),
+            ("name", "Product name"),
+            ("brand", "Brand"),
+            ("url", "Product page path"),
+            ("date", "Day, YYYY-MM-DD"),
+            ("price_min", "Lowest offer, in euros"),
+            ("discount", "Discount percentage (NULL if not shown)"),
+            ("total_offers", "Number of offers"),
+            ("first_seen", "First day the product was seen as a deal"),
+        ],
+    },
+    View {
+        name: "category_snapshots",
+        description: "Daily ranking of popular products per category",
+        columns: &[
+            ("category_id", "Category ID"),
+            ("category", "Category label (NULL if categories were never fetched)"),
+            ("date", "Day, YYYY-MM-DD"),
+            ("rank", "Position in the popular list, starting at 1"),
+            ("product_id", "KuantoKusta product ID"),
+            ("name", "Product name"),
+            ("price_min", "Lowest offer that day, in euros"),
+        ],
+    },
+    View {
+        name: "saved_search_results",
+        description: "Daily results of each saved search (the last run of the day)",
+        columns: &[
+            ("search", "Saved search name"),
+            ("date", "Day, YYYY-MM-DD"),
+            ("rank", "Position in the results, starting at 1"),
+            ("product_id", "KuantoKusta product ID"),
+            ("name", "Product name"),
+            ("url", "Product page path"),
+            ("price_min", "Lowest offer, in euros"),
+            ("total_offers", "Number of offers"),
+        ],
+    },
+    View {
+        name: "fired_alerts",
+        description: "Alerts fired by checks, deal scans and saved searches",
+        columns: &[
+            ("fired_at", "When it fired, RFC 3339 in UTC"),
+            ("date", "Day it fired, YYYY-MM-DD"),
+            ("product_id", "KuantoKusta product ID"),
+            ("name", "Product name"),
+            ("reason", "What fired, e.g. \"Target 650.00€ reached\""),
+            ("rule", "Label of the rule that fired, if any"),
+            ("old_price", "Price at the previous check, if it changed"),
+            ("new_price", "Price when it fired, in euros"),
+            ("discount", "Discount percentage, if any"),
+            ("url", "Product page URL"),
+        ],
+    },
+];
+
+/// Result of an ad-hoc query
+#[derive(Debug, Clone, Default)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl QueryResult {
+    /// Rows as JSON objects keyed by column name
+    pub fn records(&self) -> Vec<Map<String, Value>> {
+        self.rows
+            .iter()
+            .map(|row| self.columns.iter().cloned().zip(row.iter().cloned()).collect())
+            .collect()
+    }
+}
+
+impl Store {
+    /// Open an existing store for queries only
+    ///
+    /// The file is opened read-only and never migrated, and other databases
+    /// can't be attached to the connection.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            anyhow::bail!(
+                "No local price database at {}; run a few kk commands first",
+                path.display()
+            );
+        }
+
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let conn = Connection::open_with_flags(path, flags)
+            .with_context(|| format!("Failed to open price store at {}", path.display()))?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))
+            .context("Failed to set busy timeout")?;
+        conn.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 0);
+
+        let store = Self { conn, path: Some(path.to_path_buf()) };
+        let version = store.schema_version()?;
+        if version != MIGRATIONS.len() {
+            anyhow::bail!(
+                "Price store schema v{version} doesn't match this kk (v{}); {}",
+                MIGRATIONS.len(),
+                if version > MIGRATIONS.len() {
+                    "please upgrade"
+                } else {
+                    "run another kk command (e.g. `kk browse`) to migrate it"
+                }
+            );
+        }
+        Ok(store)
+    }
+
+    /// Run a single read-only SQL statement
+    pub fn query(&self, sql: &str) -> Result<QueryResult> {
+        let mut stmt = self.conn.prepare(sql).context("Invalid SQL")?;
+        // ATTACH and DETACH count as read-only: they change the connection, not the file
+        let keyword = sql.split_whitespace().next().unwrap_or_default();
+        if !stmt.readonly()
+            || keyword.eq_ignore_ascii_case("ATTACH")
+            || keyword.eq_ignore_ascii_case("DETACH")
+        {
+            anyhow::bail!("Only read-only statements (SELECT, WITH, ...) are allowed");
+        }
+
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = Vec::new();
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            let values = (0..columns.len())
+                .map(|i| row.get_ref(i).map(to_json))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows.push(values);
+        }
+
+        Ok(QueryResult { columns, rows })
+    }
+}
+
+fn to_json(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => Value::from(i),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => Value::from(format!("<{} bytes>", b.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{PriceHistory, PricePoint};
+
+    fn store() -> Store {
+        let mut store = Store::open_in_memory().unwrap();
+        let history = PriceHistory {
+            min_axis: 0.0,
+            max_axis: 0.0,
+            data: vec![PricePoint { date: "2024-01-01".parse().unwrap(), avg: 650.0, min: 600.0 }],
+        };
+        store.record_history(1, &history).unwrap();
+        store
+    }
+
+    #[test]
+    fn views_match_documentation() {
+        let store = store();
+        for view in VIEWS {
+            let result = store.query(&format!("SELECT * FROM {} LIMIT 0", view.name)).unwrap();
+            let documented: Vec<&str> = view.columns.iter().map(|(c, _)| *c).collect();
+            assert_eq!(result.columns, documented, "{}", view.name);
+        }
+    }
+
+    #[test]
+    fn query_returns_typed_values() {
+        let result =
+            store().query("SELECT product_id, date, min, name FROM product_prices").unwrap();
+        assert_eq!(result.columns, vec!["product_id", "date", "min", "name"]);
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0][0], Value::from(1));
+        assert_eq!(result.rows[0][1], Value::from("2024-01-01"));
+        assert_eq!(result.rows[0][2], Value::from(600.0));
+        assert_eq!(result.rows[0][3], Value::Null);
+        assert_eq!(result.records()[0]["date"], "2024-01-01");
+    }
+
+    #[test]
+    fn query_rejects_writes() {
+        let store = store();
+        let err = store.query("DELETE FROM price_points").unwrap_err();
+        assert!(err.to_string().contains("read-only"));
+        assert!(store.query("SELEC 1").is_err());
+    }
+
+    #[test]
+    fn open_read_only_requires_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.db");
+        assert!(Store::open_read_only(&path).is_err());
+
+        Store::open(&path).unwrap();
+        let store = Store::open_read_only(&path).unwrap();
+        assert!(store.connection().execute("DELETE FROM products", []).is_err());
+    }
+
+    #[test]
+    fn open_read_only_leaves_the_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.db");
+        Connection::open(&path).unwrap().execute_batch("CREATE TABLE t (x)").unwrap();
+
+        let err = Store::open_read_only(&path).unwrap_err();
+        assert!(err.to_string().contains("schema v0"));
+        let conn = Connection::open(&path).unwrap();
+        let version: i64 = conn.pragma_query_value(None, "user_version", |r| r.get(0)).unwrap();
+        assert_eq!(version, 0);
+        let mode: String = conn.pragma_query_value(None, "journal_mode", |r| r.get(0)).unwrap();
+        assert_eq!(mode, "delete");
+    }
+
+    #[test]
+    fn query_rejects_attach() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.db");
+        Store::open(&path).unwrap();
+        let store = Store::open_read_only(&path).unwrap();
+
+        let other = dir.path().join("other.db");
+        let attach = format!("ATTACH DATABASE '{}' AS other", other.display());
+        assert!(store.query(&attach).unwrap_err().to_string().contains("read-only"));
+        assert!(store.query(&format!("/* */ {attach}")).is_err());
+        assert!(store.connection().execute_batch(&attach).is_err());
+        assert!(!other.exists());
+    }
+}
@@ -0,0 +1,255 @@
+//! Persistent watchlist of products (`~/.config/kk/watchlist.toml`)
+
+use crate::rules::Rule;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A watched product
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchEntry {
+    pub id: u64,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Alert when `price_min` is at or below this price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_price: Option<f64>,
+    /// Alert when the price drops by at least this percentage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_drop: Option<f64>,
+    /// Alert rules for this product, on top of the global ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    pub added: NaiveDate,
+}
+
+impl WatchEntry {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// The watchlist file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Watchlist {
+    #[serde(default, rename = "products")]
+    pub entries: Vec<WatchEntry>,
+}
+
+/// A watched product with its current price
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+    #[serde(flatten)]
+    pub entry: WatchEntry,
+    /// Current lowest price (`None` if it couldn't be fetched)
+    pub price_min: Option<f64>,
+    /// Current number of stores selling the product
+    pub total_offers: Option<u32>,
+}
+
+impl WatchStatus {
+    /// Whether the current price is at or below the target
+    pub fn on_target(&self) -> bool {
+        matches!((self.price_min, self.entry.target_price), (Some(p), Some(t)) if p <= t)
+    }
+}
+
+/// Result of merging another watchlist into this one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+}
+
+impl Watchlist {
+    /// Default watchlist location
+    pub fn default_path() -> PathBuf {
+        crate::paths::config_dir().join("watchlist.toml")
+    }
+
+    /// Load a watchlist, or an empty one if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(raw) => toml::from_str(&raw)
+                .with_context(|| format!("Invalid watchlist in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Save the watchlist
+    pub fn save(&self, path: &Path) -> Result<()> {
+        crate::paths::write_atomic(path, self.to_toml()?)
+    }
+
+    /// Serialize to TOML
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize watchlist")
+    }
+
+    pub fn get(&self, id: u64) -> Option<&WatchEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Mutable entry, or an error naming the missing product
+    pub fn get_mut(&mut self, id: u64) -> Result<&mut WatchEntry> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.id == id)
+            .with_context(|| format!("Product {id} is not on the watchlist"))
+    }
+
+    /// Add an entry; fails if the product is already watched
+    pub fn add(&mut self, entry: WatchEntry) -> Result<()> {
+        if self.get(entry.id).is_some() {
+            anyhow::bail!("Product {} is already on the watchlist", entry.id);
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Remove an entry
+    pub fn remove(&mut self, id: u64) -> Result<WatchEntry> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|e| e.id == id)
+            .with_context(|| format!("Product {id} is not on the watchlist"))?;
+        Ok(self.entries.remove(idx))
+    }
+
+    /// Merge entries from another list; existing products are kept unless `replace` is set
+    pub fn merge(&mut self, other: Self, replace: bool) -> MergeReport {
+        let mut report = MergeReport::default();
+        for entry in other.entries {
+            match self.entries.iter_mut().find(|e| e.id == entry.id) {
+                None => {
+                    self.entries.push(entry);
+                    report.added += 1;
+                }
+                Some(existing) if replace => {
+                    *existing = entry;
+                    report.replaced += 1;
+                }
+                Some(_) => report.skipped += 1,
+            }
+        }
+        report
+    }
+
+    /// Entries carrying `tag` (all entries when `None`)
+    pub fn filtered(&self, tag: Option<&str>) -> Vec<&WatchEntry> {
+        self.entries.iter().filter(|e| tag.map_or(true, |t| e.has_tag(t))).collect()
+    }
+}
+
+/// Parse a product ID or a `KuantoKusta` product URL (`.../p/<id>/<slug>`)
+pub fn parse_product_ref(reference: &str) -> Result<u64> {
+    let reference = reference.trim();
+    if let Ok(id) = reference.parse() {
+        return Ok(id);
+    }
+
+    let path = reference.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.split('/').skip_while(|s| *s != "p").skip(1);
+    segments
+        .next()
+        .and_then(|s| s.parse().ok())
+        .with_context(|| format!("Not a product ID or KuantoKusta product URL: {reference}"))
+}
+
+/// Normalise tags: trimmed, lowercase, no duplicates
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !out.contains(&tag) {
+            out.push(tag);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64) -> WatchEntry {
+        WatchEntry {
+            id,
+            label: format!("Product {id}"),
+            tags: vec!["console".to_string()],
+            target_price: Some(400.0),
+            max_drop: None,
+            rules: vec![],
+            added: "2024-01-01".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_ids_and_urls() {
+        assert_eq!(parse_product_ref("11406755").unwrap(), 11_406_755);
+        assert_eq!(
+            parse_product_ref("https://www.kuantokusta.pt/p/11406755/sony-ps5?utm=x").unwrap(),
+            11_406_755
+        );
+        assert_eq!(parse_product_ref("/p/42").unwrap(), 42);
+        assert!(parse_product_ref("https://www.kuantokusta.pt/search?q=ps5").is_err());
+    }
+
+    #[test]
+    fn add_remove_and_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("kk/watchlist.toml");
+
+        let mut list = Watchlist::load(&path).unwrap();
+        list.add(entry(1)).unwrap();
+        list.add(entry(2)).unwrap();
+        assert!(list.add(entry(1)).is_err());
+        list.save(&path).unwrap();
+
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(raw.contains("[[products]]"));
+        assert!(!raw.contains("max_drop"));
+
+        let mut loaded = Watchlist::load(&path).unwrap();
+        assert_eq!(loaded, list);
+        assert_eq!(loaded.remove(1).unwrap().id, 1);
+        assert!(loaded.remove(1).is_err());
+        assert!(loaded.get_mut(1).is_err());
+    }
+
+    #[test]
+    fn merge_lists() {
+        let mut mine = Watchlist { entries: vec![entry(1)] };
+        let mut theirs = Watchlist { entries: vec![entry(1), entry(2)] };
+        theirs.entries[0].label = "Theirs".to_string();
+
+        let report = mine.clone().merge(theirs.clone(), false);
+        assert_eq!(report, MergeReport { added: 1, replaced: 0, skipped: 1 });
+
+        let report = mine.merge(theirs, true);
+        assert_eq!(report, MergeReport { added: 1, replaced: 1, skipped: 0 });
+        assert_eq!(mine.get(1).unwrap().label, "Theirs");
+    }
+
+    #[test]
+    fn tags_and_targets() {
+        assert_eq!(
+            normalize_tags(vec![" Console ".to_string(), "console".to_string(), String::new()]),
+            vec!["console"]
+        );
+
+        let list = Watchlist { entries: vec![entry(1), WatchEntry { tags: vec![], ..entry(2) }] };
+        assert_eq!(list.filtered(Some("CONSOLE")).len(), 1);
+        assert_eq!(list.filtered(None).len(), 2);
+
+        let status = WatchStatus { entry: entry(1), price_min: Some(399.0), total_offers: Some(3) };
+        assert!(status.on_target());
+        assert!(!WatchStatus { price_min: None, ..status }.on_target());
+    }
+}
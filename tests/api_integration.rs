@@ -232,7 +232,7 @@ fn price_point_parsing() {
     });
 
     let point: PricePoint = serde_json::from_value(json).expect("Failed to parse price point");
-    assert_eq!(point.date, "2024-06-15");
+    assert_eq!(point.date.to_string(), "2024-06-15");
     assert!((point.avg - 299.99).abs() < f64::EPSILON);
     assert!((point.min - 279.99).abs() < f64::EPSILON);
 }